@@ -0,0 +1,77 @@
+#![feature(test)]
+extern crate test;
+
+use aggregator::core::{
+    Amount, Exchange, Number, Order, OrderBook, OrderBookBids, OrderBookDiffBids, Price,
+    SummaryOrderBook, BEST_ORDER_BOOK_SIZE,
+};
+use test::{black_box, Bencher};
+
+/// xorshift so inputs are the same on every run
+struct Rng(u64);
+
+impl Rng {
+    fn next(&mut self, below: u32) -> u32 {
+        self.0 ^= self.0 << 13;
+        self.0 ^= self.0 >> 7;
+        self.0 ^= self.0 << 17;
+        (self.0 % below as u64) as u32
+    }
+}
+
+fn order(price: u32, amount: u32) -> Order {
+    Order::new(
+        Price::new(Number::from(price)).unwrap(),
+        Amount::new(Number::from(amount)).unwrap(),
+    )
+}
+
+/// up to `len` levels around the top with empty ones now and then
+fn diff(rng: &mut Rng, len: usize) -> OrderBookDiffBids {
+    let mut orders: Vec<_> = (0..len)
+        .map(|_| order(1 + rng.next(30), rng.next(4)))
+        .collect();
+    orders.sort_unstable_by_key(|order| order.price());
+    orders.dedup_by_key(|order| order.price());
+    OrderBookDiffBids::new(orders).unwrap()
+}
+
+/// full book of consecutive prices from the first one
+fn book<const QUOTE: bool>(first: u32) -> OrderBook<QUOTE, BEST_ORDER_BOOK_SIZE> {
+    OrderBook::new(
+        (first..first + BEST_ORDER_BOOK_SIZE as u32)
+            .map(|price| order(price, 1))
+            .collect(),
+    )
+    .unwrap()
+}
+
+#[bench]
+fn update_10k_diffs(b: &mut Bencher) {
+    let mut rng = Rng(0x2545_f491_4f6c_dd1d);
+    let diffs: Vec<_> = (0..10_000).map(|_| diff(&mut rng, 5)).collect();
+    let book: OrderBookBids = book(10);
+    b.iter(|| {
+        diffs
+            .iter()
+            .fold(book.clone(), |book, diff| book.update(black_box(diff)))
+    })
+}
+
+#[bench]
+fn merge_full_diffs(b: &mut Bencher) {
+    let mut rng = Rng(0x9e37_79b9_7f4a_7c15);
+    let (first, second) = (diff(&mut rng, 30), diff(&mut rng, 30));
+    b.iter(|| black_box(&first).merge(black_box(&second)))
+}
+
+#[bench]
+fn drain_summary(b: &mut Bencher) {
+    let mut summary = SummaryOrderBook::default();
+    summary.reset(Exchange::Binance, book(10), book(30));
+    summary.reset(Exchange::Kraken, book(15), book(35));
+    b.iter(|| {
+        let summary = black_box(&summary);
+        (summary.bids().count(), summary.asks().count())
+    })
+}
@@ -0,0 +1,30 @@
+#![feature(test)]
+extern crate test;
+
+use aggregator::core::{Amount, Number, Order, OrderBookBids, Price};
+use test::{black_box, Bencher};
+
+/// levels as sent by exchanges i.e. sorted from the best one
+fn levels() -> Vec<Order> {
+    (1..=20u8)
+        .rev()
+        .map(|price| {
+            Order::new(
+                Price::new(Number::from(price)).unwrap(),
+                Amount::new(Number::from(1u8)).unwrap(),
+            )
+        })
+        .collect()
+}
+
+#[bench]
+fn new(b: &mut Bencher) {
+    let levels = levels();
+    b.iter(|| OrderBookBids::new(black_box(levels.clone())))
+}
+
+#[bench]
+fn from_sorted_slice(b: &mut Bencher) {
+    let levels = levels();
+    b.iter(|| OrderBookBids::from_sorted_slice(black_box(&levels)))
+}
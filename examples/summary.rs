@@ -0,0 +1,38 @@
+//! Prints the summary of the symbol aggregated from live feeds on every update
+//!
+//! `cargo run --example summary -- BTC/USDT`
+use aggregator::{
+    core::{Exchange, SummaryOrderBook},
+    feeds::{
+        binance::{self, BookPeriod},
+        BookStream,
+    },
+    symbol::Symbol,
+};
+use futures_util::StreamExt;
+
+#[tokio::main(flavor = "current_thread")]
+async fn main() -> Result<(), Box<dyn std::error::Error>> {
+    let symbol = std::env::args()
+        .nth(1)
+        .ok_or("usage: summary BASE/QUOTE e.g. BTC/USDT")?;
+    let (base, quote) = symbol
+        .split_once('/')
+        .ok_or("symbol should be BASE/QUOTE")?;
+    let symbol = Symbol::new(base, quote);
+
+    let (binance_tx, binance_books) = BookStream::unbounded();
+    let _binance = binance::Config::default()
+        .subscribe_order_book(binance_tx, symbol.clone(), BookPeriod::Fast, None)
+        .connect()
+        .await?;
+    let mut books = binance_books.map(|update| (Exchange::Binance, update));
+
+    let mut summary = SummaryOrderBook::default();
+    while let Some((exchange, update)) = books.next().await {
+        summary.reset(exchange, update.bids, update.asks);
+        println!("{symbol}\n{summary}\n");
+    }
+    println!("feeds closed");
+    Ok(())
+}
@@ -0,0 +1,225 @@
+use std::{sync::Arc, time::Duration};
+
+use futures_util::{
+    future::ready,
+    lock::Mutex,
+    stream::{select_all, unfold},
+    Stream, StreamExt,
+};
+use tokio::{
+    sync::watch,
+    time::{interval, MissedTickBehavior},
+};
+
+use crate::core::{
+    summary::{Summary, SummaryDiff},
+    Exchange, OrderBookAsks, OrderBookBids, SummaryOrderBook,
+};
+
+/// Merges books streamed by every exchange into summaries of the symbol
+/// emitting a summary on each update changing it until all the streams end
+/// e.g. updates of levels deeper than the summary are skipped
+pub fn aggregate<S>(
+    symbol: impl Into<String>,
+    feeds: impl IntoIterator<Item = (Exchange, S)>,
+) -> impl Stream<Item = Summary>
+where
+    S: Stream<Item = (OrderBookBids, OrderBookAsks)> + Unpin,
+{
+    let symbol = symbol.into();
+    let mut book = SummaryOrderBook::default();
+    let mut previous: Option<Summary> = None;
+    select_all(
+        feeds
+            .into_iter()
+            .map(|(exchange, books)| books.map(move |(bids, asks)| (exchange, bids, asks))),
+    )
+    .filter_map(move |(exchange, bids, asks)| {
+        book.reset(exchange, bids, asks);
+        let summary = Summary::new(symbol.clone(), &book);
+        if previous.as_ref() == Some(&summary) {
+            return ready(None);
+        }
+        previous = Some(summary.clone());
+        ready(Some(summary))
+    })
+}
+
+/// Same as aggregate but emits only changes relative to the previous emission
+/// skipping updates which haven't changed the summary
+pub fn aggregate_diffs<S>(
+    symbol: impl Into<String>,
+    feeds: impl IntoIterator<Item = (Exchange, S)>,
+) -> impl Stream<Item = SummaryDiff>
+where
+    S: Stream<Item = (OrderBookBids, OrderBookAsks)> + Unpin,
+{
+    let symbol = symbol.into();
+    let previous = Summary::new(symbol.clone(), &SummaryOrderBook::default());
+    aggregate(symbol, feeds)
+        .scan(previous, |previous, summary| {
+            let diff = summary.changes_since(previous);
+            *previous = summary;
+            ready(Some(diff))
+        })
+        .filter(|diff| ready(!diff.is_empty()))
+}
+
+/// SummaryOrderBook of a symbol reset by feeds running on separate tasks
+/// clones share the book, subscribers are notified of every reset changing the summary
+#[derive(Clone)]
+pub struct SharedSummary {
+    symbol: String,
+    book: Arc<Mutex<SummaryOrderBook>>,
+    summaries: Arc<watch::Sender<Summary>>,
+}
+
+impl SharedSummary {
+    pub fn new(symbol: impl Into<String>) -> Self {
+        let symbol = symbol.into();
+        let book = SummaryOrderBook::default();
+        let (summaries, _) = watch::channel(Summary::new(symbol.clone(), &book));
+        Self {
+            symbol,
+            book: Arc::new(Mutex::new(book)),
+            summaries: Arc::new(summaries),
+        }
+    }
+    /// see SummaryOrderBook::reset
+    pub async fn reset(&self, exchange: Exchange, bids: OrderBookBids, asks: OrderBookAsks) {
+        let mut book = self.book.lock().await;
+        book.reset(exchange, bids, asks);
+        let summary = Summary::new(self.symbol.clone(), &book);
+        // sent under the lock so summaries follow the order of resets
+        self.summaries.send_if_modified(|current| {
+            let modified = *current != summary;
+            *current = summary;
+            modified
+        });
+    }
+    /// the current summary is marked as seen
+    pub fn subscribe(&self) -> watch::Receiver<Summary> {
+        self.summaries.subscribe()
+    }
+    pub fn summary(&self) -> Summary {
+        self.summaries.borrow().clone()
+    }
+}
+
+/// Emits at most one summary per interval, the latest one, dropping intermediate states
+/// spread and the rest of the summary are those of the emitted snapshot
+/// as each summary is recomputed from scratch by aggregate
+/// the first summary after a quiet period is emitted right away
+/// the pending one is flushed as soon as summaries end
+pub fn coalesce<S, T>(summaries: S, period: Duration) -> impl Stream<Item = T>
+where
+    S: Stream<Item = T> + Unpin,
+{
+    let mut ticks = interval(period);
+    ticks.set_missed_tick_behavior(MissedTickBehavior::Delay);
+    unfold(
+        (summaries.fuse(), ticks, false),
+        |(mut summaries, mut ticks, ended)| async move {
+            if ended {
+                return None;
+            }
+            let mut latest = None;
+            loop {
+                tokio::select! {
+                    biased;
+                    summary = summaries.next() => match summary {
+                        Some(summary) => latest = Some(summary),
+                        None => return latest.map(|summary| (summary, (summaries, ticks, true))),
+                    },
+                    _ = ticks.tick(), if latest.is_some() => {
+                        return latest.map(|summary| (summary, (summaries, ticks, false)));
+                    }
+                }
+            }
+        },
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn coalesce_summaries() {
+        use futures_channel::mpsc::unbounded;
+        use tokio::time::Instant;
+
+        let summary = |symbol: &str| Summary::new(symbol, &SummaryOrderBook::default());
+        let period = Duration::from_millis(100);
+        let (tx, rx) = unbounded();
+        let mut summaries = Box::pin(coalesce(rx, period));
+
+        for symbol in ["1", "2", "3"] {
+            tx.unbounded_send(summary(symbol)).unwrap();
+        }
+        let start = Instant::now();
+        assert_eq!(summaries.next().await.unwrap().symbol, "3");
+
+        for symbol in ["4", "5"] {
+            tx.unbounded_send(summary(symbol)).unwrap();
+        }
+        assert_eq!(summaries.next().await.unwrap().symbol, "5");
+        assert!(start.elapsed() >= period);
+
+        tx.unbounded_send(summary("6")).unwrap();
+        drop(tx);
+        assert_eq!(summaries.next().await.unwrap().symbol, "6");
+        assert!(summaries.next().await.is_none());
+    }
+
+    #[tokio::test]
+    async fn shared_summary() {
+        use crate::core::{Amount, Number, Order, OrderBook, Price};
+
+        let order = |price: u32| {
+            Order::new(
+                Price::new(Number::from(price)).unwrap(),
+                Amount::new(Number::from(1u32)).unwrap(),
+            )
+        };
+        let shared = SharedSummary::new("ethbtc");
+        let mut summaries = shared.subscribe();
+        assert!(summaries.borrow().bids.is_empty());
+
+        let feeds = [(Exchange::Binance, 10), (Exchange::Kraken, 20)].map(|(exchange, first)| {
+            let shared = shared.clone();
+            tokio::spawn(async move {
+                for price in first..first + 10 {
+                    let bids = OrderBook::new(vec![order(price)]).unwrap();
+                    let asks = OrderBook::new(vec![order(price + 100)]).unwrap();
+                    shared.reset(exchange, bids, asks).await;
+                    tokio::task::yield_now().await;
+                }
+            })
+        });
+        for feed in feeds {
+            feed.await.unwrap();
+        }
+
+        assert!(summaries.has_changed().unwrap());
+        let summary = summaries.borrow_and_update().clone();
+        assert_eq!(summary, shared.summary());
+        assert_eq!(summary.symbol, "ethbtc");
+        // the latest books of both exchanges
+        assert_eq!(
+            summary
+                .bids
+                .iter()
+                .map(|level| (level.exchange, level.price))
+                .collect::<Vec<_>>(),
+            [(Exchange::Kraken, 29.0), (Exchange::Binance, 19.0)]
+        );
+        assert_eq!(summary.asks[0].price, 119.0);
+
+        // resets leaving the summary as it is don't notify
+        let bids = OrderBook::new(vec![order(19)]).unwrap();
+        let asks = OrderBook::new(vec![order(119)]).unwrap();
+        shared.reset(Exchange::Binance, bids, asks).await;
+        assert!(!summaries.has_changed().unwrap());
+    }
+}
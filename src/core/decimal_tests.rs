@@ -0,0 +1,42 @@
+use rust_decimal::Decimal;
+
+use crate::core::*;
+
+#[test]
+fn invalid_price() {
+    assert!(Price::new(Decimal::ZERO).contains_err(&Decimal::ZERO));
+    assert!(Price::new(Decimal::new(-1, 1)).contains_err(&Decimal::new(-1, 1)));
+}
+
+#[test]
+fn valid_price() {
+    assert!(Price::new(Decimal::new(1, 1)).is_ok_and(|v| v.into_inner() == Decimal::new(1, 1)));
+}
+
+#[test]
+fn invalid_amount() {
+    assert!(Amount::new(Decimal::new(-1, 1)).contains_err(&Decimal::new(-1, 1)));
+}
+
+#[test]
+fn valid_amount() {
+    assert_eq!(Amount::default().into_inner(), Decimal::ZERO);
+    assert!(Amount::new(Decimal::ZERO).is_ok_and(|v| v.0 == Decimal::ZERO));
+    assert!(Amount::new(Decimal::new(1, 1)).is_ok_and(|v| v.0 == Decimal::new(1, 1)));
+}
+
+#[test]
+fn exact_spread() {
+    unsafe {
+        let bids = vec![SummaryOrder(
+            Exchange::Bitstamp,
+            Order::new_unchecked(0.3, 1.0),
+        )];
+        let asks = vec![SummaryOrder(
+            Exchange::Binance,
+            Order::new_unchecked(0.1, 1.0),
+        )];
+        let spread = SummaryOrderBook::spread(bids.into_iter(), asks.into_iter());
+        assert_eq!(spread, 0.2);
+    }
+}
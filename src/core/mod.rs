@@ -1,17 +1,101 @@
 use std::{
     cmp::{min, Ordering},
+    collections::HashMap,
     fmt::{Debug, Display},
+    hash::{Hash, Hasher},
     iter::Peekable,
+    ops::{Add, Sub},
     slice::Iter,
     sync::Arc,
 };
 
-use itertools::kmerge_by;
+use itertools::{kmerge_by, EitherOrBoth, Itertools};
+use serde::{Deserialize, Serialize};
 use strum::{EnumIter, IntoEnumIterator};
 
+use self::summary::Summary;
+use crate::symbol::Symbol;
+
+/// Inner representation of prices and amounts
+#[cfg(not(feature = "decimal"))]
+pub type Number = f64;
+/// Inner representation of prices and amounts
+#[cfg(feature = "decimal")]
+pub type Number = rust_decimal::Decimal;
+
+#[cfg(not(feature = "decimal"))]
+const ZERO: Number = 0.0;
+#[cfg(feature = "decimal")]
+const ZERO: Number = rust_decimal::Decimal::ZERO;
+
+#[cfg(not(feature = "decimal"))]
+const ONE: Number = 1.0;
+#[cfg(feature = "decimal")]
+const ONE: Number = rust_decimal::Decimal::ONE;
+
+#[cfg(not(feature = "decimal"))]
+fn to_f64(value: Number) -> f64 {
+    value
+}
+#[cfg(feature = "decimal")]
+fn to_f64(value: Number) -> f64 {
+    rust_decimal::prelude::ToPrimitive::to_f64(&value).unwrap_or(f64::NAN)
+}
+
+/// number of integer minor units e.g. satoshis at scale 8
+#[cfg(not(feature = "decimal"))]
+fn from_scaled(value: i64, scale: u32) -> Number {
+    value as f64 / 10f64.powi(scale.try_into().unwrap_or(i32::MAX))
+}
+/// number of integer minor units e.g. satoshis at scale 8
+/// scales finer than decimal holds i.e. over 28 give 0
+#[cfg(feature = "decimal")]
+fn from_scaled(value: i64, scale: u32) -> Number {
+    Number::try_new(value, scale).unwrap_or(ZERO)
+}
+
+/// rounds half away from zero to dp decimal places
+#[cfg(not(feature = "decimal"))]
+fn round_dp(value: Number, dp: u8) -> f64 {
+    let scale = 10f64.powi(dp.into());
+    (value * scale).round() / scale
+}
+/// rounds half away from zero to dp decimal places
+#[cfg(feature = "decimal")]
+fn round_dp(value: Number, dp: u8) -> f64 {
+    to_f64(value.round_dp_with_strategy(
+        dp.into(),
+        rust_decimal::RoundingStrategy::MidpointAwayFromZero,
+    ))
+}
+
+/// test inputs are f64 literals converted to Number so tests run with either of them
+#[cfg(all(test, not(feature = "decimal")))]
+pub(crate) fn n(value: f64) -> Number {
+    value
+}
+/// test inputs are f64 literals converted to Number so tests run with either of them
+/// 0.1 becomes exactly 0.1 as excess bits of the float are dropped
+#[cfg(all(test, feature = "decimal"))]
+pub(crate) fn n(value: f64) -> Number {
+    rust_decimal::prelude::FromPrimitive::from_f64(value).expect("finite test input")
+}
+
+/// hashes bits of the float so values equal but for their bits like 0 and -0 hash apart
+/// hence 0 is normalized, valid prices and amounts have no other such values e.g. NAN
+#[cfg(not(feature = "decimal"))]
+fn hash_number<H: Hasher>(value: Number, state: &mut H) {
+    let value = if value == ZERO { ZERO } else { value };
+    value.to_bits().hash(state);
+}
+#[cfg(feature = "decimal")]
+fn hash_number<H: Hasher>(value: Number, state: &mut H) {
+    value.hash(state);
+}
+
 /// A normal positive float representing valid price
-#[derive(PartialEq, PartialOrd, Copy, Clone)]
-pub struct Price(f64);
+#[derive(PartialEq, Copy, Clone)]
+pub struct Price(Number);
 
 impl Debug for Price {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
@@ -19,11 +103,30 @@ impl Debug for Price {
     }
 }
 
+impl Display for Price {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        Display::fmt(&self.0, f)
+    }
+}
+
 impl Eq for Price {}
 
+/// consistent with Eq, see hash_number
+impl Hash for Price {
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        hash_number(self.0, state);
+    }
+}
+
+impl PartialOrd for Price {
+    fn partial_cmp(&self, other: &Self) -> Option<std::cmp::Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
 impl Ord for Price {
     fn cmp(&self, other: &Self) -> std::cmp::Ordering {
-        self.partial_cmp(other).unwrap()
+        self.0.partial_cmp(&other.0).unwrap()
     }
 }
 
@@ -32,24 +135,56 @@ impl Price {
     ///
     /// Behavior is undefined if value is not normal or negative
     /// Note that 0 is subnormal
-    unsafe fn new_unchecked(value: f64) -> Self {
+    unsafe fn new_unchecked(value: Number) -> Self {
         Self(value)
     }
-    pub fn new(value: f64) -> std::result::Result<Self, f64> {
+    #[cfg(not(feature = "decimal"))]
+    pub fn new(value: Number) -> std::result::Result<Self, Number> {
         if value.is_normal() && value.is_sign_positive() {
             unsafe { Ok(Self::new_unchecked(value)) }
         } else {
             Err(value)
         }
     }
-    pub fn into_inner(&self) -> f64 {
+    #[cfg(feature = "decimal")]
+    pub fn new(value: Number) -> std::result::Result<Self, Number> {
+        if value > ZERO {
+            unsafe { Ok(Self::new_unchecked(value)) }
+        } else {
+            Err(value)
+        }
+    }
+    /// price of integer minor units e.g. cents at scale 2
+    /// returns the offending value if it isn't a valid price
+    pub fn from_scaled(value: i64, scale: u32) -> std::result::Result<Self, Number> {
+        Self::new(from_scaled(value, scale))
+    }
+    /// clamps value into min..=max, values which aren't valid prices become min
+    /// except positive infinity becoming max, panics if min > max
+    pub fn new_clamped(value: Number, min: Price, max: Price) -> Self {
+        match Self::new(value) {
+            Ok(price) => price.clamp(min, max),
+            Err(value) if to_f64(value) == f64::INFINITY => max,
+            Err(_) => min,
+        }
+    }
+    pub fn into_inner(&self) -> Number {
         self.0
     }
 }
 
+/// distance between prices e.g. spread of bid and ask
+impl Sub for Price {
+    type Output = f64;
+
+    fn sub(self, other: Price) -> Self::Output {
+        to_f64(self.0 - other.0)
+    }
+}
+
 /// A normal positive float representing valid amount
-#[derive(Default, PartialEq, PartialOrd, Copy, Clone)]
-pub struct Amount(f64);
+#[derive(Default, PartialEq, Copy, Clone)]
+pub struct Amount(Number);
 
 impl Debug for Amount {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
@@ -59,48 +194,163 @@ impl Debug for Amount {
 
 impl Display for Amount {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
-        write!(f, "{}", self.0)
+        Display::fmt(&self.0, f)
+    }
+}
+
+impl PartialOrd for Amount {
+    fn partial_cmp(&self, other: &Self) -> Option<std::cmp::Ordering> {
+        Some(self.cmp(other))
     }
 }
 
 impl Ord for Amount {
     fn cmp(&self, other: &Self) -> std::cmp::Ordering {
-        self.partial_cmp(other).unwrap()
+        self.0.partial_cmp(&other.0).unwrap()
     }
 }
 
 impl Eq for Amount {}
 
+/// consistent with Eq, see hash_number
+impl Hash for Amount {
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        hash_number(self.0, state);
+    }
+}
+
 impl Amount {
     /// # Safety
     ///
     /// Behavior is undefined if value is not a normal float (except 0) or negative
-    unsafe fn new_unchecked(value: f64) -> Self {
+    unsafe fn new_unchecked(value: Number) -> Self {
         Self(value)
     }
-    pub fn new(value: f64) -> std::result::Result<Self, f64> {
+    #[cfg(not(feature = "decimal"))]
+    pub fn new(value: Number) -> std::result::Result<Self, Number> {
         match value.classify() {
             std::num::FpCategory::Nan
             | std::num::FpCategory::Infinite
             | std::num::FpCategory::Subnormal => Err(value),
-            _ if value < 0.0 => Err(value),
+            _ if value < ZERO => Err(value),
             _ => unsafe { Ok(Self::new_unchecked(value)) },
         }
     }
-    pub fn into_inner(&self) -> f64 {
+    #[cfg(feature = "decimal")]
+    pub fn new(value: Number) -> std::result::Result<Self, Number> {
+        if value < ZERO {
+            Err(value)
+        } else {
+            unsafe { Ok(Self::new_unchecked(value)) }
+        }
+    }
+    /// amount of integer minor units e.g. satoshis at scale 8
+    /// returns the offending value if it isn't a valid amount
+    pub fn from_scaled(value: i64, scale: u32) -> std::result::Result<Self, Number> {
+        Self::new(from_scaled(value, scale))
+    }
+    /// caps value at max, values which aren't valid amounts become 0
+    /// except positive infinity becoming max
+    pub fn new_clamped(value: Number, max: Amount) -> Self {
+        match Self::new(value) {
+            Ok(amount) => amount.min(max),
+            Err(value) if to_f64(value) == f64::INFINITY => max,
+            Err(_) => Self::default(),
+        }
+    }
+    pub fn into_inner(&self) -> Number {
         self.0
     }
+    /// returns the offending sum if it isn't a valid amount (e.g. overflow)
+    pub fn checked_add(&self, other: Amount) -> std::result::Result<Amount, Number> {
+        Amount::new(self.0 + other.0)
+    }
+    /// returns the offending difference if it isn't a valid amount (e.g. negative)
+    pub fn checked_sub(&self, other: Amount) -> std::result::Result<Amount, Number> {
+        Amount::new(self.0 - other.0)
+    }
+    /// amount in quote currency converted into base one at the price
+    pub fn to_base(&self, price: Price) -> std::result::Result<Amount, Number> {
+        Amount::new(self.0 / price.0)
+    }
+    /// amount in base currency converted into quote one at the price
+    pub fn to_quote(&self, price: Price) -> std::result::Result<Amount, Number> {
+        Amount::new(self.0 * price.0)
+    }
 }
 
-#[derive(Eq, PartialEq, Copy, Clone)]
-pub struct Order(Price, Amount);
+impl Add for Amount {
+    type Output = std::result::Result<Amount, Number>;
+
+    fn add(self, other: Amount) -> Self::Output {
+        self.checked_add(other)
+    }
+}
+
+impl Sub for Amount {
+    type Output = std::result::Result<Amount, Number>;
+
+    fn sub(self, other: Amount) -> Self::Output {
+        self.checked_sub(other)
+    }
+}
+
+/// Currency the amount of an order is in
+/// some venues report sizes in quote currency i.e. as notional
+#[derive(Debug, Default, Eq, PartialEq, Hash, Copy, Clone)]
+pub enum AmountDenomination {
+    #[default]
+    Base,
+    Quote,
+}
+
+#[derive(Eq, PartialEq, Hash, Copy, Clone)]
+pub struct Order(Price, Amount, AmountDenomination);
+
+/// Fields of an order to destructure by name
+#[derive(Debug, Eq, PartialEq, Copy, Clone)]
+pub struct OrderParts {
+    pub price: Price,
+    pub amount: Amount,
+    pub denomination: AmountDenomination,
+}
+
+impl From<Order> for OrderParts {
+    fn from(order: Order) -> Self {
+        Self {
+            price: order.0,
+            amount: order.1,
+            denomination: order.2,
+        }
+    }
+}
 
 impl Order {
+    /// see n
+    #[cfg(test)]
     unsafe fn new_unchecked(price: f64, amount: f64) -> Self {
-        unsafe { Self(Price::new_unchecked(price), Amount::new_unchecked(amount)) }
+        unsafe {
+            Self(
+                Price::new_unchecked(n(price)),
+                Amount::new_unchecked(n(amount)),
+                AmountDenomination::Base,
+            )
+        }
     }
+    /// amount is in base currency, see with_denomination
     pub fn new(price: Price, amount: Amount) -> Self {
-        Self(price, amount)
+        Self(price, amount, AmountDenomination::Base)
+    }
+    /// the same order with amount reported in the denomination, the amount isn't converted
+    pub fn with_denomination(self, denomination: AmountDenomination) -> Self {
+        Self(self.0, self.1, denomination)
+    }
+    /// validates raw price and amount reporting the first invalid one
+    pub fn try_new(price: Number, amount: Number) -> std::result::Result<Self, OrderError> {
+        Ok(Self::new(
+            Price::new(price).map_err(OrderError::InvalidPrice)?,
+            Amount::new(amount).map_err(OrderError::InvalidAmount)?,
+        ))
     }
     pub fn price(&self) -> Price {
         self.0
@@ -108,11 +358,86 @@ impl Order {
     pub fn amount(&self) -> Amount {
         self.1
     }
+    pub fn denomination(&self) -> AmountDenomination {
+        self.2
+    }
+    /// price and amount, the amount is in denomination of the order
+    pub fn into_parts(self) -> (Price, Amount) {
+        (self.0, self.1)
+    }
     pub fn is_empty(&self) -> bool {
-        self.1 .0 == 0.0
+        self.1 .0 == ZERO
     }
     pub fn empty(&self) -> Self {
-        Self(self.price(), Amount::default())
+        Self(self.price(), Amount::default(), self.2)
+    }
+    /// notional of the order i.e. price * amount in base currency
+    pub fn value(&self) -> f64 {
+        match self.2 {
+            AmountDenomination::Base => to_f64(self.0 .0 * self.1 .0),
+            AmountDenomination::Quote => to_f64(self.1 .0),
+        }
+    }
+    /// the same order with amount in base currency
+    /// returns the offending amount if it isn't a valid one
+    pub fn to_base(&self) -> std::result::Result<Self, Number> {
+        match self.2 {
+            AmountDenomination::Base => Ok(*self),
+            AmountDenomination::Quote => Ok(Self::new(self.0, self.1.to_base(self.0)?)),
+        }
+    }
+    /// the same order with amount in quote currency
+    /// returns the offending amount if it isn't a valid one
+    pub fn to_quote(&self) -> std::result::Result<Self, Number> {
+        match self.2 {
+            AmountDenomination::Base => Ok(Self(
+                self.0,
+                self.1.to_quote(self.0)?,
+                AmountDenomination::Quote,
+            )),
+            AmountDenomination::Quote => Ok(*self),
+        }
+    }
+    /// order to submit with price rounded to the nearest multiple of tick
+    /// and amount rounded to the nearest multiple of lot, 0 lot leaves amount as is
+    pub fn conform(
+        price: Number,
+        amount: Number,
+        tick: Price,
+        lot: Amount,
+    ) -> std::result::Result<Self, OrderError> {
+        let price =
+            Price::new((price / tick.0).round() * tick.0).map_err(OrderError::InvalidPrice)?;
+        let amount = if lot.0 == ZERO {
+            amount
+        } else {
+            (amount / lot.0).round() * lot.0
+        };
+        let amount = Amount::new(amount).map_err(OrderError::InvalidAmount)?;
+        if amount.0 == ZERO {
+            return Err(OrderError::EmptyAmount);
+        }
+        Ok(Self::new(price, amount))
+    }
+}
+
+#[derive(Debug, PartialEq, Clone, Copy)]
+pub enum OrderError {
+    /// Price isn't positive e.g. after rounding to tick
+    InvalidPrice(Number),
+    /// Amount is negative or not a number
+    InvalidAmount(Number),
+    /// Amount is below half of the lot, there is nothing to submit
+    EmptyAmount,
+}
+
+impl Display for OrderError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            OrderError::InvalidPrice(price) => write!(f, "order has invalid price {price}"),
+            OrderError::InvalidAmount(amount) => write!(f, "order has invalid amount {amount}"),
+            OrderError::EmptyAmount => f.write_str("order amount rounds to 0"),
+        }
     }
 }
 
@@ -131,9 +456,22 @@ pub struct OrderBookDiff<const QUOTE: bool>(Vec<Order>);
 
 /// Properly sorted fixed size vector of unique non empty orders
 /// Note that OrderBook is a valid OrderBookDiff
-#[derive(Default, Eq, PartialEq, Clone)]
+/// books are equal if their best COUNT levels are, see deep_eq
+#[derive(Default, Clone)]
 pub struct OrderBook<const QUOTE: bool, const COUNT: usize>(OrderBookDiff<QUOTE>);
 
+impl<const QUOTE: bool, const COUNT: usize> PartialEq for OrderBook<QUOTE, COUNT> {
+    fn eq(&self, other: &Self) -> bool {
+        self.0
+             .0
+            .iter()
+            .take(COUNT)
+            .eq(other.0 .0.iter().take(COUNT))
+    }
+}
+
+impl<const QUOTE: bool, const COUNT: usize> Eq for OrderBook<QUOTE, COUNT> {}
+
 impl<const QUOTE: bool> Debug for OrderBookDiff<QUOTE> {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
         let quote = quote_to_str::<QUOTE>();
@@ -148,16 +486,52 @@ impl<const QUOTE: bool, const COUNT: usize> Debug for OrderBook<QUOTE, COUNT> {
     }
 }
 
+/// width of a column of the ladders rendered by Display
+const LADDER_WIDTH: usize = 12;
+
+/// renders price/amount ladder with the best order first
+impl<const QUOTE: bool> Display for OrderBookDiff<QUOTE> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let quote = quote_to_str::<QUOTE>();
+        write!(f, "{quote:>LADDER_WIDTH$} {:>LADDER_WIDTH$}", "amount")?;
+        for order in &self.0 {
+            write!(
+                f,
+                "\n{:>LADDER_WIDTH$} {:>LADDER_WIDTH$}",
+                order.price(),
+                order.amount()
+            )?;
+        }
+        Ok(())
+    }
+}
+
+impl<const QUOTE: bool, const COUNT: usize> Display for OrderBook<QUOTE, COUNT> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        Display::fmt(&self.0, f)
+    }
+}
+
 struct Merger<'a, const QUOTE: bool> {
     book: Peekable<Iter<'a, Order>>,
     diff: Peekable<Iter<'a, Order>>,
+    /// prices closer than epsilon are equal
+    epsilon: Number,
 }
 
 impl<'a, const QUOTE: bool> Merger<'a, QUOTE> {
     fn new(book: &'a OrderBookDiff<QUOTE>, diff: &'a OrderBookDiff<QUOTE>) -> Self {
+        Self::with_epsilon(book, diff, ZERO)
+    }
+    fn with_epsilon(
+        book: &'a OrderBookDiff<QUOTE>,
+        diff: &'a OrderBookDiff<QUOTE>,
+        epsilon: Number,
+    ) -> Self {
         Self {
             book: book.0.iter().peekable(),
             diff: diff.0.iter().peekable(),
+            epsilon,
         }
     }
 }
@@ -167,6 +541,9 @@ impl<'a, const QUOTE: bool> Iterator for Merger<'a, QUOTE> {
 
     fn next(&mut self) -> Option<Self::Item> {
         let which = match (self.book.peek(), self.diff.peek()) {
+            (Some(b), Some(d)) if (b.price().0 - d.price().0).abs() < self.epsilon => {
+                Ordering::Equal
+            }
             (Some(b), Some(d)) => order_comparator::<QUOTE>()(b, d),
             (Some(_), None) => Ordering::Less,
             (None, Some(_)) => Ordering::Greater,
@@ -199,6 +576,85 @@ const fn order_partial_comparator<const QUOTE: bool>(
     }
 }
 
+/// sorts orders according to QUOTE unless they already are
+/// and merges orders with equal prices by summing their amounts
+/// returns the offending sum if it isn't a valid amount
+fn coalesce<const QUOTE: bool>(mut orders: Vec<Order>) -> std::result::Result<Vec<Order>, Number> {
+    if !orders.is_sorted_by(order_partial_comparator::<QUOTE>()) {
+        orders.sort_by(order_comparator::<QUOTE>());
+    }
+    let mut coalesced: Vec<Order> = Vec::with_capacity(orders.len());
+    for order in orders {
+        match coalesced.last_mut() {
+            Some(last) if last.price() == order.price() => {
+                last.1 = last.amount().checked_add(order.amount())?;
+            }
+            _ => coalesced.push(order),
+        }
+    }
+    Ok(coalesced)
+}
+
+/// maps prices of the book through 1/price which turns asks into bids and vice versa
+/// orders whose inverted price isn't a valid price are dropped
+fn invert<const QUOTE: bool, const INVERTED: bool, const COUNT: usize>(
+    book: &OrderBook<QUOTE, COUNT>,
+) -> OrderBook<INVERTED, COUNT> {
+    debug_assert_ne!(QUOTE, INVERTED);
+    let orders = book
+        .0
+         .0
+        .iter()
+        .filter_map(|order| {
+            Price::new(ONE / order.price().0)
+                .ok()
+                .map(|price| Order(price, order.amount(), order.denomination()))
+        })
+        .collect();
+    // 1/price is strictly decreasing so orders remain unique and properly sorted
+    OrderBook(OrderBookDiff(orders))
+}
+
+/// yields price of each order with running total of amounts
+/// stops early if the total isn't a valid amount anymore
+fn cumulative(orders: impl Iterator<Item = Order>) -> impl Iterator<Item = (Price, Amount)> {
+    orders.scan(Amount::default(), |total, order| {
+        *total = total.checked_add(order.amount()).ok()?;
+        Some((order.price(), *total))
+    })
+}
+
+/// CRC32 of levels in the format Kraken documents for its book checksum
+/// the price then the amount of every level, each as the exchange has sent it
+/// with the decimal point and leading zeros removed, all written without separators
+/// e.g. levels ("0.05005", "0.00000500") and ("0.05010", "0.00000500") hash "50055005010500"
+/// the text is needed as parsed numbers lose trailing zeros the exchange hashes
+pub fn checksum<'a>(levels: impl IntoIterator<Item = (&'a str, &'a str)>) -> u32 {
+    let mut hasher = crc32fast::Hasher::new();
+    for (price, amount) in levels {
+        for value in [price, amount] {
+            hasher.update(value.replace('.', "").trim_start_matches('0').as_bytes());
+        }
+    }
+    hasher.finalize()
+}
+
+/// index of the first order with the same price as the previous one
+fn not_unique(orders: &[Order]) -> Option<usize> {
+    orders
+        .windows(2)
+        .position(|pair| pair[0].price() == pair[1].price())
+        .map(|index| index + 1)
+}
+
+/// index of the first order which should have been before the previous one
+fn not_sorted<const QUOTE: bool>(orders: &[Order]) -> Option<usize> {
+    orders
+        .windows(2)
+        .position(|pair| order_comparator::<QUOTE>()(&pair[0], &pair[1]) == Ordering::Greater)
+        .map(|index| index + 1)
+}
+
 const fn quote_to_str<const QUOTE: bool>() -> &'static str {
     match QUOTE {
         ASK => "ask",
@@ -206,28 +662,47 @@ const fn quote_to_str<const QUOTE: bool>() -> &'static str {
     }
 }
 
-#[derive(Eq, PartialEq, Ord, PartialOrd, Clone, Copy)]
+#[derive(Debug, Eq, PartialEq, Ord, PartialOrd, Clone, Copy)]
 pub enum OrderBookError {
     /// We are not allowed neither merge nor peek one. Something wrong with the feed data
-    HasOrderWithNotUniquePrice,
+    /// index is of the second order with the price
+    HasOrderWithNotUniquePrice { price: Price, index: usize },
     /// Likely diff was used instead of snapshot for initialization by mistake
-    HasOrderWithEmptyAmount,
+    HasOrderWithEmptyAmount { price: Price, index: usize },
     /// Likely asks used instead of bids or the other way around by mistake
-    OrdersNotSortedAccordingToQuoteType,
+    /// index is of the first order out of place
+    OrdersNotSortedAccordingToQuoteType { price: Price, index: usize },
+    /// Locally maintained book diverged from the feed, likely a diff was dropped
+    ChecksumMismatch,
+    /// Best bid of a single exchange isn't below its best ask, the exchange sent bad data
+    Crossed { bid: Price, ask: Price },
+    /// Amounts of orders with the price summed to an invalid amount, see DuplicatePolicy::Sum
+    InvalidSum { price: Price },
 }
 
 impl Display for OrderBookError {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
-        let str = match self {
-            OrderBookError::HasOrderWithNotUniquePrice => {
-                "order book has multiple orders with the same price"
+        match self {
+            OrderBookError::HasOrderWithNotUniquePrice { price, index } => write!(
+                f,
+                "order book has multiple orders with price {price}, the second one at {index}"
+            ),
+            OrderBookError::HasOrderWithEmptyAmount { price, index } => write!(
+                f,
+                "order book has order with 0 amount at price {price} at {index}"
+            ),
+            OrderBookError::OrdersNotSortedAccordingToQuoteType { price, index } => write!(
+                f,
+                "order book is not properly sorted, order with price {price} at {index} is out of place"
+            ),
+            OrderBookError::ChecksumMismatch => f.write_str("order book checksum doesn't match"),
+            OrderBookError::Crossed { bid, ask } => {
+                write!(f, "order book is crossed, best bid {bid} isn't below best ask {ask}")
             }
-            OrderBookError::HasOrderWithEmptyAmount => "order book has order with 0 amount",
-            OrderBookError::OrdersNotSortedAccordingToQuoteType => {
-                "order book is not properly sorted"
+            OrderBookError::InvalidSum { price } => {
+                write!(f, "order book amounts at price {price} sum to an invalid amount")
             }
-        };
-        f.write_str(str)
+        }
     }
 }
 
@@ -237,6 +712,8 @@ impl<const QUOTE: bool, const COUNT: usize> OrderBook<QUOTE, COUNT> {
     /// Behavior is undefined if orders are not unique or empty or not sorted according to QUOTE
     unsafe fn new_unchecked(mut orders: Vec<Order>) -> Self {
         orders.truncate(COUNT);
+        // snapshots may be much deeper than the book
+        orders.shrink_to(COUNT);
         Self(OrderBookDiff::new_unchecked(orders))
     }
     /// # Safety
@@ -249,57 +726,198 @@ impl<const QUOTE: bool, const COUNT: usize> OrderBook<QUOTE, COUNT> {
         // because diff may have no empty orders. On the other hand
         // checking it in runtime sooner or later will return an error
         // if diff was used instead of snapshot for initialization by mistake
-        let (is_unique, is_empty) = orders
-            .split_first()
-            .map(|(first, rest)| {
-                let (unique, empty, _) = rest.iter().take(COUNT).fold(
-                    (true, first.is_empty(), first.price()),
-                    |(unique, empty, price), order| {
-                        (
-                            unique && price != order.price(),
-                            empty || order.is_empty(),
-                            order.price(),
-                        )
-                    },
-                );
-                (unique, empty)
+        Self::check(&orders[..min(orders.len(), COUNT + 1)])?;
+        unsafe { Ok(Self::new_unchecked(orders)) }
+    }
+    /// checks the orders have no empty amounts and no repeating prices
+    fn check(checked: &[Order]) -> std::result::Result<(), OrderBookError> {
+        if let Some(index) = checked.iter().position(Order::is_empty) {
+            Err(OrderBookError::HasOrderWithEmptyAmount {
+                price: checked[index].price(),
+                index,
+            })
+        } else if let Some(index) = not_unique(checked) {
+            Err(OrderBookError::HasOrderWithNotUniquePrice {
+                price: checked[index].price(),
+                index,
             })
-            .unwrap_or((true, false));
-
-        if is_empty {
-            Err(OrderBookError::HasOrderWithEmptyAmount)
-        } else if !is_unique {
-            Err(OrderBookError::HasOrderWithNotUniquePrice)
         } else {
-            unsafe { Ok(Self::new_unchecked(orders)) }
+            Ok(())
         }
     }
     pub fn new_sorted(orders: Vec<Order>) -> std::result::Result<Self, OrderBookError> {
-        if !orders[0..min(orders.len(), COUNT)].is_sorted_by(order_partial_comparator::<QUOTE>()) {
-            return Err(OrderBookError::OrdersNotSortedAccordingToQuoteType);
+        // the same orders as checked for uniqueness and emptiness
+        if let Some(index) = not_sorted::<QUOTE>(&orders[..min(orders.len(), COUNT + 1)]) {
+            return Err(OrderBookError::OrdersNotSortedAccordingToQuoteType {
+                price: orders[index].price(),
+                index,
+            });
         }
         unsafe { Self::new_sorted_unchecked(orders) }
     }
+    /// validates orders as new_sorted does copying only the best COUNT of them
+    /// for levels which exchanges send already sorted
+    pub fn from_sorted_slice(orders: &[Order]) -> std::result::Result<Self, OrderBookError> {
+        let checked = &orders[..min(orders.len(), COUNT + 1)];
+        if let Some(index) = not_sorted::<QUOTE>(checked) {
+            return Err(OrderBookError::OrdersNotSortedAccordingToQuoteType {
+                price: orders[index].price(),
+                index,
+            });
+        }
+        Self::check(checked)?;
+        unsafe {
+            Ok(Self::new_unchecked(
+                orders[..min(orders.len(), COUNT)].to_vec(),
+            ))
+        }
+    }
     pub fn new(mut orders: Vec<Order>) -> std::result::Result<Self, OrderBookError> {
         if orders.is_empty() {
             return Ok(Self::default());
         }
 
-        let index = min(orders.len(), COUNT) - 1;
+        // the order following the best COUNT ones has to be in place as well
+        // to detect its price repeating the last one, it also makes COUNT 0 valid
+        let index = min(orders.len(), COUNT + 1) - 1;
         orders
             .select_nth_unstable_by(index, order_comparator::<QUOTE>())
             .0
             .sort_unstable_by(order_comparator::<QUOTE>());
         unsafe { Self::new_sorted_unchecked(orders) }
     }
+    /// collects and validates orders as new does e.g. out of an adapter over exchange levels
+    pub fn try_from_iter<I: IntoIterator<Item = Order>>(
+        orders: I,
+    ) -> std::result::Result<Self, OrderBookError> {
+        Self::new(orders.into_iter().collect())
+    }
+    /// applies the diff and keeps the best COUNT levels
+    /// levels pushed out beyond COUNT are forgotten, so once better levels are removed
+    /// the book holds less than COUNT levels until the diffs bring the deeper ones back
+    /// keep a deeper book and take the best levels of it for a book always filled up to N
     pub fn update(&self, diff: &OrderBookDiff<QUOTE>) -> OrderBook<QUOTE, COUNT> {
-        let mut book = Vec::with_capacity(COUNT);
-        Merger::new(&self.0, &diff)
+        self.update_reporting(diff).0
+    }
+    /// see update, also tells whether any of the kept levels has changed
+    /// e.g. diffs of levels beyond COUNT or repeating current levels change nothing
+    pub fn update_reporting(&self, diff: &OrderBookDiff<QUOTE>) -> (OrderBook<QUOTE, COUNT>, bool) {
+        let current = &self.0 .0;
+        // deep books are mostly far from full so COUNT isn't reserved upfront
+        let mut book = Vec::with_capacity(min(COUNT, current.len() + diff.0.len()));
+        let mut changed = false;
+        for order in Merger::new(&self.0, diff)
             .filter(|order| !order.is_empty())
             .take(COUNT)
-            .collect_into(&mut book);
-        Self(OrderBookDiff::<QUOTE>(book))
+        {
+            changed |= current.get(book.len()) != Some(&order);
+            book.push(order);
+        }
+        changed |= book.len() != current.len();
+        (Self(OrderBookDiff::<QUOTE>(book)), changed)
+    }
+    /// compares every retained level unlike == which compares the best COUNT ones
+    /// the same as == as long as books don't retain levels beyond COUNT
+    pub fn deep_eq(&self, other: &Self) -> bool {
+        self.0 == other.0
+    }
+    /// sum of amounts across all levels
+    /// returns the offending sum if it isn't a valid amount (e.g. overflow)
+    pub fn total_amount(&self) -> std::result::Result<Amount, Number> {
+        Amount::new(self.0 .0.iter().map(|order| order.amount().0).sum())
     }
+    /// sum of notionals across all levels
+    pub fn total_value(&self) -> f64 {
+        self.0 .0.iter().map(Order::value).sum()
+    }
+    /// transforms every price, see OrderBookDiff::map_prices
+    pub fn map_prices<F: Fn(Price) -> Price>(&self, f: F) -> std::result::Result<Self, Number> {
+        Ok(Self(self.0.map_prices(f)?))
+    }
+    /// snaps prices to the tick grid, see OrderBookDiff::round_to_tick
+    pub fn round_to_tick(&self, tick: Price) -> std::result::Result<Self, Number> {
+        Ok(Self(self.0.round_to_tick(tick)?))
+    }
+    /// guesses tick size as the greatest common divisor of gaps between adjacent levels
+    /// remainders below a millionth of the smallest gap are treated as rounding noise
+    /// None if there are less than 2 levels
+    pub fn infer_tick(&self) -> Option<f64> {
+        let gaps: Vec<_> = self
+            .0
+             .0
+            .windows(2)
+            .map(|pair| to_f64(pair[0].price().0 - pair[1].price().0).abs())
+            .collect();
+        let epsilon = gaps.iter().copied().reduce(f64::min)? * 1e-6;
+        gaps.into_iter().reduce(|mut a, mut b| {
+            while b > epsilon {
+                (a, b) = (b, a % b);
+            }
+            a
+        })
+    }
+    /// checksum of the best levels in the order of the book
+    /// text gives the price and the amount of a level as the exchange has sent them
+    pub fn checksum<'a, F: FnMut(&Order) -> (&'a str, &'a str)>(
+        &self,
+        levels: usize,
+        text: F,
+    ) -> u32 {
+        checksum(self.0 .0.iter().take(levels).map(text))
+    }
+    /// see checksum
+    pub fn verify_checksum<'a, F: FnMut(&Order) -> (&'a str, &'a str)>(
+        &self,
+        levels: usize,
+        text: F,
+        expected: u32,
+    ) -> std::result::Result<(), OrderBookError> {
+        if self.checksum(levels, text) == expected {
+            Ok(())
+        } else {
+            Err(OrderBookError::ChecksumMismatch)
+        }
+    }
+    /// releases memory kept after levels have gone, capacity never exceeds COUNT anyway
+    /// orders live on the heap so books of any depth can be moved around cheaply
+    /// and a shallow book of a deep COUNT doesn't occupy memory for all of its levels
+    pub fn shrink_to_fit(&mut self) {
+        self.0 .0.shrink_to_fit();
+    }
+    /// amount resting at exactly the price if any, found by binary search
+    pub fn amount_at(&self, price: Price) -> Option<Amount> {
+        let probe = Order::new(price, Amount::default());
+        self.0
+             .0
+            .binary_search_by(|order| order_comparator::<QUOTE>()(order, &probe))
+            .ok()
+            .map(|index| self.0 .0[index].amount())
+    }
+    /// book of up to N best levels
+    pub fn best<const N: usize>(&self) -> OrderBook<QUOTE, N> {
+        OrderBook(OrderBookDiff(self.0 .0[..min(self.0 .0.len(), N)].to_vec()))
+    }
+    /// orders from the best one
+    pub fn orders(&self) -> &[Order] {
+        &self.0 .0
+    }
+    /// depth chart from the top of the book down
+    pub fn cumulative(&self) -> impl Iterator<Item = (Price, Amount)> + '_ {
+        cumulative(self.0 .0.iter().copied())
+    }
+}
+
+/// What OrderBookDiff::new_with_policy does with orders of equal price
+/// some venues occasionally send two entries for the same price in one update
+#[derive(Debug, Default, Eq, PartialEq, Copy, Clone)]
+pub enum DuplicatePolicy {
+    /// fails with HasOrderWithNotUniquePrice as new does
+    #[default]
+    Reject,
+    /// the latest of the orders in the input is kept
+    KeepLast,
+    /// amounts are summed as they are, the denomination of the first order is kept
+    Sum,
 }
 
 impl<const QUOTE: bool> OrderBookDiff<QUOTE> {
@@ -315,39 +933,159 @@ impl<const QUOTE: bool> OrderBookDiff<QUOTE> {
     unsafe fn new_sorted_unchecked(
         orders: Vec<Order>,
     ) -> std::result::Result<Self, OrderBookError> {
-        let is_unique = orders
-            .split_first()
-            .map(|(first, rest)| {
-                rest.iter()
-                    .fold((true, first.price()), |(unique, price), order| {
-                        (unique && price != order.price(), order.price())
-                    })
-                    .0
-            })
-            .unwrap_or(true);
-
-        if is_unique {
-            Ok(Self(orders))
-        } else {
-            Err(OrderBookError::HasOrderWithNotUniquePrice)
+        match not_unique(&orders) {
+            Some(index) => Err(OrderBookError::HasOrderWithNotUniquePrice {
+                price: orders[index].price(),
+                index,
+            }),
+            None => Ok(Self(orders)),
         }
     }
     pub fn new_sorted(orders: Vec<Order>) -> std::result::Result<Self, OrderBookError> {
-        if !orders.is_sorted_by(order_partial_comparator::<QUOTE>()) {
-            Err(OrderBookError::OrdersNotSortedAccordingToQuoteType)
-        } else {
-            unsafe { Self::new_sorted_unchecked(orders) }
+        match not_sorted::<QUOTE>(&orders) {
+            Some(index) => Err(OrderBookError::OrdersNotSortedAccordingToQuoteType {
+                price: orders[index].price(),
+                index,
+            }),
+            None => unsafe { Self::new_sorted_unchecked(orders) },
         }
     }
     pub fn new(mut orders: Vec<Order>) -> std::result::Result<Self, OrderBookError> {
         orders.sort_unstable_by(order_comparator::<QUOTE>());
         unsafe { Self::new_sorted_unchecked(orders) }
     }
+    /// collects and validates orders as new does
+    pub fn try_from_iter<I: IntoIterator<Item = Order>>(
+        orders: I,
+    ) -> std::result::Result<Self, OrderBookError> {
+        Self::new(orders.into_iter().collect())
+    }
+    /// fails at the first empty order where a snapshot is expected instead of a diff
+    pub fn require_non_empty(self) -> std::result::Result<Self, OrderBookError> {
+        match self.0.iter().position(Order::is_empty) {
+            Some(index) => Err(OrderBookError::HasOrderWithEmptyAmount {
+                price: self.0[index].price(),
+                index,
+            }),
+            None => Ok(self),
+        }
+    }
+    /// like new but orders of equal price are handled according to the policy
+    pub fn new_with_policy(
+        orders: Vec<Order>,
+        policy: DuplicatePolicy,
+    ) -> std::result::Result<Self, OrderBookError> {
+        if policy == DuplicatePolicy::Reject {
+            return Self::new(orders);
+        }
+        let mut orders = orders;
+        // stable so orders of equal price stay in input order
+        orders.sort_by(order_comparator::<QUOTE>());
+        let mut levels: Vec<Order> = Vec::with_capacity(orders.len());
+        for order in orders {
+            match levels.last_mut() {
+                Some(last) if last.price() == order.price() => {
+                    if policy == DuplicatePolicy::Sum {
+                        last.1 = last.amount().checked_add(order.amount()).map_err(|_| {
+                            OrderBookError::InvalidSum {
+                                price: order.price(),
+                            }
+                        })?;
+                    } else {
+                        *last = order;
+                    }
+                }
+                _ => levels.push(order),
+            }
+        }
+        unsafe { Ok(Self::new_unchecked(levels)) }
+    }
+    /// takes prices closer than epsilon to the first price of their run for one level
+    /// e.g. ones a ULP apart after different parse paths, the latest of such orders is kept
+    /// 0 epsilon takes no prices for one level so it is the same as new
+    /// beware that epsilon as large as the tick merges distinct levels
+    pub fn new_with_epsilon(
+        orders: Vec<Order>,
+        epsilon: Number,
+    ) -> std::result::Result<Self, OrderBookError> {
+        let mut orders = orders.into_iter().enumerate().collect_vec();
+        orders.sort_by(|(_, l), (_, r)| order_comparator::<QUOTE>()(l, r));
+        // first price of the run, index of the latest order and the order
+        let mut levels: Vec<(Price, usize, Order)> = Vec::with_capacity(orders.len());
+        for (index, order) in orders {
+            match levels.last_mut() {
+                Some((first, latest, kept)) if (order.price().0 - first.0).abs() < epsilon => {
+                    if index > *latest {
+                        (*latest, *kept) = (index, order);
+                    }
+                }
+                _ => levels.push((order.price(), index, order)),
+            }
+        }
+        let orders = levels.into_iter().map(|(.., order)| order).collect();
+        // kept orders stay closer than epsilon to their runs so they remain sorted
+        unsafe { Self::new_sorted_unchecked(orders) }
+    }
+    /// orders of both diffs with orders of the other one taking precedence at equal prices
+    /// unlike OrderBook::update empty orders are kept and nothing is truncated
+    /// so diffs can be accumulated before being applied to a book
+    pub fn merge(&self, other: &OrderBookDiff<QUOTE>) -> OrderBookDiff<QUOTE> {
+        Self(Merger::new(self, other).collect())
+    }
+    /// like merge but prices closer than epsilon are equal, see new_with_epsilon
+    pub fn merge_with_epsilon(
+        &self,
+        other: &OrderBookDiff<QUOTE>,
+        epsilon: Number,
+    ) -> OrderBookDiff<QUOTE> {
+        Self(Merger::with_epsilon(self, other, epsilon).collect())
+    }
+    /// drops empty orders for prices the book doesn't have as applying them changes nothing
+    pub fn compact<const COUNT: usize>(&self, against: &OrderBook<QUOTE, COUNT>) -> Self {
+        let mut book = against.0 .0.iter().peekable();
+        Self(
+            self.0
+                .iter()
+                .filter(|order| {
+                    if !order.is_empty() {
+                        return true;
+                    }
+                    // both are sorted so the book is walked once
+                    while book
+                        .next_if(|level| {
+                            order_comparator::<QUOTE>()(level, order) == Ordering::Less
+                        })
+                        .is_some()
+                    {}
+                    book.peek()
+                        .is_some_and(|level| level.price() == order.price())
+                })
+                .copied()
+                .collect(),
+        )
+    }
+    /// transforms every price e.g. to apply fees or convert currency
+    /// the transform doesn't have to be monotonic, orders are re-sorted if needed
+    /// and orders ending up with equal prices are merged by summing their amounts
+    /// returns the offending sum if it isn't a valid amount
+    pub fn map_prices<F: Fn(Price) -> Price>(&self, f: F) -> std::result::Result<Self, Number> {
+        let orders = self
+            .0
+            .iter()
+            .map(|order| Order(f(order.price()), order.amount(), order.denomination()))
+            .collect();
+        Ok(Self(coalesce::<QUOTE>(orders)?))
+    }
+    /// snaps every price to the nearest multiple of tick, prices closer to 0 snap to tick
+    /// orders collapsing onto the same price are merged by summing their amounts
+    pub fn round_to_tick(&self, tick: Price) -> std::result::Result<Self, Number> {
+        self.map_prices(|price| Price::new((price.0 / tick.0).round() * tick.0).unwrap_or(tick))
+    }
 }
 
-const ASK: bool = false;
-const BID: bool = true;
-const BEST_ORDER_BOOK_SIZE: usize = 10;
+pub const ASK: bool = false;
+pub const BID: bool = true;
+pub const BEST_ORDER_BOOK_SIZE: usize = 10;
 
 pub type OrderBookDiffAsks = OrderBookDiff<ASK>;
 pub type OrderBookDiffBids = OrderBookDiff<BID>;
@@ -355,10 +1093,94 @@ pub type OrderBookDiffBids = OrderBookDiff<BID>;
 pub type OrderBookAsks = OrderBook<ASK, BEST_ORDER_BOOK_SIZE>;
 pub type OrderBookBids = OrderBook<BID, BEST_ORDER_BOOK_SIZE>;
 
-#[derive(Debug, Eq, PartialEq, PartialOrd, Ord, Clone, Copy, EnumIter)]
+/// Side of a book known at runtime e.g. from labels of a generic feed
+/// see ASK and BID for the const generic one
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Quote {
+    Bid,
+    Ask,
+}
+
+impl Quote {
+    /// the side of books of QUOTE
+    pub const fn of<const QUOTE: bool>() -> Self {
+        match QUOTE {
+            ASK => Self::Ask,
+            BID => Self::Bid,
+        }
+    }
+    /// the value of QUOTE for books of the side
+    pub const fn value(self) -> bool {
+        match self {
+            Self::Ask => ASK,
+            Self::Bid => BID,
+        }
+    }
+    /// validates orders as OrderBook::new does for books of the side
+    pub fn build_book(self, orders: Vec<Order>) -> std::result::Result<Book, OrderBookError> {
+        Ok(match self {
+            Self::Bid => Book::Bids(OrderBook::new(orders)?),
+            Self::Ask => Book::Asks(OrderBook::new(orders)?),
+        })
+    }
+}
+
+impl From<bool> for Quote {
+    fn from(quote: bool) -> Self {
+        match quote {
+            ASK => Self::Ask,
+            BID => Self::Bid,
+        }
+    }
+}
+
+impl Display for Quote {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(match self {
+            Self::Ask => quote_to_str::<ASK>(),
+            Self::Bid => quote_to_str::<BID>(),
+        })
+    }
+}
+
+/// takes both book and maker order labels, "sell" orders rest on asks
+impl std::str::FromStr for Quote {
+    type Err = String;
+
+    fn from_str(quote: &str) -> std::result::Result<Self, Self::Err> {
+        match quote.to_ascii_lowercase().as_str() {
+            "bid" | "bids" | "buy" => Ok(Self::Bid),
+            "ask" | "asks" | "sell" => Ok(Self::Ask),
+            _ => Err(format!("unknown quote {quote}")),
+        }
+    }
+}
+
+/// Book of a side known at runtime, see Quote::build_book
+#[derive(Debug, Clone, PartialEq)]
+pub enum Book {
+    Bids(OrderBookBids),
+    Asks(OrderBookAsks),
+}
+
+impl Book {
+    pub fn quote(&self) -> Quote {
+        match self {
+            Self::Bids(_) => Quote::Bid,
+            Self::Asks(_) => Quote::Ask,
+        }
+    }
+}
+
+#[derive(
+    Debug, Eq, PartialEq, PartialOrd, Ord, Hash, Clone, Copy, EnumIter, Serialize, Deserialize,
+)]
+#[serde(rename_all = "lowercase")]
 pub enum Exchange {
     Binance,
     Bitstamp,
+    Kraken,
+    Coinbase,
 }
 
 #[derive(Eq, PartialEq, Copy, Clone)]
@@ -383,8 +1205,129 @@ impl Debug for SummaryOrder {
     }
 }
 
+/// Direction of an order to be matched against the aggregated book
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Side {
+    /// takes asks
+    Buy,
+    /// takes bids
+    Sell,
+}
+
+/// Outcome of a market order walking the aggregated book
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Fill {
+    /// cost / filled, None if nothing is filled
+    pub average_price: Option<f64>,
+    /// sum of notionals of consumed liquidity
+    pub cost: f64,
+    pub filled: Amount,
+    /// left unfilled when the book is too thin
+    pub remaining: Amount,
+}
+
+/// Best bid and ask of a crossed summary, sell on the bid exchange and buy on the ask one
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Arb {
+    pub bid: SummaryOrder,
+    pub ask: SummaryOrder,
+    /// bid price - ask price per unit, not accounting for fees
+    pub edge: f64,
+}
+
+impl Fill {
+    fn new<I: Iterator<Item = SummaryOrder>>(orders: I, amount: Amount) -> Self {
+        let mut remaining = amount;
+        let mut cost = 0.0;
+        for order in orders {
+            if remaining.0 == ZERO {
+                break;
+            }
+            let taken = min(order.order().amount(), remaining);
+            cost += Order(order.order().price(), taken, order.order().denomination()).value();
+            remaining = remaining
+                .checked_sub(taken)
+                .expect("taken doesn't exceed remaining");
+        }
+        let filled = amount
+            .checked_sub(remaining)
+            .expect("remaining doesn't exceed amount");
+        Self {
+            average_price: (filled.0 != ZERO).then(|| cost / to_f64(filled.0)),
+            cost,
+            filled,
+            remaining,
+        }
+    }
+    pub fn is_complete(&self) -> bool {
+        self.remaining.0 == ZERO
+    }
+}
+
+/// Compares quotes of equal price, see TieBreak::Custom
+pub type TieBreakFn = Arc<dyn Fn(&SummaryOrder, &SummaryOrder) -> Ordering + Send + Sync>;
+
+/// Order of summary quotes of different exchanges at equal price
+#[derive(Clone, Default)]
+pub enum TieBreak {
+    /// larger amount first, then exchanges in declaration order
+    #[default]
+    ByAmountDesc,
+    /// exchanges in the listed order, unlisted ones after them by amount
+    ByExchange(Vec<Exchange>),
+    /// quotes ordered as less come first
+    Custom(TieBreakFn),
+}
+
+impl Debug for TieBreak {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::ByAmountDesc => f.write_str("ByAmountDesc"),
+            Self::ByExchange(exchanges) => f.debug_tuple("ByExchange").field(exchanges).finish(),
+            Self::Custom(_) => f.write_str("Custom"),
+        }
+    }
+}
+
+impl TieBreak {
+    fn cmp(&self, l: &SummaryOrder, r: &SummaryOrder) -> Ordering {
+        let by_amount = || {
+            r.order()
+                .amount()
+                .cmp(&l.order().amount())
+                .then_with(|| l.exchange().cmp(&r.exchange()))
+        };
+        match self {
+            Self::ByAmountDesc => by_amount(),
+            Self::ByExchange(exchanges) => {
+                let rank = |order: &SummaryOrder| {
+                    exchanges
+                        .iter()
+                        .position(|exchange| *exchange == order.exchange())
+                        .unwrap_or(exchanges.len())
+                };
+                rank(l).cmp(&rank(r)).then_with(by_amount)
+            }
+            Self::Custom(cmp) => cmp(l, r),
+        }
+    }
+}
+
+/// Levels of an exchange as persisted by SummaryOrderBook::to_bytes
+#[derive(Serialize, Deserialize)]
+struct PersistedBook {
+    exchange: Exchange,
+    bids: Vec<(Number, Number)>,
+    asks: Vec<(Number, Number)>,
+}
+
 pub struct SummaryOrderBook {
     books: Vec<(Exchange, OrderBookBids, OrderBookAsks)>,
+    /// per exchange rate converting its prices into the common quote currency
+    conversions: Vec<Option<Price>>,
+    /// per exchange flag of instruments quoted as reciprocal of spot price
+    inverse: Vec<bool>,
+    tie_break: TieBreak,
 }
 
 impl Default for SummaryOrderBook {
@@ -392,45 +1335,128 @@ impl Default for SummaryOrderBook {
         let books = Exchange::iter()
             .map(|exchange| (exchange, OrderBookBids::default(), OrderBookAsks::default()))
             .collect();
-        Self { books }
+        let conversions = Exchange::iter().map(|_| None).collect();
+        let inverse = Exchange::iter().map(|_| false).collect();
+        Self {
+            books,
+            conversions,
+            inverse,
+            tie_break: TieBreak::default(),
+        }
+    }
+}
+
+/// renders bids on the left and asks on the right with the spread above them
+impl Display for SummaryOrderBook {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let bids: Vec<_> = self.bids().collect();
+        let asks: Vec<_> = self.asks().collect();
+        let spread = Self::spread(bids.iter().copied(), asks.iter().copied());
+        let width = 6 * LADDER_WIDTH + 7;
+        write!(f, "{:^width$}", format!("spread {spread}"))?;
+        write!(
+            f,
+            "\n{:>LADDER_WIDTH$} {:>LADDER_WIDTH$} {:>LADDER_WIDTH$} | {:<LADDER_WIDTH$} {:<LADDER_WIDTH$} {:<LADDER_WIDTH$}",
+            "exchange", "amount", "bid", "ask", "amount", "exchange"
+        )?;
+        let empty = || (String::default(), String::default(), String::default());
+        let columns = |order: &SummaryOrder| {
+            (
+                format!("{:?}", order.exchange()),
+                order.order().amount().to_string(),
+                order.order().price().to_string(),
+            )
+        };
+        for pair in bids.iter().zip_longest(asks.iter()) {
+            let ((bid_exchange, bid_amount, bid), (ask_exchange, ask_amount, ask)) = match pair {
+                EitherOrBoth::Both(bid, ask) => (columns(bid), columns(ask)),
+                EitherOrBoth::Left(bid) => (columns(bid), empty()),
+                EitherOrBoth::Right(ask) => (empty(), columns(ask)),
+            };
+            write!(
+                f,
+                "\n{bid_exchange:>LADDER_WIDTH$} {bid_amount:>LADDER_WIDTH$} {bid:>LADDER_WIDTH$} | {ask:<LADDER_WIDTH$} {ask_amount:<LADDER_WIDTH$} {ask_exchange:<LADDER_WIDTH$}"
+            )?;
+        }
+        Ok(())
     }
 }
 
 impl SummaryOrderBook {
+    /// position of the exchange entry in books, conversions and inverse
+    /// looked up rather than derived from the discriminant so the order of entries doesn't matter
+    fn index(&self, exchange: Exchange) -> usize {
+        self.books
+            .iter()
+            .position(|(e, _, _)| *e == exchange)
+            .expect("every exchange is registered on construction")
+    }
     fn quotes<const QUOTE: bool>(&self) -> impl Iterator<Item = SummaryOrder> + '_ {
+        self.merged::<QUOTE>().take(BEST_ORDER_BOOK_SIZE)
+    }
+    /// best quotes keeping at least per_exchange levels of every exchange which has them
+    /// remaining of total slots are filled by price, quotas win if they exceed total
+    /// so a venue dominating prices doesn't hide the others
+    pub fn quotes_with_quota<const QUOTE: bool>(
+        &self,
+        per_exchange: usize,
+        total: usize,
+    ) -> impl Iterator<Item = SummaryOrder> {
+        let merged: Vec<_> = self.merged::<QUOTE>().collect();
+        let mut taken = vec![0; self.books.len()];
+        let mut selected: Vec<_> = merged
+            .iter()
+            .map(|order| {
+                let taken = &mut taken[self.index(order.exchange())];
+                *taken += 1;
+                *taken <= per_exchange
+            })
+            .collect();
+        let mut spare = total.saturating_sub(selected.iter().filter(|s| **s).count());
+        for selected in selected.iter_mut().filter(|s| !**s) {
+            if spare == 0 {
+                break;
+            }
+            *selected = true;
+            spare -= 1;
+        }
+        merged
+            .into_iter()
+            .zip(selected)
+            .filter_map(|(order, selected)| selected.then_some(order))
+    }
+    /// quotes of all exchanges from the best one
+    fn merged<const QUOTE: bool>(&self) -> impl Iterator<Item = SummaryOrder> + '_ {
+        let tie_break = &self.tie_break;
         kmerge_by(
-            self.books.iter().map(|books| {
-                let (exchange, bids, asks) = books;
+            self.books
+                .iter()
+                .zip(self.conversions.iter().copied())
+                .map(|(books, rate)| {
+                    let (exchange, bids, asks) = books;
+                    match QUOTE {
+                        ASK => &asks.0 .0,
+                        BID => &bids.0 .0,
+                    }
+                    .iter()
+                    .copied()
+                    .filter_map(move |order| match rate {
+                        Some(rate) => Price::new(order.price().0 * rate.0)
+                            .ok()
+                            .map(|price| Order(price, order.amount(), order.denomination())),
+                        None => Some(order),
+                    })
+                    .map(|order| SummaryOrder(*exchange, order))
+                }),
+            move |l: &SummaryOrder, r: &SummaryOrder| {
                 match QUOTE {
-                    ASK => &asks.0 .0,
-                    BID => &bids.0 .0,
+                    ASK => l.order().price().cmp(&r.order().price()),
+                    BID => r.order().price().cmp(&l.order().price()),
                 }
-                .iter()
-                .copied()
-                .map(|order| SummaryOrder(*exchange, order))
-            }),
-            match QUOTE {
-                ASK => |l: &SummaryOrder, r: &SummaryOrder| match l
-                    .order()
-                    .price()
-                    .cmp(&r.order().price())
-                {
-                    Ordering::Less => true,
-                    Ordering::Equal => l.order().amount() > r.order().amount(),
-                    Ordering::Greater => false,
-                },
-                BID => |l: &SummaryOrder, r: &SummaryOrder| match r
-                    .order()
-                    .price()
-                    .cmp(&l.order().price())
-                {
-                    Ordering::Less => true,
-                    Ordering::Equal => l.order().amount() > r.order().amount(),
-                    Ordering::Greater => false,
-                },
+                .then_with(|| tie_break.cmp(l, r))
+                    == Ordering::Less
             },
         )
-        .take(BEST_ORDER_BOOK_SIZE)
     }
     /*fn new(spread: Price, bids: SummaryBookBestBids, asks: SummaryBookBestAsks) -> Self {
         Self { spread, bids, asks }
@@ -445,9 +1471,44 @@ impl SummaryOrderBook {
             (None, None) => f64::NAN,
             (Some(_), None) => f64::INFINITY,
             (None, Some(_)) => f64::NEG_INFINITY,
-            (Some(bid), Some(ask)) => bid.1.price().into_inner() - ask.1.price().into_inner(),
+            (Some(bid), Some(ask)) => {
+                to_f64(bid.1.price().into_inner() - ask.1.price().into_inner())
+            }
         }
     }
+    /// spread of the stored books, see spread
+    /// quotes are lazy so only the best level of each side is merged
+    pub fn current_spread(&self) -> f64 {
+        Self::spread(
+            self.bids().next().into_iter(),
+            self.asks().next().into_iter(),
+        )
+    }
+    /// average of best bid and best ask, None if either side is empty
+    pub fn mid(&self) -> Option<f64> {
+        let (bid, ask) = (self.bids().next()?, self.asks().next()?);
+        Some(to_f64(bid.order().price().0 + ask.order().price().0) / 2.0)
+    }
+    /// (ask - bid) / mid in basis points to compare spreads across instruments
+    /// negative if the book is crossed, None if either side is empty
+    pub fn spread_bps(&self) -> Option<f64> {
+        // current_spread is bid - ask
+        self.mid()
+            .map(|mid| -self.current_spread() / mid * 10_000.0)
+    }
+    /// best bid and ask if the best bid reaches the best ask
+    pub fn arbitrage(&self) -> Option<Arb> {
+        let (bid, ask) = (self.bids().next()?, self.asks().next()?);
+        (bid.order().price() >= ask.order().price()).then(|| Arb {
+            bid,
+            ask,
+            edge: to_f64(bid.order().price().0 - ask.order().price().0),
+        })
+    }
+    /// the summary of the stored books differs from prev in published levels or spread
+    pub fn changed_since(&self, prev: &Summary) -> bool {
+        Summary::new(prev.symbol.clone(), self) != *prev
+    }
     /// returns up to BEST_ORDER_BOOK_SIZE best asks
     pub fn asks(&self) -> impl Iterator<Item = SummaryOrder> + '_ {
         self.quotes::<ASK>()
@@ -456,11 +1517,281 @@ impl SummaryOrderBook {
     pub fn bids(&self) -> impl Iterator<Item = SummaryOrder> + '_ {
         self.quotes::<BID>()
     }
+    /// books of the exchange as stored i.e. inverted if it's inverse and not converted
+    pub fn exchange_book(&self, exchange: Exchange) -> Option<(&OrderBookBids, &OrderBookAsks)> {
+        self.books
+            .iter()
+            .find(|(e, _, _)| *e == exchange)
+            .map(|(_, bids, asks)| (bids, asks))
+    }
+    /// books of every registered exchange, see exchange_book
+    pub fn exchange_books(
+        &self,
+    ) -> impl Iterator<Item = (Exchange, &OrderBookBids, &OrderBookAsks)> + '_ {
+        self.books
+            .iter()
+            .map(|(exchange, bids, asks)| (*exchange, bids, asks))
+    }
+    /// numbers of bid and ask levels of the best ones each exchange contributes
+    /// exchanges contributing none are absent
+    pub fn contribution(&self) -> HashMap<Exchange, (usize, usize)> {
+        let mut contribution = HashMap::<Exchange, (usize, usize)>::new();
+        for order in self.bids() {
+            contribution.entry(order.exchange()).or_default().0 += 1;
+        }
+        for order in self.asks() {
+            contribution.entry(order.exchange()).or_default().1 += 1;
+        }
+        contribution
+    }
+    /// whether books of every registered exchange have any level on either side
+    pub fn exchange_status(&self) -> HashMap<Exchange, bool> {
+        self.books
+            .iter()
+            .map(|(exchange, bids, asks)| {
+                (
+                    *exchange,
+                    !bids.orders().is_empty() || !asks.orders().is_empty(),
+                )
+            })
+            .collect()
+    }
+    /// diffs reproducing books of every registered exchange from empty ones, the counterpart of reset
+    /// books of inverse exchanges are given as stored i.e. already inverted
+    pub fn as_diffs(
+        &self,
+    ) -> impl Iterator<Item = (Exchange, OrderBookDiffBids, OrderBookDiffAsks)> + '_ {
+        self.books
+            .iter()
+            .map(|(exchange, bids, asks)| (*exchange, bids.0.clone(), asks.0.clone()))
+    }
+    /// depth chart of best bids
+    pub fn cumulative_bids(&self) -> impl Iterator<Item = (Price, Amount)> + '_ {
+        cumulative(self.bids().map(|order| order.order()))
+    }
+    /// depth chart of best asks
+    pub fn cumulative_asks(&self) -> impl Iterator<Item = (Price, Amount)> + '_ {
+        cumulative(self.asks().map(|order| order.order()))
+    }
+    /// (bid volume - ask volume) / (bid volume + ask volume) over top depth levels
+    /// None if both sides are empty
+    pub fn imbalance(&self, depth: usize) -> Option<f64> {
+        let volume = |orders: &mut dyn Iterator<Item = SummaryOrder>| -> Option<Number> {
+            orders
+                .take(depth)
+                .map(|order| order.order().amount().0)
+                .reduce(|sum, amount| sum + amount)
+        };
+        match (volume(&mut self.bids()), volume(&mut self.asks())) {
+            (None, None) => None,
+            (bids, asks) => {
+                let (bids, asks) = (to_f64(bids.unwrap_or(ZERO)), to_f64(asks.unwrap_or(ZERO)));
+                Some((bids - asks) / (bids + asks))
+            }
+        }
+    }
+    /// walks best asks consuming liquidity until amount is bought
+    /// only BEST_ORDER_BOOK_SIZE levels are available, see asks
+    pub fn simulate_market_buy(&self, amount: Amount) -> Fill {
+        Fill::new(self.asks(), amount)
+    }
+    /// walks best bids consuming liquidity until amount is sold
+    /// only BEST_ORDER_BOOK_SIZE levels are available, see bids
+    pub fn simulate_market_sell(&self, amount: Amount) -> Fill {
+        Fill::new(self.bids(), amount)
+    }
+    /// total amount a limit order of the side could take at the limit price or better
+    /// only BEST_ORDER_BOOK_SIZE levels are available, see asks and bids
+    pub fn fillable_at(&self, side: Side, limit: Price) -> Amount {
+        let fillable: Box<dyn Iterator<Item = Order>> = match side {
+            Side::Buy => Box::new(
+                self.asks()
+                    .map(|order| order.order())
+                    .take_while(|order| order.price() <= limit),
+            ),
+            Side::Sell => Box::new(
+                self.bids()
+                    .map(|order| order.order())
+                    .take_while(|order| order.price() >= limit),
+            ),
+        };
+        cumulative(fillable)
+            .last()
+            .map_or(Amount::default(), |(_, total)| total)
+    }
     /// resets order books for specified exchange only
+    /// books of inverse exchange are inverted first, see set_inverse
     pub fn reset(&mut self, exchange: Exchange, bids: OrderBookBids, asks: OrderBookAsks) {
-        self.books[exchange as usize] = (exchange, bids, asks);
+        let index = self.index(exchange);
+        self.books[index] = if self.inverse[index] {
+            (exchange, invert(&asks), invert(&bids))
+        } else {
+            (exchange, bids, asks)
+        };
+    }
+    /// resets order books for specified exchange unless they are crossed
+    /// so a single bad exchange can't spoil the summary, see reset
+    pub fn try_reset(
+        &mut self,
+        exchange: Exchange,
+        bids: OrderBookBids,
+        asks: OrderBookAsks,
+    ) -> Result<(), OrderBookError> {
+        if let (Some(bid), Some(ask)) = (bids.orders().first(), asks.orders().first()) {
+            if bid.price() >= ask.price() {
+                return Err(OrderBookError::Crossed {
+                    bid: bid.price(),
+                    ask: ask.price(),
+                });
+            }
+        }
+        self.reset(exchange, bids, asks);
+        Ok(())
+    }
+    /// resets bids of specified exchange only keeping its asks
+    /// bids of inverse exchange become its asks, see set_inverse
+    pub fn reset_bids(&mut self, exchange: Exchange, bids: OrderBookBids) {
+        let index = self.index(exchange);
+        let book = &mut self.books[index];
+        if self.inverse[index] {
+            book.2 = invert(&bids);
+        } else {
+            book.1 = bids;
+        }
     }
+    /// resets asks of specified exchange only keeping its bids
+    /// asks of inverse exchange become its bids, see set_inverse
+    pub fn reset_asks(&mut self, exchange: Exchange, asks: OrderBookAsks) {
+        let index = self.index(exchange);
+        let book = &mut self.books[index];
+        if self.inverse[index] {
+            book.1 = invert(&asks);
+        } else {
+            book.2 = asks;
+        }
+    }
+    /// marks exchange as quoting inverse instrument whose prices are reciprocal of spot ones
+    /// so its prices are mapped through 1/price and bids and asks swap their roles
+    /// takes effect starting from the next reset of the exchange
+    pub fn set_inverse(&mut self, exchange: Exchange, inverse: bool) {
+        let index = self.index(exchange);
+        self.inverse[index] = inverse;
+    }
+    /// books of every exchange as stored i.e. inverse ones are already inverted
+    /// conversions, inverse flags and tie break aren't included
+    pub fn to_bytes(&self) -> Vec<u8> {
+        let levels = |orders: &[Order]| {
+            orders
+                .iter()
+                .map(|order| (order.price().0, order.amount().0))
+                .collect()
+        };
+        let books: Vec<_> = self
+            .books
+            .iter()
+            .map(|(exchange, bids, asks)| PersistedBook {
+                exchange: *exchange,
+                bids: levels(bids.orders()),
+                asks: levels(asks.orders()),
+            })
+            .collect();
+        serde_json::to_vec(&books).expect("books are serializable")
+    }
+    /// summary with books restored from to_bytes validated as new_sorted does
+    /// exchanges missing in bytes have empty books
+    pub fn from_bytes(bytes: &[u8]) -> std::result::Result<Self, String> {
+        let books: Vec<PersistedBook> = serde_json::from_slice(bytes).map_err(|e| e.to_string())?;
+        let orders = |levels: Vec<(Number, Number)>| {
+            levels
+                .into_iter()
+                .map(|(price, amount)| Order::try_new(price, amount).map_err(|e| e.to_string()))
+                .collect::<std::result::Result<Vec<_>, _>>()
+        };
+        let mut summary = Self::default();
+        for book in books {
+            let index = summary.index(book.exchange);
+            summary.books[index] = (
+                book.exchange,
+                OrderBook::new_sorted(orders(book.bids)?).map_err(|e| e.to_string())?,
+                OrderBook::new_sorted(orders(book.asks)?).map_err(|e| e.to_string())?,
+            );
+        }
+        Ok(summary)
+    }
+    /// orders quotes of different exchanges at equal price, see TieBreak
+    pub fn set_tie_break(&mut self, tie_break: TieBreak) {
+        self.tie_break = tie_break;
+    }
+    /// converts prices of specified exchange into the common quote currency
+    /// by multiplying them by rate, amounts are left as is
+    /// levels whose converted price isn't a valid price are skipped
+    pub fn set_conversion(&mut self, exchange: Exchange, rate: Price) {
+        let index = self.index(exchange);
+        self.conversions[index] = Some(rate);
+    }
+}
+
+/// Summaries of several instruments fed by the same exchanges
+#[derive(Default)]
+pub struct SummaryBooks {
+    books: HashMap<Symbol, SummaryOrderBook>,
 }
 
+impl SummaryBooks {
+    /// resets books of the exchange for the symbol, see SummaryOrderBook::reset
+    /// the summary of the symbol is created on its first reset
+    pub fn reset(
+        &mut self,
+        symbol: &Symbol,
+        exchange: Exchange,
+        bids: OrderBookBids,
+        asks: OrderBookAsks,
+    ) {
+        self.get_or_default(symbol).reset(exchange, bids, asks);
+    }
+    pub fn get(&self, symbol: &Symbol) -> Option<&SummaryOrderBook> {
+        self.books.get(symbol)
+    }
+    /// e.g. to configure conversions of the symbol before it is reset
+    pub fn get_or_default(&mut self, symbol: &Symbol) -> &mut SummaryOrderBook {
+        if !self.books.contains_key(symbol) {
+            self.books
+                .insert(symbol.clone(), SummaryOrderBook::default());
+        }
+        self.books.get_mut(symbol).expect("inserted above")
+    }
+    pub fn symbols(&self) -> impl Iterator<Item = &Symbol> {
+        self.books.keys()
+    }
+    /// best bids of the symbol, none if it has never been reset
+    pub fn bids<'a>(&'a self, symbol: &Symbol) -> impl Iterator<Item = SummaryOrder> + 'a {
+        self.books
+            .get(symbol)
+            .into_iter()
+            .flat_map(|book| book.bids())
+    }
+    /// best asks of the symbol, none if it has never been reset
+    pub fn asks<'a>(&'a self, symbol: &Symbol) -> impl Iterator<Item = SummaryOrder> + 'a {
+        self.books
+            .get(symbol)
+            .into_iter()
+            .flat_map(|book| book.asks())
+    }
+    /// see SummaryOrderBook::current_spread, NAN if the symbol has never been reset
+    pub fn spread(&self, symbol: &Symbol) -> f64 {
+        self.books
+            .get(symbol)
+            .map_or(f64::NAN, SummaryOrderBook::current_spread)
+    }
+}
+
+pub mod snapshot;
+pub mod stats;
+pub mod summary;
+
+#[cfg(all(test, feature = "decimal"))]
+mod decimal_tests;
+#[cfg(test)]
+mod proptests;
 #[cfg(test)]
 mod tests;
@@ -0,0 +1,155 @@
+use proptest::prelude::*;
+
+use crate::core::*;
+
+/// orders with few distinct prices to get duplicates and empty amounts now and then
+fn orders(max_len: usize) -> impl Strategy<Value = Vec<Order>> {
+    prop::collection::vec((1u8..=20, 0u8..4), 0..=max_len).prop_map(|orders| {
+        orders
+            .into_iter()
+            .map(|(price, amount)| {
+                Order::new(
+                    Price::new(Number::from(price)).unwrap(),
+                    Amount::new(Number::from(amount)).unwrap(),
+                )
+            })
+            .collect()
+    })
+}
+
+/// the same orders with unique prices and non empty amounts
+fn valid(mut orders: Vec<Order>) -> Vec<Order> {
+    orders.retain(|order| !order.is_empty());
+    orders.sort_unstable_by_key(|order| order.price());
+    orders.dedup_by_key(|order| order.price());
+    orders
+}
+
+fn assert_book<const QUOTE: bool, const COUNT: usize>(book: &OrderBook<QUOTE, COUNT>) {
+    let orders = book.orders();
+    assert!(orders.len() <= COUNT);
+    assert_eq!(not_sorted::<QUOTE>(orders), None);
+    assert_eq!(not_unique(orders), None);
+    assert!(!orders.iter().any(Order::is_empty));
+}
+
+fn constructors<const QUOTE: bool, const COUNT: usize>(orders: Vec<Order>) {
+    assert_eq!(
+        OrderBookDiff::<QUOTE>::new_with_epsilon(orders.clone(), ZERO),
+        OrderBookDiff::new(orders.clone())
+    );
+    if let Ok(book) = OrderBook::<QUOTE, COUNT>::new(orders.clone()) {
+        assert_book(&book);
+    }
+    if let Ok(book) = OrderBook::<QUOTE, COUNT>::new_sorted(orders.clone()) {
+        assert_book(&book);
+    }
+
+    let mut orders = valid(orders);
+    let len = min(orders.len(), COUNT);
+    let book = OrderBook::<QUOTE, COUNT>::new(orders.clone()).unwrap();
+    assert_eq!(book.orders().len(), len);
+    assert_book(&book);
+
+    orders.sort_unstable_by(order_comparator::<QUOTE>());
+    assert_eq!(book.orders(), &orders[..len]);
+    assert_eq!(
+        OrderBook::<QUOTE, COUNT>::from_sorted_slice(&orders),
+        Ok(book.clone())
+    );
+    assert_eq!(OrderBook::<QUOTE, COUNT>::new_sorted(orders), Ok(book));
+}
+
+proptest! {
+    #[test]
+    fn book_constructors(orders in orders(3 * BEST_ORDER_BOOK_SIZE)) {
+        constructors::<BID, 0>(orders.clone());
+        constructors::<ASK, 0>(orders.clone());
+        constructors::<BID, 1>(orders.clone());
+        constructors::<ASK, 1>(orders.clone());
+        constructors::<BID, BEST_ORDER_BOOK_SIZE>(orders.clone());
+        constructors::<ASK, BEST_ORDER_BOOK_SIZE>(orders);
+    }
+}
+
+fn diff<const QUOTE: bool>(mut orders: Vec<Order>) -> OrderBookDiff<QUOTE> {
+    orders.sort_unstable_by_key(|order| order.price());
+    orders.dedup_by_key(|order| order.price());
+    OrderBookDiff::new(orders).unwrap()
+}
+
+fn updates<const QUOTE: bool, const COUNT: usize>(book: Vec<Order>, diffs: &[Vec<Order>]) {
+    let mut book = OrderBook::<QUOTE, COUNT>::new(valid(book)).unwrap();
+    for orders in diffs {
+        let (updated, changed) = book.update_reporting(&diff(orders.clone()));
+        assert_eq!(changed, updated != book);
+        book = updated;
+        assert_book(&book);
+    }
+}
+
+/// with prices never repeating, applying diffs one by one equals applying them at once
+fn fold<const QUOTE: bool, const COUNT: usize>(book: &[Order], diffs: &[&[Order]]) {
+    let book = OrderBook::<QUOTE, COUNT>::new(valid(book.to_vec())).unwrap();
+    let folded = diffs.iter().fold(book.clone(), |book, orders| {
+        book.update(&diff(orders.to_vec()))
+    });
+    let merged = book.update(&diff(diffs.concat()));
+    assert_eq!(folded, merged);
+}
+
+/// orders with distinct prices split into a book and diffs at the cuts
+fn distinct() -> impl Strategy<Value = (Vec<Order>, Vec<usize>)> {
+    const LEN: u8 = 60;
+    (
+        Just((1..=LEN).collect::<Vec<_>>()).prop_shuffle(),
+        prop::collection::vec(0u8..4, LEN as usize),
+        prop::collection::vec(0..=LEN as usize, 0..6),
+    )
+        .prop_map(|(prices, amounts, mut cuts)| {
+            cuts.sort_unstable();
+            let orders = prices
+                .into_iter()
+                .zip(amounts)
+                .map(|(price, amount)| {
+                    Order::new(
+                        Price::new(Number::from(price)).unwrap(),
+                        Amount::new(Number::from(amount)).unwrap(),
+                    )
+                })
+                .collect();
+            (orders, cuts)
+        })
+}
+
+proptest! {
+    #[test]
+    fn book_updates(
+        book in orders(3 * BEST_ORDER_BOOK_SIZE),
+        diffs in prop::collection::vec(orders(BEST_ORDER_BOOK_SIZE), 0..10),
+    ) {
+        updates::<BID, 3>(book.clone(), &diffs);
+        updates::<ASK, 3>(book.clone(), &diffs);
+        updates::<BID, BEST_ORDER_BOOK_SIZE>(book.clone(), &diffs);
+        updates::<ASK, BEST_ORDER_BOOK_SIZE>(book, &diffs);
+    }
+
+    #[test]
+    fn book_updates_fold((orders, cuts) in distinct()) {
+        let mut parts = Vec::new();
+        let mut rest = orders.as_slice();
+        let mut start = 0;
+        for cut in cuts {
+            let (part, tail) = rest.split_at(cut - start);
+            parts.push(part);
+            rest = tail;
+            start = cut;
+        }
+        parts.push(rest);
+        let (book, diffs) = parts.split_first().unwrap();
+        fold::<BID, 3>(book, diffs);
+        fold::<ASK, 3>(book, diffs);
+        fold::<BID, BEST_ORDER_BOOK_SIZE>(book, diffs);
+        fold::<ASK, BEST_ORDER_BOOK_SIZE>(book, diffs);
+    }
+}
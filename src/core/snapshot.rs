@@ -0,0 +1,190 @@
+use std::{fmt::Display, str::FromStr};
+
+use itertools::{EitherOrBoth, Itertools};
+use strum::IntoEnumIterator;
+
+use crate::core::{quote_to_str, to_f64, Exchange, Number, Order, SummaryOrderBook, ASK, BID};
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+struct Level {
+    exchange: Exchange,
+    quote: bool,
+    price: Number,
+    amount: Number,
+}
+
+impl Level {
+    fn new<const QUOTE: bool>(exchange: Exchange, order: &Order) -> Self {
+        Self {
+            exchange,
+            quote: QUOTE,
+            price: order.price().into_inner(),
+            amount: order.amount().into_inner(),
+        }
+    }
+}
+
+impl Display for Level {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let quote = match self.quote {
+            ASK => quote_to_str::<ASK>(),
+            BID => quote_to_str::<BID>(),
+        };
+        write!(
+            f,
+            "{:?} {quote} {} {}",
+            self.exchange, self.price, self.amount
+        )
+    }
+}
+
+impl FromStr for Level {
+    type Err = String;
+
+    fn from_str(line: &str) -> Result<Self, Self::Err> {
+        let [exchange, quote, price, amount] =
+            <[&str; 4]>::try_from(line.split(' ').collect::<Vec<_>>())
+                .map_err(|_| format!("level {line} doesn't have 4 fields"))?;
+        Ok(Self {
+            exchange: Exchange::iter()
+                .find(|e| format!("{e:?}") == exchange)
+                .ok_or_else(|| format!("unknown exchange {exchange}"))?,
+            quote: match quote {
+                "ask" => ASK,
+                "bid" => BID,
+                _ => return Err(format!("unknown quote {quote}")),
+            },
+            price: price
+                .parse()
+                .map_err(|_| format!("invalid price {price}"))?,
+            amount: amount
+                .parse()
+                .map_err(|_| format!("invalid amount {amount}"))?,
+        })
+    }
+}
+
+/// Every level of every exchange of a summary in canonical form for golden file tests
+/// one `exchange quote price amount` per line, exchanges in declaration order, bids first
+/// levels are recorded as stored i.e. inverse exchanges already inverted but not converted
+#[derive(Debug, Clone, PartialEq)]
+pub struct Snapshot(Vec<Level>);
+
+impl From<&SummaryOrderBook> for Snapshot {
+    fn from(book: &SummaryOrderBook) -> Self {
+        Self(
+            book.books
+                .iter()
+                .flat_map(|(exchange, bids, asks)| {
+                    bids.orders()
+                        .iter()
+                        .map(|order| Level::new::<BID>(*exchange, order))
+                        .chain(
+                            asks.orders()
+                                .iter()
+                                .map(|order| Level::new::<ASK>(*exchange, order)),
+                        )
+                })
+                .collect(),
+        )
+    }
+}
+
+impl Display for Snapshot {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        self.0.iter().try_for_each(|level| writeln!(f, "{level}"))
+    }
+}
+
+impl FromStr for Snapshot {
+    type Err = String;
+
+    fn from_str(snapshot: &str) -> Result<Self, Self::Err> {
+        Ok(Self(
+            snapshot.lines().map(str::parse).collect::<Result<_, _>>()?,
+        ))
+    }
+}
+
+impl Snapshot {
+    /// describes every level which differs from the expected one
+    /// prices and amounts are compared with the absolute tolerance
+    pub fn diff(&self, expected: &Snapshot, tolerance: f64) -> Vec<String> {
+        let close = |l: Number, r: Number| (to_f64(l) - to_f64(r)).abs() <= tolerance;
+        self.0
+            .iter()
+            .zip_longest(expected.0.iter())
+            .enumerate()
+            .filter_map(|(line, pair)| match pair {
+                EitherOrBoth::Both(actual, expected)
+                    if actual.exchange == expected.exchange
+                        && actual.quote == expected.quote
+                        && close(actual.price, expected.price)
+                        && close(actual.amount, expected.amount) =>
+                {
+                    None
+                }
+                EitherOrBoth::Both(actual, expected) => {
+                    Some(format!("line {line}: expected {expected}, got {actual}"))
+                }
+                EitherOrBoth::Left(actual) => Some(format!("line {line}: unexpected {actual}")),
+                EitherOrBoth::Right(expected) => Some(format!("line {line}: missing {expected}")),
+            })
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn snapshot_round_trip() {
+        use crate::core::OrderBook;
+
+        let mut book = SummaryOrderBook::default();
+        let snapshot = Snapshot::from(&book);
+        assert_eq!(snapshot.to_string(), "");
+        assert_eq!("".parse::<Snapshot>().unwrap(), snapshot);
+
+        unsafe {
+            book.reset(
+                Exchange::Binance,
+                OrderBook::new_unchecked(vec![
+                    Order::new_unchecked(1.5, 0.1),
+                    Order::new_unchecked(1.25, 0.3),
+                ]),
+                OrderBook::new_unchecked(vec![Order::new_unchecked(2.5, 0.3)]),
+            );
+            book.reset(
+                Exchange::Kraken,
+                OrderBook::default(),
+                OrderBook::new_unchecked(vec![Order::new_unchecked(2.0, 0.4)]),
+            );
+        }
+        let snapshot = Snapshot::from(&book);
+        let canonical = snapshot.to_string();
+        assert_eq!(
+            canonical,
+            "Binance bid 1.5 0.1\nBinance bid 1.25 0.3\nBinance ask 2.5 0.3\nKraken ask 2 0.4\n"
+        );
+        let parsed: Snapshot = canonical.parse().unwrap();
+        assert_eq!(parsed, snapshot);
+        assert!(parsed.diff(&snapshot, 0.0).is_empty());
+
+        let golden = "Binance bid 1.5000001 0.1\nBinance bid 1.25 0.3\nBinance ask 2.5 0.35\n";
+        let golden: Snapshot = golden.parse().unwrap();
+        assert_eq!(
+            snapshot.diff(&golden, 1e-6),
+            vec![
+                "line 2: expected Binance ask 2.5 0.35, got Binance ask 2.5 0.3",
+                "line 3: unexpected Kraken ask 2 0.4",
+            ]
+        );
+
+        assert!("Binance bid 1.5".parse::<Snapshot>().is_err());
+        assert!("Nasdaq bid 1.5 0.1".parse::<Snapshot>().is_err());
+        assert!("Binance buy 1.5 0.1".parse::<Snapshot>().is_err());
+        assert!("Binance bid 1.5 x".parse::<Snapshot>().is_err());
+    }
+}
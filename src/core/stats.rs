@@ -0,0 +1,104 @@
+use std::collections::VecDeque;
+
+/// Rolling statistics of the last spreads e.g. of summaries for a volatility gauge
+/// non finite spreads i.e. of summaries missing a side are skipped
+#[derive(Debug, Clone, PartialEq)]
+pub struct SpreadStats {
+    window: usize,
+    spreads: VecDeque<f64>,
+}
+
+impl SpreadStats {
+    /// keeps up to window last spreads, panics if window is 0
+    pub fn new(window: usize) -> Self {
+        assert!(window > 0, "window should keep at least one spread");
+        Self {
+            window,
+            spreads: VecDeque::with_capacity(window),
+        }
+    }
+    /// evicts the oldest spread once the window is full
+    pub fn push(&mut self, spread: f64) {
+        if !spread.is_finite() {
+            return;
+        }
+        if self.spreads.len() == self.window {
+            self.spreads.pop_front();
+        }
+        self.spreads.push_back(spread);
+    }
+    /// number of spreads in the window
+    pub fn len(&self) -> usize {
+        self.spreads.len()
+    }
+    pub fn is_empty(&self) -> bool {
+        self.spreads.is_empty()
+    }
+    pub fn last(&self) -> Option<f64> {
+        self.spreads.back().copied()
+    }
+    pub fn min(&self) -> Option<f64> {
+        self.spreads.iter().copied().reduce(f64::min)
+    }
+    pub fn max(&self) -> Option<f64> {
+        self.spreads.iter().copied().reduce(f64::max)
+    }
+    pub fn mean(&self) -> Option<f64> {
+        (!self.is_empty()).then(|| self.spreads.iter().sum::<f64>() / self.len() as f64)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn eviction() {
+        let mut stats = SpreadStats::new(3);
+        assert_eq!(
+            (stats.min(), stats.max(), stats.mean(), stats.last()),
+            (None, None, None, None)
+        );
+        for spread in [-1.0, -4.0, -2.5] {
+            stats.push(spread);
+        }
+        assert_eq!(stats.len(), 3);
+        assert_eq!(stats.min(), Some(-4.0));
+        assert_eq!(stats.max(), Some(-1.0));
+        assert_eq!(stats.mean(), Some(-2.5));
+        assert_eq!(stats.last(), Some(-2.5));
+
+        // -1 goes first
+        stats.push(-3.5);
+        assert_eq!(stats.len(), 3);
+        assert_eq!(stats.max(), Some(-2.5));
+        assert_eq!(stats.mean(), Some(-10.0 / 3.0));
+        // then -4
+        stats.push(0.5);
+        assert_eq!(stats.min(), Some(-3.5));
+        assert_eq!(stats.last(), Some(0.5));
+    }
+
+    #[test]
+    fn non_finite() {
+        let mut stats = SpreadStats::new(2);
+        for spread in [f64::NAN, f64::INFINITY, f64::NEG_INFINITY] {
+            stats.push(spread);
+        }
+        assert!(stats.is_empty());
+        assert_eq!(stats.mean(), None);
+
+        stats.push(-1.0);
+        stats.push(f64::NAN);
+        stats.push(-2.0);
+        assert_eq!(stats.len(), 2);
+        assert_eq!(stats.mean(), Some(-1.5));
+        assert_eq!(stats.last(), Some(-2.0));
+    }
+
+    #[test]
+    #[should_panic(expected = "at least one spread")]
+    fn empty_window() {
+        SpreadStats::new(0);
+    }
+}
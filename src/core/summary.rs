@@ -0,0 +1,400 @@
+use serde::{Deserialize, Serialize};
+
+use crate::core::{round_dp, to_f64, Amount, Exchange, Price, SummaryOrder, SummaryOrderBook};
+
+/// Aggregated order level as seen by external consumers
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub struct Level {
+    pub exchange: Exchange,
+    pub price: f64,
+    pub amount: f64,
+}
+
+impl From<SummaryOrder> for Level {
+    fn from(order: SummaryOrder) -> Self {
+        Self {
+            exchange: order.exchange(),
+            price: to_f64(order.order().price().into_inner()),
+            amount: to_f64(order.order().amount().into_inner()),
+        }
+    }
+}
+
+/// serialized as the Level it converts into
+impl Serialize for SummaryOrder {
+    fn serialize<S: serde::Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        Level::from(*self).serialize(serializer)
+    }
+}
+
+/// Price serialized as a number rounded to DP decimal places
+/// e.g. to match the precision an exchange displays
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct PriceFmt<const DP: u8>(pub Price);
+
+impl<const DP: u8> Serialize for PriceFmt<DP> {
+    fn serialize<S: serde::Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.serialize_f64(round_dp(self.0.into_inner(), DP))
+    }
+}
+
+/// Amount serialized as a number rounded to DP decimal places, see PriceFmt
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct AmountFmt<const DP: u8>(pub Amount);
+
+impl<const DP: u8> Serialize for AmountFmt<DP> {
+    fn serialize<S: serde::Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.serialize_f64(round_dp(self.0.into_inner(), DP))
+    }
+}
+
+/// Stable wire message of the aggregated order book
+/// decoupled from SummaryOrderBook so it can evolve independently
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Summary {
+    /// bumped on every breaking change of the message shape
+    pub version: u32,
+    pub symbol: String,
+    /// difference between best bid and best ask
+    /// None if either side is empty
+    pub spread: Option<f64>,
+    /// best bid equals best ask, both sides are present
+    #[serde(default)]
+    pub locked: bool,
+    pub bids: Vec<Level>,
+    pub asks: Vec<Level>,
+}
+
+impl Summary {
+    pub const VERSION: u32 = 1;
+
+    pub fn new(symbol: impl Into<String>, book: &SummaryOrderBook) -> Self {
+        let bids: Vec<_> = book.bids().collect();
+        let asks: Vec<_> = book.asks().collect();
+        let spread = SummaryOrderBook::spread(bids.iter().copied(), asks.iter().copied());
+        Self {
+            version: Self::VERSION,
+            symbol: symbol.into(),
+            spread: spread.is_finite().then_some(spread),
+            locked: spread == 0.0,
+            bids: bids.into_iter().map(Level::from).collect(),
+            asks: asks.into_iter().map(Level::from).collect(),
+        }
+    }
+    /// levels added or changed since previous summary and levels gone from it
+    pub fn changes_since(&self, previous: &Summary) -> SummaryDiff {
+        let changed = |levels: &[Level], previous: &[Level]| -> Vec<Level> {
+            levels
+                .iter()
+                .filter(|level| !previous.contains(level))
+                .copied()
+                .collect()
+        };
+        let removed = |levels: &[Level], previous: &[Level]| {
+            previous
+                .iter()
+                .filter(|old| !levels.iter().any(|level| level.key() == old.key()))
+                .map(Level::key)
+                .collect::<Vec<_>>()
+        };
+        SummaryDiff {
+            version: Self::VERSION,
+            symbol: self.symbol.clone(),
+            spread: self.spread,
+            locked: self.locked,
+            changed_bids: changed(&self.bids, &previous.bids),
+            changed_asks: changed(&self.asks, &previous.asks),
+            removed: [
+                removed(&self.bids, &previous.bids),
+                removed(&self.asks, &previous.asks),
+            ]
+            .concat(),
+        }
+    }
+    /// reconstructs the summary the diff has been taken of
+    pub fn apply(&mut self, diff: &SummaryDiff) {
+        let apply = |levels: &mut Vec<Level>, changed: &[Level], ascending: bool| {
+            levels.retain(|level| !diff.removed.contains(&level.key()));
+            for level in changed {
+                match levels.iter_mut().find(|old| old.key() == level.key()) {
+                    Some(old) => *old = *level,
+                    None => levels.push(*level),
+                }
+            }
+            // the same order as SummaryOrderBook yields
+            levels.sort_by(|l, r| {
+                let price = l.price.total_cmp(&r.price);
+                (if ascending { price } else { price.reverse() })
+                    .then(r.amount.total_cmp(&l.amount))
+                    .then(l.exchange.cmp(&r.exchange))
+            });
+        };
+        apply(&mut self.bids, &diff.changed_bids, false);
+        apply(&mut self.asks, &diff.changed_asks, true);
+        self.symbol = diff.symbol.clone();
+        self.spread = diff.spread;
+        self.locked = diff.locked;
+    }
+}
+
+impl Level {
+    fn key(&self) -> (Exchange, f64) {
+        (self.exchange, self.price)
+    }
+}
+
+/// Changes of a summary relative to the previously emitted one
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct SummaryDiff {
+    pub version: u32,
+    pub symbol: String,
+    pub spread: Option<f64>,
+    pub locked: bool,
+    /// new levels and levels whose amount has changed
+    pub changed_bids: Vec<Level>,
+    pub changed_asks: Vec<Level>,
+    /// exchange and price of levels gone from either side
+    /// applied before the changed ones so a level can move between sides
+    pub removed: Vec<(Exchange, f64)>,
+}
+
+impl SummaryDiff {
+    /// true if levels of the summary haven't changed
+    pub fn is_empty(&self) -> bool {
+        self.changed_bids.is_empty() && self.changed_asks.is_empty() && self.removed.is_empty()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn serde_summary() {
+        let summary = Summary {
+            version: Summary::VERSION,
+            symbol: "ethbtc".into(),
+            spread: Some(-0.5),
+            locked: false,
+            bids: vec![Level {
+                exchange: Exchange::Binance,
+                price: 1.5,
+                amount: 0.1,
+            }],
+            asks: vec![Level {
+                exchange: Exchange::Bitstamp,
+                price: 2.0,
+                amount: 0.2,
+            }],
+        };
+        let json = serde_json::to_string(&summary).unwrap();
+        assert_eq!(
+            json,
+            r#"{"version":1,"symbol":"ethbtc","spread":-0.5,"locked":false,"bids":[{"exchange":"binance","price":1.5,"amount":0.1}],"asks":[{"exchange":"bitstamp","price":2.0,"amount":0.2}]}"#
+        );
+        assert_eq!(serde_json::from_str::<Summary>(&json).unwrap(), summary);
+
+        let summary = Summary::new("ethbtc", &SummaryOrderBook::default());
+        let json = serde_json::to_string(&summary).unwrap();
+        assert_eq!(serde_json::from_str::<Summary>(&json).unwrap(), summary);
+    }
+
+    #[test]
+    fn serde_summary_order() {
+        use crate::core::{Amount, Order, OrderBook, Price};
+
+        let order = |price: &str, amount: &str| {
+            Order::new(
+                Price::new(price.parse().unwrap()).unwrap(),
+                Amount::new(amount.parse().unwrap()).unwrap(),
+            )
+        };
+        let mut book = SummaryOrderBook::default();
+        book.reset(
+            Exchange::Kraken,
+            OrderBook::new(vec![order("1.5", "0.25")]).unwrap(),
+            OrderBook::default(),
+        );
+        let bids: Vec<_> = book.bids().collect();
+        assert_eq!(
+            serde_json::to_string(&bids).unwrap(),
+            r#"[{"exchange":"kraken","price":1.5,"amount":0.25}]"#
+        );
+    }
+
+    #[test]
+    fn serde_precision() {
+        let price = Price::new("1234.56789".parse().unwrap()).unwrap();
+        let amount = Amount::new("0.123456789".parse().unwrap()).unwrap();
+        assert_eq!(
+            serde_json::to_string(&(PriceFmt::<2>(price), AmountFmt::<2>(amount))).unwrap(),
+            "[1234.57,0.12]"
+        );
+        assert_eq!(
+            serde_json::to_string(&(PriceFmt::<8>(price), AmountFmt::<8>(amount))).unwrap(),
+            "[1234.56789,0.12345679]"
+        );
+        // half away from zero
+        let amount = Amount::new("0.125".parse().unwrap()).unwrap();
+        assert_eq!(
+            serde_json::to_string(&AmountFmt::<2>(amount)).unwrap(),
+            "0.13"
+        );
+    }
+
+    #[cfg(not(feature = "decimal"))]
+    #[test]
+    fn summary_from_book() {
+        use crate::core::{Order, OrderBook};
+
+        let mut book = SummaryOrderBook::default();
+        let summary = Summary::new("ethbtc", &book);
+        assert_eq!(summary.version, Summary::VERSION);
+        assert_eq!(summary.symbol, "ethbtc");
+        assert!(summary.spread.is_none());
+        assert!(summary.bids.is_empty() && summary.asks.is_empty());
+
+        unsafe {
+            book.reset(
+                Exchange::Binance,
+                OrderBook::new_unchecked(vec![Order::new_unchecked(1.5, 0.1)]),
+                OrderBook::new_unchecked(vec![Order::new_unchecked(2.5, 0.3)]),
+            );
+            book.reset(
+                Exchange::Bitstamp,
+                OrderBook::new_unchecked(vec![Order::new_unchecked(1.0, 0.2)]),
+                OrderBook::new_unchecked(vec![Order::new_unchecked(2.0, 0.4)]),
+            );
+        }
+        let summary = Summary::new("ethbtc", &book);
+        assert_eq!(summary.spread, Some(-0.5));
+        assert_eq!(
+            summary.bids,
+            vec![
+                Level {
+                    exchange: Exchange::Binance,
+                    price: 1.5,
+                    amount: 0.1
+                },
+                Level {
+                    exchange: Exchange::Bitstamp,
+                    price: 1.0,
+                    amount: 0.2
+                },
+            ]
+        );
+        assert_eq!(
+            summary.asks,
+            vec![
+                Level {
+                    exchange: Exchange::Bitstamp,
+                    price: 2.0,
+                    amount: 0.4
+                },
+                Level {
+                    exchange: Exchange::Binance,
+                    price: 2.5,
+                    amount: 0.3
+                },
+            ]
+        );
+    }
+
+    #[test]
+    fn summary_diff() {
+        let level = |exchange, price, amount| Level {
+            exchange,
+            price,
+            amount,
+        };
+        let previous = Summary {
+            version: Summary::VERSION,
+            symbol: "ethbtc".into(),
+            spread: Some(-0.5),
+            locked: false,
+            bids: vec![
+                level(Exchange::Binance, 1.5, 0.1),
+                level(Exchange::Bitstamp, 1.0, 0.2),
+                level(Exchange::Binance, 0.5, 0.3),
+            ],
+            asks: vec![
+                level(Exchange::Bitstamp, 2.0, 0.4),
+                level(Exchange::Binance, 2.5, 0.5),
+            ],
+        };
+        assert!(previous.changes_since(&previous).is_empty());
+
+        // only the deepest bid has changed
+        let mut summary = previous.clone();
+        summary.bids[2].amount = 0.6;
+        let diff = summary.changes_since(&previous);
+        assert_eq!(diff.changed_bids, vec![level(Exchange::Binance, 0.5, 0.6)]);
+        assert!(diff.changed_asks.is_empty());
+        assert!(diff.removed.is_empty());
+        let mut applied = previous.clone();
+        applied.apply(&diff);
+        assert_eq!(applied, summary);
+
+        // level gone from bids, new one pushed into the middle of asks
+        let mut next = summary.clone();
+        next.bids.pop();
+        next.asks.insert(1, level(Exchange::Binance, 2.2, 0.7));
+        let diff = next.changes_since(&summary);
+        assert!(diff.changed_bids.is_empty());
+        assert_eq!(diff.changed_asks, vec![level(Exchange::Binance, 2.2, 0.7)]);
+        assert_eq!(diff.removed, vec![(Exchange::Binance, 0.5)]);
+        applied.apply(&diff);
+        assert_eq!(applied, next);
+
+        // empty previous summary
+        let empty = Summary {
+            bids: vec![],
+            asks: vec![],
+            spread: None,
+            ..previous.clone()
+        };
+        let mut applied = empty.clone();
+        applied.apply(&previous.changes_since(&empty));
+        assert_eq!(applied, previous);
+    }
+
+    #[cfg(not(feature = "decimal"))]
+    #[test]
+    fn locked_summary() {
+        use crate::core::{Order, OrderBook};
+
+        let mut book = SummaryOrderBook::default();
+        unsafe {
+            book.reset(
+                Exchange::Binance,
+                OrderBook::new_unchecked(vec![Order::new_unchecked(2.0, 0.1)]),
+                OrderBook::new_unchecked(vec![Order::new_unchecked(2.5, 0.3)]),
+            );
+            book.reset(
+                Exchange::Bitstamp,
+                OrderBook::new_unchecked(vec![Order::new_unchecked(1.0, 0.2)]),
+                OrderBook::new_unchecked(vec![Order::new_unchecked(2.0, 0.4)]),
+            );
+        }
+        let summary = Summary::new("ethbtc", &book);
+        assert_eq!(summary.spread, Some(0.0));
+        assert!(summary.locked);
+
+        // one sided book has no spread to be locked
+        let mut book = SummaryOrderBook::default();
+        unsafe {
+            book.reset(
+                Exchange::Binance,
+                OrderBook::new_unchecked(vec![Order::new_unchecked(2.0, 0.1)]),
+                OrderBook::default(),
+            );
+        }
+        let summary = Summary::new("ethbtc", &book);
+        assert!(summary.spread.is_none());
+        assert!(!summary.locked);
+
+        // messages without the flag are still accepted
+        let json = r#"{"version":1,"symbol":"ethbtc","spread":null,"bids":[],"asks":[]}"#;
+        assert!(!serde_json::from_str::<Summary>(json).unwrap().locked);
+    }
+}
@@ -1,58 +1,60 @@
 use itertools::Itertools;
 
-use crate::core::*;
+use crate::{core::*, symbol::Symbol};
 
+#[cfg(not(feature = "decimal"))]
 #[test]
 fn invalid_price() {
     assert!(Price::new(f64::NAN).is_err_and(|v| v.is_nan()));
-    assert!(Price::new(0.0).contains_err(&0.0));
-    assert!(Price::new(-0.1).contains_err(&-0.1));
+    assert!(Price::new(n(0.0)).contains_err(&0.0));
+    assert!(Price::new(n(-0.1)).contains_err(&-0.1));
     assert!(Price::new(f64::INFINITY).contains_err(&f64::INFINITY));
     assert!(Price::new(f64::NEG_INFINITY).contains_err(&f64::NEG_INFINITY));
 }
 
 #[test]
 fn valid_price() {
-    assert!(Price::new(0.1).is_ok_and(|v| v.into_inner() == 0.1));
+    assert!(Price::new(n(0.1)).is_ok_and(|v| v.into_inner() == n(0.1)));
 }
 
 #[test]
 fn compare_price() {
-    assert_eq!(Price::new(0.1), Price::new(0.1));
-    assert_eq!(Price::new(0.2), Price::new(0.2));
-    assert_ne!(Price::new(0.2), Price::new(0.3));
-    assert_ne!(Price::new(0.3), Price::new(0.2));
-    assert!(Price::new(0.1) < Price::new(0.2));
-    assert!(Price::new(0.2) > Price::new(0.1));
-    assert!(Price::new(0.2) < Price::new(0.3));
-    assert!(Price::new(0.3) > Price::new(0.2));
+    assert_eq!(Price::new(n(0.1)), Price::new(n(0.1)));
+    assert_eq!(Price::new(n(0.2)), Price::new(n(0.2)));
+    assert_ne!(Price::new(n(0.2)), Price::new(n(0.3)));
+    assert_ne!(Price::new(n(0.3)), Price::new(n(0.2)));
+    assert!(Price::new(n(0.1)) < Price::new(n(0.2)));
+    assert!(Price::new(n(0.2)) > Price::new(n(0.1)));
+    assert!(Price::new(n(0.2)) < Price::new(n(0.3)));
+    assert!(Price::new(n(0.3)) > Price::new(n(0.2)));
 }
 
+#[cfg(not(feature = "decimal"))]
 #[test]
 fn invalid_amount() {
     assert!(Amount::new(f64::NAN).is_err_and(|v| v.is_nan()));
-    assert!(Amount::new(-0.1).contains_err(&-0.1));
+    assert!(Amount::new(n(-0.1)).contains_err(&-0.1));
     assert!(Amount::new(f64::INFINITY).contains_err(&f64::INFINITY));
     assert!(Amount::new(f64::NEG_INFINITY).contains_err(&f64::NEG_INFINITY));
 }
 
 #[test]
 fn valid_amount() {
-    assert_eq!(Amount::default().into_inner(), 0.0);
-    assert!(Amount::new(0.0).is_ok_and(|v| v.0 == 0.0));
-    assert!(Amount::new(0.1).is_ok_and(|v| v.0 == 0.1));
+    assert_eq!(Amount::default().into_inner(), n(0.0));
+    assert!(Amount::new(n(0.0)).is_ok_and(|v| v.0 == n(0.0)));
+    assert!(Amount::new(n(0.1)).is_ok_and(|v| v.0 == n(0.1)));
 }
 
 #[test]
 fn compare_amount() {
-    assert_eq!(Amount::new(0.0), Amount::new(0.0));
-    assert_eq!(Amount::new(0.1), Amount::new(0.1));
-    assert_ne!(Amount::new(0.2), Amount::new(0.3));
-    assert_ne!(Amount::new(0.3), Amount::new(0.2));
-    assert!(Amount::new(0.1) < Amount::new(0.2));
-    assert!(Amount::new(0.2) > Amount::new(0.1));
-    assert!(Amount::new(0.2) < Amount::new(0.3));
-    assert!(Amount::new(0.3) > Amount::new(0.2));
+    assert_eq!(Amount::new(n(0.0)), Amount::new(n(0.0)));
+    assert_eq!(Amount::new(n(0.1)), Amount::new(n(0.1)));
+    assert_ne!(Amount::new(n(0.2)), Amount::new(n(0.3)));
+    assert_ne!(Amount::new(n(0.3)), Amount::new(n(0.2)));
+    assert!(Amount::new(n(0.1)) < Amount::new(n(0.2)));
+    assert!(Amount::new(n(0.2)) > Amount::new(n(0.1)));
+    assert!(Amount::new(n(0.2)) < Amount::new(n(0.3)));
+    assert!(Amount::new(n(0.3)) > Amount::new(n(0.2)));
 }
 
 #[test]
@@ -62,14 +64,22 @@ fn invalid_order_book_diff_asks() {
             Order::new_unchecked(0.2, 0.1),
             Order::new_unchecked(0.2, 0.2),
         ];
-        assert!(OrderBookDiffAsks::new(orders)
-            .contains_err(&OrderBookError::HasOrderWithNotUniquePrice));
+        assert!(OrderBookDiffAsks::new(orders).contains_err(
+            &OrderBookError::HasOrderWithNotUniquePrice {
+                price: Price(n(0.2)),
+                index: 1
+            }
+        ));
         let orders = vec![
             Order::new_unchecked(0.3, 0.1),
             Order::new_unchecked(0.2, 0.2),
         ];
-        assert!(OrderBookDiffAsks::new_sorted(orders)
-            .contains_err(&OrderBookError::OrdersNotSortedAccordingToQuoteType));
+        assert!(OrderBookDiffAsks::new_sorted(orders).contains_err(
+            &OrderBookError::OrdersNotSortedAccordingToQuoteType {
+                price: Price(n(0.2)),
+                index: 1
+            }
+        ));
     }
 }
 
@@ -77,20 +87,32 @@ fn invalid_order_book_diff_asks() {
 fn invalid_order_book_asks() {
     unsafe {
         let orders = vec![Order::new_unchecked(0.2, 0.0)];
-        assert!(OrderBookAsks::new(orders).contains_err(&OrderBookError::HasOrderWithEmptyAmount));
+        assert!(OrderBookAsks::new(orders).contains_err(
+            &OrderBookError::HasOrderWithEmptyAmount {
+                price: Price(n(0.2)),
+                index: 0
+            }
+        ));
         let orders = vec![
             Order::new_unchecked(0.2, 0.1),
             Order::new_unchecked(0.2, 0.2),
         ];
-        assert!(
-            OrderBookAsks::new(orders).contains_err(&OrderBookError::HasOrderWithNotUniquePrice)
-        );
+        assert!(OrderBookAsks::new(orders).contains_err(
+            &OrderBookError::HasOrderWithNotUniquePrice {
+                price: Price(n(0.2)),
+                index: 1
+            }
+        ));
         let orders = vec![
             Order::new_unchecked(0.3, 0.1),
             Order::new_unchecked(0.2, 0.2),
         ];
-        assert!(OrderBookAsks::new_sorted(orders)
-            .contains_err(&OrderBookError::OrdersNotSortedAccordingToQuoteType));
+        assert!(OrderBookAsks::new_sorted(orders).contains_err(
+            &OrderBookError::OrdersNotSortedAccordingToQuoteType {
+                price: Price(n(0.2)),
+                index: 1
+            }
+        ));
     }
 }
 
@@ -141,14 +163,22 @@ fn invalid_order_book_diff_bids() {
             Order::new_unchecked(0.2, 0.1),
             Order::new_unchecked(0.2, 0.2),
         ];
-        assert!(OrderBookDiffBids::new(orders)
-            .contains_err(&OrderBookError::HasOrderWithNotUniquePrice));
+        assert!(OrderBookDiffBids::new(orders).contains_err(
+            &OrderBookError::HasOrderWithNotUniquePrice {
+                price: Price(n(0.2)),
+                index: 1
+            }
+        ));
         let orders = vec![
             Order::new_unchecked(0.2, 0.1),
             Order::new_unchecked(0.3, 0.2),
         ];
-        assert!(OrderBookDiffBids::new_sorted(orders)
-            .contains_err(&OrderBookError::OrdersNotSortedAccordingToQuoteType));
+        assert!(OrderBookDiffBids::new_sorted(orders).contains_err(
+            &OrderBookError::OrdersNotSortedAccordingToQuoteType {
+                price: Price(n(0.3)),
+                index: 1
+            }
+        ));
     }
 }
 
@@ -156,20 +186,32 @@ fn invalid_order_book_diff_bids() {
 fn invalid_order_book_bids() {
     unsafe {
         let orders = vec![Order::new_unchecked(0.2, 0.0)];
-        assert!(OrderBookBids::new(orders).contains_err(&OrderBookError::HasOrderWithEmptyAmount));
+        assert!(OrderBookBids::new(orders).contains_err(
+            &OrderBookError::HasOrderWithEmptyAmount {
+                price: Price(n(0.2)),
+                index: 0
+            }
+        ));
         let orders = vec![
             Order::new_unchecked(0.2, 0.1),
             Order::new_unchecked(0.2, 0.2),
         ];
-        assert!(
-            OrderBookBids::new(orders).contains_err(&OrderBookError::HasOrderWithNotUniquePrice)
-        );
+        assert!(OrderBookBids::new(orders).contains_err(
+            &OrderBookError::HasOrderWithNotUniquePrice {
+                price: Price(n(0.2)),
+                index: 1
+            }
+        ));
         let orders = vec![
             Order::new_unchecked(0.2, 0.1),
             Order::new_unchecked(0.3, 0.2),
         ];
-        assert!(OrderBookBids::new_sorted(orders)
-            .contains_err(&OrderBookError::OrdersNotSortedAccordingToQuoteType));
+        assert!(OrderBookBids::new_sorted(orders).contains_err(
+            &OrderBookError::OrdersNotSortedAccordingToQuoteType {
+                price: Price(n(0.3)),
+                index: 1
+            }
+        ));
     }
 }
 
@@ -519,23 +561,39 @@ fn update_asks() {
     }
 }
 
+/// either Number compared as f64 so assert_feq takes literals on the right
+trait AsF64 {
+    fn as_f64(&self) -> f64;
+}
+impl AsF64 for f64 {
+    fn as_f64(&self) -> f64 {
+        *self
+    }
+}
+#[cfg(feature = "decimal")]
+impl AsF64 for Number {
+    fn as_f64(&self) -> f64 {
+        to_f64(*self)
+    }
+}
+
 macro_rules! assert_feq {
     ($left:expr, $right:expr $(,)?) => {
-        match (&$left, &$right) {
+        match (AsF64::as_f64(&$left), AsF64::as_f64(&$right)) {
             (left_val, right_val) => {
-                if (*left_val - *right_val).abs() > 1E-15 {
-                    panic!("left: {} not equal to right: {}", &*left_val, &*right_val);
+                if (left_val - right_val).abs() > 1E-15 {
+                    panic!("left: {} not equal to right: {}", left_val, right_val);
                 }
             }
         }
     };
     ($left:expr, $right:expr, $delta:expr $(,)?) => {
-        match (&$left, &$right, &$delta) {
+        match (AsF64::as_f64(&$left), AsF64::as_f64(&$right), $delta) {
             (left_val, right_val, delta_val) => {
-                if (*left_val - *right_val).abs() > *delta_val {
+                if (left_val - right_val).abs() > delta_val {
                     panic!(
                         "left: {} not equal to right: {} with precision: {}",
-                        &*left_val, &*right_val, &*delta_val
+                        left_val, right_val, delta_val
                     );
                 }
             }
@@ -788,3 +846,2029 @@ fn reset_summary() {
         assert!(summary.bids().eq(bids.into_iter()));
     }
 }
+
+#[test]
+fn order_value() {
+    unsafe {
+        assert_feq!(Order::new_unchecked(2.0, 1.5).value(), 3.0);
+        assert_feq!(Order::new_unchecked(0.1, 0.0).value(), 0.0);
+    }
+}
+
+#[test]
+fn total_order_book() {
+    assert!(OrderBookBids::default()
+        .total_amount()
+        .is_ok_and(|a| a == Amount::default()));
+    assert_feq!(OrderBookBids::default().total_value(), 0.0);
+
+    unsafe {
+        let bids = OrderBookBids::new_unchecked(vec![
+            Order::new_unchecked(2.0, 1.5),
+            Order::new_unchecked(1.5, 1.0),
+            Order::new_unchecked(0.5, 2.5),
+        ]);
+        assert!(bids.total_amount().is_ok_and(|a| a.into_inner() == n(5.0)));
+        assert_feq!(bids.total_value(), 5.75);
+    }
+}
+
+#[test]
+#[cfg(not(feature = "decimal"))]
+fn total_order_book_overflow() {
+    unsafe {
+        let asks = OrderBookAsks::new_unchecked(vec![
+            Order::new_unchecked(1.0, f64::MAX),
+            Order::new_unchecked(2.0, f64::MAX),
+        ]);
+        assert!(asks.total_amount().is_err_and(|v| v.is_infinite()));
+    }
+}
+
+#[test]
+fn conversion_summary() {
+    let mut summary = SummaryOrderBook::default();
+
+    unsafe {
+        summary.reset(
+            Exchange::Binance,
+            OrderBook::new_unchecked(vec![
+                Order::new_unchecked(2.0, 0.1),
+                Order::new_unchecked(1.0, 0.2),
+            ]),
+            OrderBook::new_unchecked(vec![
+                Order::new_unchecked(3.0, 0.1),
+                Order::new_unchecked(4.0, 0.2),
+            ]),
+        );
+        summary.reset(
+            Exchange::Bitstamp,
+            OrderBook::new_unchecked(vec![
+                Order::new_unchecked(0.75, 1.1),
+                Order::new_unchecked(0.25, 1.2),
+            ]),
+            OrderBook::new_unchecked(vec![
+                Order::new_unchecked(1.25, 1.1),
+                Order::new_unchecked(2.25, 1.2),
+            ]),
+        );
+        summary.set_conversion(Exchange::Bitstamp, Price::new_unchecked(n(2.0)));
+
+        let bids = vec![
+            SummaryOrder(Exchange::Binance, Order::new_unchecked(2.0, 0.1)),
+            SummaryOrder(Exchange::Bitstamp, Order::new_unchecked(1.5, 1.1)),
+            SummaryOrder(Exchange::Binance, Order::new_unchecked(1.0, 0.2)),
+            SummaryOrder(Exchange::Bitstamp, Order::new_unchecked(0.5, 1.2)),
+        ];
+        let asks = vec![
+            SummaryOrder(Exchange::Bitstamp, Order::new_unchecked(2.5, 1.1)),
+            SummaryOrder(Exchange::Binance, Order::new_unchecked(3.0, 0.1)),
+            SummaryOrder(Exchange::Binance, Order::new_unchecked(4.0, 0.2)),
+            SummaryOrder(Exchange::Bitstamp, Order::new_unchecked(4.5, 1.2)),
+        ];
+        assert!(summary.bids().eq(bids.into_iter()));
+        assert!(summary.asks().eq(asks.into_iter()));
+    }
+}
+
+#[test]
+fn imbalance_summary() {
+    let mut summary = SummaryOrderBook::default();
+    assert!(summary.imbalance(BEST_ORDER_BOOK_SIZE).is_none());
+
+    unsafe {
+        summary.reset(
+            Exchange::Binance,
+            OrderBook::new_unchecked(vec![
+                Order::new_unchecked(2.0, 3.0),
+                Order::new_unchecked(1.0, 1.0),
+            ]),
+            OrderBook::default(),
+        );
+        assert!(summary.imbalance(0).is_none());
+        assert!(summary.imbalance(1).is_some_and(|i| i == 1.0));
+
+        summary.reset(
+            Exchange::Bitstamp,
+            OrderBook::default(),
+            OrderBook::new_unchecked(vec![
+                Order::new_unchecked(3.0, 1.0),
+                Order::new_unchecked(4.0, 4.0),
+            ]),
+        );
+        assert_feq!(summary.imbalance(1).unwrap(), 0.5);
+        assert_feq!(summary.imbalance(2).unwrap(), -0.1111111111111111);
+        assert_feq!(
+            summary.imbalance(BEST_ORDER_BOOK_SIZE).unwrap(),
+            -0.1111111111111111
+        );
+    }
+}
+
+#[test]
+fn cumulative_order_book() {
+    assert_eq!(OrderBookAsks::default().cumulative().count(), 0);
+
+    unsafe {
+        let asks = OrderBookAsks::new_unchecked(vec![
+            Order::new_unchecked(0.5, 2.5),
+            Order::new_unchecked(1.5, 1.0),
+            Order::new_unchecked(2.0, 1.5),
+        ]);
+        assert!(asks.cumulative().eq(vec![
+            (Price(n(0.5)), Amount(n(2.5))),
+            (Price(n(1.5)), Amount(n(3.5))),
+            (Price(n(2.0)), Amount(n(5.0))),
+        ]));
+        assert_eq!(
+            asks.cumulative().last().map(|(_, total)| total),
+            asks.total_amount().ok()
+        );
+    }
+}
+
+#[test]
+#[cfg(not(feature = "decimal"))]
+fn cumulative_order_book_overflow() {
+    unsafe {
+        let bids = OrderBookBids::new_unchecked(vec![
+            Order::new_unchecked(2.0, f64::MAX),
+            Order::new_unchecked(1.5, f64::MAX),
+        ]);
+        assert!(bids.cumulative().eq(vec![(Price(2.0), Amount(f64::MAX))]));
+    }
+}
+
+#[test]
+fn cumulative_summary() {
+    let mut summary = SummaryOrderBook::default();
+    assert_eq!(summary.cumulative_bids().count(), 0);
+    assert_eq!(summary.cumulative_asks().count(), 0);
+
+    unsafe {
+        summary.reset(
+            Exchange::Binance,
+            OrderBook::new_unchecked(vec![
+                Order::new_unchecked(2.0, 0.5),
+                Order::new_unchecked(1.0, 1.0),
+            ]),
+            OrderBook::new_unchecked(vec![Order::new_unchecked(3.0, 0.25)]),
+        );
+        summary.reset(
+            Exchange::Bitstamp,
+            OrderBook::new_unchecked(vec![Order::new_unchecked(1.5, 2.0)]),
+            OrderBook::new_unchecked(vec![Order::new_unchecked(2.5, 0.5)]),
+        );
+    }
+    assert!(summary.cumulative_bids().eq(vec![
+        (Price(n(2.0)), Amount(n(0.5))),
+        (Price(n(1.5)), Amount(n(2.5))),
+        (Price(n(1.0)), Amount(n(3.5))),
+    ]));
+    assert!(summary.cumulative_asks().eq(vec![
+        (Price(n(2.5)), Amount(n(0.5))),
+        (Price(n(3.0)), Amount(n(0.75))),
+    ]));
+}
+
+#[test]
+fn map_prices() {
+    unsafe {
+        let asks = OrderBookAsks::new_unchecked(vec![
+            Order::new_unchecked(1.0, 0.1),
+            Order::new_unchecked(2.0, 0.2),
+            Order::new_unchecked(3.0, 0.3),
+        ]);
+        let fees = asks.map_prices(|p| Price(p.0 * n(2.0))).unwrap();
+        assert_eq!(
+            fees.0 .0,
+            vec![
+                Order::new_unchecked(2.0, 0.1),
+                Order::new_unchecked(4.0, 0.2),
+                Order::new_unchecked(6.0, 0.3),
+            ]
+        );
+
+        // not monotonic, 1.0 and 3.0 collapse into 1.5
+        let skewed = asks
+            .map_prices(|p| Price((p.0 - n(2.0)).abs() + n(0.5)))
+            .unwrap();
+        assert_eq!(
+            skewed.0 .0,
+            vec![
+                Order::new_unchecked(0.5, 0.2),
+                Order::new_unchecked(1.5, 0.4),
+            ]
+        );
+        assert!(OrderBookAsks::new_sorted(skewed.0 .0.clone()).is_ok_and(|a| a == skewed));
+
+        let bids = OrderBookDiffBids::new_unchecked(vec![
+            Order::new_unchecked(4.0, 0.3),
+            Order::new_unchecked(2.0, 0.0),
+            Order::new_unchecked(1.0, 0.1),
+        ]);
+        let inverted = bids.map_prices(|p| Price(n(1.0) / p.0)).unwrap();
+        assert_eq!(
+            inverted.0,
+            vec![
+                Order::new_unchecked(1.0, 0.1),
+                Order::new_unchecked(0.5, 0.0),
+                Order::new_unchecked(0.25, 0.3),
+            ]
+        );
+    }
+}
+
+#[test]
+#[cfg(not(feature = "decimal"))]
+fn map_prices_overflow() {
+    unsafe {
+        let bids = OrderBookDiffBids::new_unchecked(vec![
+            Order::new_unchecked(3.0, f64::MAX),
+            Order::new_unchecked(1.0, f64::MAX),
+        ]);
+        assert!(bids
+            .map_prices(|_| Price(1.0))
+            .is_err_and(|v| v.is_infinite()));
+    }
+}
+
+#[test]
+fn round_to_tick() {
+    unsafe {
+        let tick = Price::new_unchecked(n(0.5));
+        let bids = OrderBookDiffBids::new_unchecked(vec![
+            Order::new_unchecked(2.1, 0.1),
+            Order::new_unchecked(1.9, 0.2),
+            Order::new_unchecked(1.6, 0.0),
+            Order::new_unchecked(1.2, 0.3),
+            Order::new_unchecked(0.2, 0.4),
+        ]);
+        let expected = OrderBookDiffBids::new_unchecked(vec![
+            Order::new_unchecked(2.0, 0.30000000000000004),
+            Order::new_unchecked(1.5, 0.0),
+            Order::new_unchecked(1.0, 0.3),
+            Order::new_unchecked(0.5, 0.4),
+        ]);
+        assert!(bids.round_to_tick(tick).is_ok_and(|b| b == expected));
+
+        let asks = OrderBookAsks::new_unchecked(vec![
+            Order::new_unchecked(1.0, 0.1),
+            Order::new_unchecked(1.1, 0.2),
+            Order::new_unchecked(1.3, 0.3),
+        ]);
+        let expected = OrderBookAsks::new_unchecked(vec![
+            Order::new_unchecked(1.0, 0.30000000000000004),
+            Order::new_unchecked(1.5, 0.3),
+        ]);
+        assert!(asks.round_to_tick(tick).is_ok_and(|a| a == expected));
+    }
+}
+
+#[test]
+fn infer_tick() {
+    assert!(OrderBookBids::default().infer_tick().is_none());
+
+    unsafe {
+        let bids = OrderBookBids::new_unchecked(vec![Order::new_unchecked(2.0, 0.1)]);
+        assert!(bids.infer_tick().is_none());
+
+        let bids = OrderBookBids::new_unchecked(vec![
+            Order::new_unchecked(100.05, 0.1),
+            Order::new_unchecked(100.04, 0.2),
+            Order::new_unchecked(100.02, 0.3),
+            Order::new_unchecked(99.99, 0.4),
+            Order::new_unchecked(99.95, 0.5),
+        ]);
+        assert_feq!(bids.infer_tick().unwrap(), 0.01, 1e-9);
+
+        let asks = OrderBookAsks::new_unchecked(vec![
+            Order::new_unchecked(0.5, 0.1),
+            Order::new_unchecked(1.5, 0.2),
+            Order::new_unchecked(2.5, 0.3),
+            Order::new_unchecked(4.0, 0.4),
+        ]);
+        assert_feq!(asks.infer_tick().unwrap(), 0.5);
+    }
+}
+
+#[test]
+fn checksum() {
+    let no_text = |_: &Order| -> (&str, &str) { unreachable!("no levels") };
+    assert_eq!(OrderBookBids::default().checksum(10, no_text), 0);
+    assert!(OrderBookBids::default()
+        .verify_checksum(10, no_text, 0)
+        .is_ok());
+
+    // levels of the example above as the exchange sends them
+    let levels = [
+        (0.05005, "0.05005", "0.00000500"),
+        (0.0501, "0.05010", "0.00000500"),
+        (0.0502, "0.05020", "1.00000000"),
+    ];
+    let text = |order: &Order| {
+        let (_, price, amount) = levels
+            .iter()
+            .find(|(price, ..)| Price(n(*price)) == order.price())
+            .unwrap();
+        (*price, *amount)
+    };
+    unsafe {
+        let asks = OrderBookAsks::new_unchecked(
+            levels
+                .iter()
+                .map(|(price, ..)| Order::new_unchecked(*price, 1.0))
+                .collect(),
+        );
+        let expected = crc32fast::hash(b"50055005010500");
+        assert_eq!(asks.checksum(2, text), expected);
+        assert!(asks.verify_checksum(2, text, expected).is_ok());
+        // the third level counts once it is among the best ones
+        assert!(asks
+            .verify_checksum(3, text, expected)
+            .contains_err(&OrderBookError::ChecksumMismatch));
+        assert_eq!(
+            asks.checksum(3, text),
+            crc32fast::hash(b"500550050105005020100000000")
+        );
+    }
+}
+
+#[test]
+fn display_order_book() {
+    assert_eq!(
+        OrderBookBids::default().to_string(),
+        "         bid       amount"
+    );
+
+    unsafe {
+        let asks = OrderBookAsks::new_unchecked(vec![
+            Order::new_unchecked(0.5, 2.5),
+            Order::new_unchecked(1.25, 10.0),
+        ]);
+        assert_eq!(
+            asks.to_string(),
+            [
+                "         ask       amount",
+                "         0.5          2.5",
+                "        1.25           10",
+            ]
+            .join("\n")
+        );
+    }
+}
+
+#[test]
+fn display_summary() {
+    let mut summary = SummaryOrderBook::default();
+
+    unsafe {
+        summary.reset(
+            Exchange::Binance,
+            OrderBook::new_unchecked(vec![
+                Order::new_unchecked(2.0, 0.5),
+                Order::new_unchecked(1.0, 1.0),
+            ]),
+            OrderBook::new_unchecked(vec![Order::new_unchecked(3.0, 0.25)]),
+        );
+    }
+    assert_eq!(
+        summary.to_string(),
+        [
+            "                                   spread -1                                   ",
+            "    exchange       amount          bid | ask          amount       exchange    ",
+            "     Binance          0.5            2 | 3            0.25         Binance     ",
+            "     Binance            1            1 |                                       ",
+        ]
+        .join("\n")
+    );
+}
+
+#[test]
+fn inverse_summary() {
+    let mut summary = SummaryOrderBook::default();
+    summary.set_inverse(Exchange::Bitstamp, true);
+
+    unsafe {
+        summary.reset(
+            Exchange::Binance,
+            OrderBook::new_unchecked(vec![
+                Order::new_unchecked(2.0, 0.1),
+                Order::new_unchecked(1.0, 0.2),
+            ]),
+            OrderBook::new_unchecked(vec![
+                Order::new_unchecked(4.0, 0.1),
+                Order::new_unchecked(5.0, 0.2),
+            ]),
+        );
+        // quoted as 1/price, so its bids are spot asks and the other way around
+        summary.reset(
+            Exchange::Bitstamp,
+            OrderBook::new_unchecked(vec![
+                Order::new_unchecked(0.25, 1.1),
+                Order::new_unchecked(0.2, 1.2),
+            ]),
+            OrderBook::new_unchecked(vec![
+                Order::new_unchecked(0.5, 1.3),
+                Order::new_unchecked(1.0, 1.4),
+            ]),
+        );
+
+        let bids = vec![
+            SummaryOrder(Exchange::Bitstamp, Order::new_unchecked(2.0, 1.3)),
+            SummaryOrder(Exchange::Binance, Order::new_unchecked(2.0, 0.1)),
+            SummaryOrder(Exchange::Bitstamp, Order::new_unchecked(1.0, 1.4)),
+            SummaryOrder(Exchange::Binance, Order::new_unchecked(1.0, 0.2)),
+        ];
+        let asks = vec![
+            SummaryOrder(Exchange::Bitstamp, Order::new_unchecked(4.0, 1.1)),
+            SummaryOrder(Exchange::Binance, Order::new_unchecked(4.0, 0.1)),
+            SummaryOrder(Exchange::Bitstamp, Order::new_unchecked(5.0, 1.2)),
+            SummaryOrder(Exchange::Binance, Order::new_unchecked(5.0, 0.2)),
+        ];
+        assert!(summary.bids().eq(bids.into_iter()));
+        assert!(summary.asks().eq(asks.into_iter()));
+    }
+}
+
+#[test]
+fn conform_order() {
+    let tick = Price::new(n(0.01)).unwrap();
+    let lot = Amount::new(n(0.001)).unwrap();
+
+    let order = Order::conform(n(1.23456), n(0.12345), tick, lot).unwrap();
+    assert_feq!(order.price().into_inner(), 1.23);
+    assert_feq!(order.amount().into_inner(), 0.123);
+    let order = Order::conform(n(1.235001), n(0.1236), tick, lot).unwrap();
+    assert_feq!(order.price().into_inner(), 1.24);
+    assert_feq!(order.amount().into_inner(), 0.124);
+    // already on the grid
+    let order = Order::conform(n(0.3), n(0.3), tick, lot).unwrap();
+    assert_feq!(order.price().into_inner(), 0.3);
+    assert_feq!(order.amount().into_inner(), 0.3);
+    // no lot
+    let order = Order::conform(n(1.0), n(0.12345), tick, Amount::default()).unwrap();
+    assert_feq!(order.amount().into_inner(), 0.12345);
+
+    assert_eq!(
+        Order::conform(n(1.0), n(0.0004), tick, lot),
+        Err(OrderError::EmptyAmount)
+    );
+    assert_eq!(
+        Order::conform(n(0.004), n(0.1), tick, lot),
+        Err(OrderError::InvalidPrice(n(0.0)))
+    );
+    assert_eq!(
+        Order::conform(n(1.0), n(-0.1), tick, lot),
+        Err(OrderError::InvalidAmount(n(-0.1)))
+    );
+}
+
+#[test]
+fn order_book_error_context() {
+    unsafe {
+        let orders = vec![
+            Order::new_unchecked(0.1, 0.1),
+            Order::new_unchecked(0.2, 0.1),
+            Order::new_unchecked(0.4, 0.1),
+            Order::new_unchecked(0.3, 0.2),
+        ];
+        let error = OrderBookAsks::new_sorted(orders).unwrap_err();
+        assert_eq!(
+            error,
+            OrderBookError::OrdersNotSortedAccordingToQuoteType {
+                price: Price(n(0.3)),
+                index: 3
+            }
+        );
+        assert_eq!(
+            error.to_string(),
+            "order book is not properly sorted, order with price 0.3 at 3 is out of place"
+        );
+
+        let orders = vec![
+            Order::new_unchecked(0.3, 0.1),
+            Order::new_unchecked(0.2, 0.1),
+            Order::new_unchecked(0.2, 0.2),
+        ];
+        let error = OrderBookDiffBids::new(orders).unwrap_err();
+        assert_eq!(
+            error.to_string(),
+            "order book has multiple orders with price 0.2, the second one at 2"
+        );
+
+        let orders = vec![
+            Order::new_unchecked(0.3, 0.1),
+            Order::new_unchecked(0.2, 0.0),
+        ];
+        let error = OrderBookBids::new(orders).unwrap_err();
+        assert_eq!(
+            error.to_string(),
+            "order book has order with 0 amount at price 0.2 at 1"
+        );
+    }
+}
+
+#[test]
+fn try_new_order() {
+    assert!(
+        Order::try_new(n(0.2), n(0.1)).is_ok_and(
+            |order| order == Order(Price(n(0.2)), Amount(n(0.1)), AmountDenomination::Base)
+        )
+    );
+    assert!(Order::try_new(n(0.2), n(0.0)).is_ok_and(|order| order.is_empty()));
+
+    assert_eq!(
+        Order::try_new(n(0.0), n(0.1)),
+        Err(OrderError::InvalidPrice(n(0.0)))
+    );
+    assert_eq!(
+        Order::try_new(n(-0.2), n(0.1)),
+        Err(OrderError::InvalidPrice(n(-0.2)))
+    );
+    assert_eq!(
+        Order::try_new(n(0.2), n(-0.1)),
+        Err(OrderError::InvalidAmount(n(-0.1)))
+    );
+    // price is checked first
+    assert_eq!(
+        Order::try_new(n(0.0), n(-0.1)),
+        Err(OrderError::InvalidPrice(n(0.0)))
+    );
+}
+
+#[test]
+#[cfg(not(feature = "decimal"))]
+fn try_new_order_non_finite() {
+    assert!(
+        matches!(Order::try_new(f64::NAN, 0.1), Err(OrderError::InvalidPrice(p)) if p.is_nan())
+    );
+    assert_eq!(
+        Order::try_new(0.2, f64::INFINITY),
+        Err(OrderError::InvalidAmount(f64::INFINITY))
+    );
+}
+
+#[test]
+fn amount_arithmetic() {
+    assert_eq!(Amount(n(0.1)) + Amount(n(0.2)), Ok(Amount(n(0.1) + n(0.2))));
+    assert_eq!(Amount(n(0.3)) - Amount(n(0.1)), Ok(Amount(n(0.3) - n(0.1))));
+    assert_eq!(Amount(n(0.3)) - Amount(n(0.3)), Ok(Amount::default()));
+    assert_eq!(Amount(n(0.1)) - Amount(n(0.3)), Err(n(0.1) - n(0.3)));
+    assert_eq!(
+        Amount(n(0.1)).checked_sub(Amount(n(0.3))),
+        Err(n(0.1) - n(0.3))
+    );
+}
+
+#[test]
+#[cfg(not(feature = "decimal"))]
+fn amount_overflow() {
+    // overflow to infinity
+    assert_eq!(Amount(f64::MAX) + Amount(f64::MAX), Err(f64::INFINITY));
+    assert_eq!(
+        Amount(f64::MAX).checked_add(Amount(f64::MAX)),
+        Err(f64::INFINITY)
+    );
+    assert_eq!(Amount(f64::MAX) - Amount(f64::MAX), Ok(Amount::default()));
+}
+
+#[test]
+fn price_arithmetic() {
+    assert_feq!(Price(n(2.5)) - Price(n(2.0)), 0.5);
+    assert_feq!(Price(n(2.0)) - Price(n(2.5)), -0.5);
+}
+
+#[test]
+#[cfg(not(feature = "decimal"))]
+fn price_overflow() {
+    assert_eq!(Price(f64::MAX) - Price(f64::MIN_POSITIVE), f64::MAX);
+    assert_eq!(Price(f64::MAX) - Price(f64::MAX), 0.0);
+}
+
+#[test]
+fn simulate_market_order() {
+    let mut summary = SummaryOrderBook::default();
+    let fill = summary.simulate_market_buy(Amount(n(1.0)));
+    assert_eq!(fill.average_price, None);
+    assert_eq!(fill.cost, 0.0);
+    assert_eq!(fill.filled, Amount::default());
+    assert_eq!(fill.remaining, Amount(n(1.0)));
+    assert!(!fill.is_complete());
+
+    unsafe {
+        summary.reset(
+            Exchange::Binance,
+            OrderBook::new_unchecked(vec![
+                Order::new_unchecked(2.0, 0.5),
+                Order::new_unchecked(1.0, 1.0),
+            ]),
+            OrderBook::new_unchecked(vec![
+                Order::new_unchecked(3.0, 0.5),
+                Order::new_unchecked(5.0, 1.0),
+            ]),
+        );
+        summary.reset(
+            Exchange::Bitstamp,
+            OrderBook::new_unchecked(vec![Order::new_unchecked(1.5, 0.25)]),
+            OrderBook::new_unchecked(vec![Order::new_unchecked(4.0, 0.25)]),
+        );
+    }
+
+    // takes whole binance level, whole bitstamp level and a part of the next binance one
+    let fill = summary.simulate_market_buy(Amount(n(1.0)));
+    assert_feq!(fill.cost, 0.5 * 3.0 + 0.25 * 4.0 + 0.25 * 5.0);
+    assert_feq!(fill.filled.into_inner(), 1.0);
+    assert_feq!(fill.remaining.into_inner(), 0.0);
+    assert_feq!(fill.average_price.unwrap(), 3.75);
+    assert!(fill.is_complete());
+
+    // the book is too thin
+    let fill = summary.simulate_market_sell(Amount(n(2.0)));
+    assert_feq!(fill.cost, 0.5 * 2.0 + 0.25 * 1.5 + 1.0 * 1.0);
+    assert_feq!(fill.filled.into_inner(), 1.75);
+    assert_feq!(fill.remaining.into_inner(), 0.25);
+    assert_feq!(fill.average_price.unwrap(), 2.375 / 1.75);
+    assert!(!fill.is_complete());
+
+    // stops within the best level
+    let fill = summary.simulate_market_sell(Amount(n(0.1)));
+    assert_feq!(fill.cost, 0.2);
+    assert_feq!(fill.average_price.unwrap(), 2.0);
+    assert!(fill.is_complete());
+}
+
+#[test]
+fn ties_summary() {
+    for exchanges in [
+        [Exchange::Binance, Exchange::Bitstamp, Exchange::Kraken],
+        [Exchange::Kraken, Exchange::Bitstamp, Exchange::Binance],
+    ] {
+        let mut summary = SummaryOrderBook::default();
+        for exchange in exchanges {
+            unsafe {
+                summary.reset(
+                    exchange,
+                    OrderBook::new_unchecked(vec![Order::new_unchecked(1.0, 0.1)]),
+                    OrderBook::new_unchecked(vec![Order::new_unchecked(2.0, 0.1)]),
+                );
+            }
+        }
+        let expected = [Exchange::Binance, Exchange::Bitstamp, Exchange::Kraken];
+        assert!(summary
+            .bids()
+            .map(|order| order.exchange())
+            .eq(expected.into_iter()));
+        assert!(summary
+            .asks()
+            .map(|order| order.exchange())
+            .eq(expected.into_iter()));
+    }
+}
+
+#[test]
+fn update_truncation() {
+    unsafe {
+        let deep = OrderBook::<BID, 3>::new_unchecked(vec![
+            Order::new_unchecked(3.0, 1.0),
+            Order::new_unchecked(2.0, 1.0),
+            Order::new_unchecked(1.0, 1.0),
+        ]);
+        let bids = deep.best::<2>();
+        assert_eq!(bids.orders(), &deep.orders()[..2]);
+
+        // better level pushes the worst one out and it's forgotten
+        let better = OrderBookDiffBids::new_unchecked(vec![Order::new_unchecked(4.0, 1.0)]);
+        let bids = bids.update(&better);
+        assert_eq!(
+            bids.orders(),
+            &[
+                Order::new_unchecked(4.0, 1.0),
+                Order::new_unchecked(3.0, 1.0)
+            ]
+        );
+        let removed = OrderBookDiffBids::new_unchecked(vec![Order::new_unchecked(4.0, 0.0)]);
+        let bids = bids.update(&removed);
+        assert_eq!(bids.orders(), &[Order::new_unchecked(3.0, 1.0)]);
+
+        // while deeper book refills the best levels
+        let deep = deep.update(&better).update(&removed);
+        assert_eq!(
+            deep.best::<2>().orders(),
+            &[
+                Order::new_unchecked(3.0, 1.0),
+                Order::new_unchecked(2.0, 1.0)
+            ]
+        );
+        assert_eq!(deep.best::<5>().orders(), deep.orders());
+    }
+}
+
+fn diff<const QUOTE: bool>(orders: &[(f64, f64)]) -> OrderBookDiff<QUOTE> {
+    OrderBookDiff::new(
+        orders
+            .iter()
+            .map(|&(price, amount)| {
+                Order::new(
+                    Price::new(n(price)).unwrap(),
+                    Amount::new(n(amount)).unwrap(),
+                )
+            })
+            .collect(),
+    )
+    .unwrap()
+}
+
+#[test]
+fn merge_diff_bids() {
+    let first = diff::<BID>(&[(2.0, 1.5), (1.5, 1.0), (0.5, 2.5)]);
+    assert_eq!(OrderBookDiffBids::default().merge(&first), first);
+    assert_eq!(first.merge(&OrderBookDiff::default()), first);
+    assert_eq!(first.merge(&first), first);
+    assert_eq!(first.merge(&default_amount(&first)), default_amount(&first));
+
+    let second = diff::<BID>(&[(2.1, 0.5), (1.9, 0.7), (1.5, 0.0), (0.6, 5.0), (0.1, 1.5)]);
+    let merged = first.merge(&second);
+    assert_eq!(
+        merged,
+        diff(&[
+            (2.1, 0.5),
+            (2.0, 1.5),
+            (1.9, 0.7),
+            (1.5, 0.0),
+            (0.6, 5.0),
+            (0.5, 2.5),
+            (0.1, 1.5),
+        ])
+    );
+
+    let third = diff::<BID>(&[(2.1, 0.0), (1.9, 0.8), (1.5, 0.1), (0.2, 0.5)]);
+    assert_eq!(
+        merged.merge(&third),
+        diff(&[
+            (2.1, 0.0),
+            (2.0, 1.5),
+            (1.9, 0.8),
+            (1.5, 0.1),
+            (0.6, 5.0),
+            (0.5, 2.5),
+            (0.2, 0.5),
+            (0.1, 1.5),
+        ])
+    );
+    // accumulated diffs give the same book as applied one by one
+    let book = OrderBookBids::default();
+    assert_eq!(
+        book.update(&first.merge(&second).merge(&third)),
+        book.update(&first).update(&second).update(&third)
+    );
+}
+
+#[test]
+fn merge_diff_asks() {
+    let first = diff::<ASK>(&[(0.5, 2.5), (1.5, 1.0), (2.0, 1.5)]);
+    assert_eq!(OrderBookDiffAsks::default().merge(&first), first);
+    assert_eq!(first.merge(&OrderBookDiff::default()), first);
+    assert_eq!(first.merge(&first), first);
+    assert_eq!(first.merge(&default_amount(&first)), default_amount(&first));
+
+    let second = diff::<ASK>(&[(0.1, 1.5), (0.6, 5.0), (1.5, 0.0), (1.9, 0.7), (2.1, 0.5)]);
+    let merged = first.merge(&second);
+    assert_eq!(
+        merged,
+        diff(&[
+            (0.1, 1.5),
+            (0.5, 2.5),
+            (0.6, 5.0),
+            (1.5, 0.0),
+            (1.9, 0.7),
+            (2.0, 1.5),
+            (2.1, 0.5),
+        ])
+    );
+
+    let third = diff::<ASK>(&[(0.2, 0.5), (1.5, 0.1), (1.9, 0.8), (2.1, 0.0)]);
+    assert_eq!(
+        merged.merge(&third),
+        diff(&[
+            (0.1, 1.5),
+            (0.2, 0.5),
+            (0.5, 2.5),
+            (0.6, 5.0),
+            (1.5, 0.1),
+            (1.9, 0.8),
+            (2.0, 1.5),
+            (2.1, 0.0),
+        ])
+    );
+    let book = OrderBookAsks::default();
+    assert_eq!(
+        book.update(&first.merge(&second).merge(&third)),
+        book.update(&first).update(&second).update(&third)
+    );
+}
+
+#[test]
+fn reset_side_summary() {
+    let orders = |exchange, book: &[Order]| {
+        book.iter()
+            .map(|o| SummaryOrder(exchange, *o))
+            .collect::<Vec<_>>()
+    };
+    let mut summary = SummaryOrderBook::default();
+    unsafe {
+        let bids = OrderBook::new_unchecked(vec![Order::new_unchecked(1.0, 0.1)]);
+        let asks = OrderBook::new_unchecked(vec![Order::new_unchecked(2.0, 0.2)]);
+        summary.reset_bids(Exchange::Binance, bids.clone());
+        assert_eq!(
+            summary.bids().collect_vec(),
+            orders(Exchange::Binance, bids.orders())
+        );
+        assert_eq!(summary.asks().count(), 0);
+
+        summary.reset_asks(Exchange::Binance, asks.clone());
+        summary.reset_asks(Exchange::Kraken, asks.clone());
+        assert_eq!(
+            summary.bids().collect_vec(),
+            orders(Exchange::Binance, bids.orders())
+        );
+        assert_eq!(summary.asks().count(), 2);
+
+        let new_bids = OrderBook::new_unchecked(vec![Order::new_unchecked(1.5, 0.3)]);
+        summary.reset_bids(Exchange::Binance, new_bids.clone());
+        assert_eq!(
+            summary.bids().collect_vec(),
+            orders(Exchange::Binance, new_bids.orders())
+        );
+        assert_eq!(
+            summary.asks().collect_vec(),
+            [
+                orders(Exchange::Binance, asks.orders()),
+                orders(Exchange::Kraken, asks.orders())
+            ]
+            .concat()
+        );
+
+        // the combined reset is the same as resetting both sides
+        let mut combined = SummaryOrderBook::default();
+        combined.reset(Exchange::Binance, new_bids, asks.clone());
+        combined.reset(Exchange::Kraken, OrderBook::default(), asks);
+        assert!(combined.bids().eq(summary.bids()));
+        assert!(combined.asks().eq(summary.asks()));
+
+        // bids of inverse exchange replace its asks
+        summary.set_inverse(Exchange::Kraken, true);
+        summary.reset_bids(
+            Exchange::Kraken,
+            OrderBook::new_unchecked(vec![Order::new_unchecked(0.5, 1.0)]),
+        );
+        assert_eq!(
+            summary.asks().collect_vec(),
+            [
+                SummaryOrder(Exchange::Kraken, Order::new_unchecked(2.0, 1.0)),
+                SummaryOrder(Exchange::Binance, Order::new_unchecked(2.0, 0.2)),
+            ]
+        );
+        assert_eq!(summary.bids().count(), 1);
+    }
+}
+
+#[test]
+fn reset_exchange_lookup() {
+    // entries in an order other than the declaration one
+    let mut summary = SummaryOrderBook::default();
+    summary.books.reverse();
+    unsafe {
+        for (price, exchange) in Exchange::iter().enumerate() {
+            summary.reset(
+                exchange,
+                OrderBook::new_unchecked(vec![Order::new_unchecked(price as f64 + 1.0, 0.1)]),
+                OrderBook::default(),
+            );
+        }
+        summary.set_conversion(Exchange::Kraken, Price(n(10.0)));
+    }
+    for order in summary.bids() {
+        let expected = Exchange::iter()
+            .position(|e| e == order.exchange())
+            .unwrap() as f64
+            + 1.0;
+        let rate = if order.exchange() == Exchange::Kraken {
+            10.0
+        } else {
+            1.0
+        };
+        assert_feq!(order.order().price().into_inner(), expected * rate);
+    }
+    assert_eq!(summary.bids().count(), Exchange::iter().count());
+}
+
+#[test]
+fn order_book_capacity() {
+    let orders = |count: usize| {
+        (1..=count)
+            .map(|price| unsafe { Order::new_unchecked(price as f64, 1.0) })
+            .collect_vec()
+    };
+    let mut asks = OrderBook::<ASK, 3>::new(orders(100)).unwrap();
+    assert!(asks.0 .0.capacity() <= 3);
+
+    for count in [1, 5, 50] {
+        asks = asks.update(&OrderBookDiff::new(orders(count)).unwrap());
+        assert_eq!(asks.orders().len(), 3);
+        assert!(asks.0 .0.capacity() <= 3);
+    }
+
+    let removed =
+        OrderBookDiffAsks::new(orders(2).into_iter().map(|order| order.empty()).collect()).unwrap();
+    let mut asks = OrderBookAsks::new(orders(10)).unwrap().update(&removed);
+    asks.0 .0.reserve(100);
+    asks.shrink_to_fit();
+    assert_eq!(asks.orders().len(), 8);
+    assert_eq!(asks.0 .0.capacity(), 8);
+}
+
+#[test]
+fn amount_denomination() {
+    let order = Order::new(Price(n(2.0)), Amount(n(0.5)));
+    assert_eq!(order.denomination(), AmountDenomination::Base);
+    assert_eq!(order.to_base(), Ok(order));
+
+    let quote = order.to_quote().unwrap();
+    assert_eq!(quote.denomination(), AmountDenomination::Quote);
+    assert_eq!(quote.price(), Price(n(2.0)));
+    assert_feq!(quote.amount().into_inner(), 1.0);
+    assert_eq!(quote.to_quote(), Ok(quote));
+    assert_eq!(quote.to_base(), Ok(order));
+    // notional doesn't depend on the denomination
+    assert_feq!(quote.value(), order.value());
+
+    let reported =
+        Order::new(Price(n(4.0)), Amount(n(2.0))).with_denomination(AmountDenomination::Quote);
+    assert_eq!(
+        reported.to_base(),
+        Ok(Order::new(Price(n(4.0)), Amount(n(0.5))))
+    );
+    assert!(reported.empty().is_empty());
+    assert_eq!(reported.empty().denomination(), AmountDenomination::Quote);
+
+    assert_feq!(
+        Amount(n(3.0)).to_base(Price(n(2.0))).unwrap().into_inner(),
+        1.5
+    );
+    assert_feq!(
+        Amount(n(3.0)).to_quote(Price(n(2.0))).unwrap().into_inner(),
+        6.0
+    );
+    #[cfg(not(feature = "decimal"))]
+    assert!(Amount(f64::MAX).to_quote(Price(2.0)).is_err());
+}
+
+#[test]
+fn hash() {
+    use std::collections::HashSet;
+
+    /// orders equal if their prices are
+    struct ByPrice(Order);
+    impl PartialEq for ByPrice {
+        fn eq(&self, other: &Self) -> bool {
+            self.0.price() == other.0.price()
+        }
+    }
+    impl Eq for ByPrice {}
+    impl std::hash::Hash for ByPrice {
+        fn hash<H: std::hash::Hasher>(&self, state: &mut H) {
+            self.0.price().hash(state);
+        }
+    }
+
+    let orders = unsafe {
+        [
+            Order::new_unchecked(1.0, 1.0),
+            Order::new_unchecked(1.0, 1.0),
+            Order::new_unchecked(1.0, 2.0),
+            Order::new_unchecked(2.0, 1.0),
+        ]
+    };
+    assert_eq!(orders.iter().collect::<HashSet<_>>().len(), 3);
+    let by_price: HashSet<_> = orders.into_iter().map(ByPrice).collect();
+    assert_eq!(by_price.len(), 2);
+    assert!(by_price.contains(&ByPrice(unsafe { Order::new_unchecked(2.0, 5.0) })));
+
+    // equal amounts hash equally whatever the sign of 0
+    assert_eq!(Amount(n(0.0)), Amount(n(-0.0)));
+    assert_eq!(HashSet::from([Amount(n(0.0)), Amount(n(-0.0))]).len(), 1);
+}
+
+#[test]
+fn amount_at() {
+    unsafe {
+        let bids = OrderBookBids::new_unchecked(vec![
+            Order::new_unchecked(3.0, 1.0),
+            Order::new_unchecked(2.0, 2.0),
+            Order::new_unchecked(1.0, 3.0),
+        ]);
+        let asks = OrderBookAsks::new_unchecked(vec![
+            Order::new_unchecked(4.0, 4.0),
+            Order::new_unchecked(5.0, 5.0),
+            Order::new_unchecked(6.0, 6.0),
+        ]);
+        assert_eq!(bids.amount_at(Price(n(3.0))), Some(Amount(n(1.0))));
+        assert_eq!(bids.amount_at(Price(n(2.0))), Some(Amount(n(2.0))));
+        assert_eq!(bids.amount_at(Price(n(1.0))), Some(Amount(n(3.0))));
+        assert_eq!(asks.amount_at(Price(n(4.0))), Some(Amount(n(4.0))));
+        assert_eq!(asks.amount_at(Price(n(5.0))), Some(Amount(n(5.0))));
+        assert_eq!(asks.amount_at(Price(n(6.0))), Some(Amount(n(6.0))));
+        for price in [0.5, 2.5, 3.5, 7.0] {
+            assert_eq!(bids.amount_at(Price(n(price))), None);
+            assert_eq!(asks.amount_at(Price(n(price))), None);
+        }
+    }
+    assert_eq!(OrderBookBids::default().amount_at(Price(n(1.0))), None);
+}
+
+#[test]
+fn require_non_empty_diff() {
+    let clean = diff::<BID>(&[(2.0, 1.0), (1.0, 0.5)]);
+    assert_eq!(clean.clone().require_non_empty(), Ok(clean));
+    assert_eq!(
+        OrderBookDiffAsks::default().require_non_empty(),
+        Ok(OrderBookDiffAsks::default())
+    );
+
+    let with_empty = diff::<ASK>(&[(2.0, 1.0), (1.0, 0.5), (3.0, 0.0)]);
+    assert_eq!(
+        with_empty.require_non_empty(),
+        Err(OrderBookError::HasOrderWithEmptyAmount {
+            price: Price(n(3.0)),
+            index: 2
+        })
+    );
+}
+
+#[test]
+fn compact_diff() {
+    unsafe {
+        let bids = OrderBookBids::new_unchecked(vec![
+            Order::new_unchecked(3.0, 1.0),
+            Order::new_unchecked(2.0, 1.0),
+            Order::new_unchecked(1.0, 1.0),
+        ]);
+        let diff = OrderBookDiffBids::new_unchecked(vec![
+            Order::new_unchecked(4.0, 0.0),
+            Order::new_unchecked(3.0, 0.0),
+            Order::new_unchecked(2.5, 0.5),
+            Order::new_unchecked(2.2, 0.0),
+            Order::new_unchecked(1.0, 0.0),
+            Order::new_unchecked(0.5, 0.0),
+        ]);
+        let compact = diff.compact(&bids);
+        assert_eq!(
+            compact,
+            OrderBookDiffBids::new_unchecked(vec![
+                Order::new_unchecked(3.0, 0.0),
+                Order::new_unchecked(2.5, 0.5),
+                Order::new_unchecked(1.0, 0.0),
+            ])
+        );
+        assert_eq!(bids.update(&compact), bids.update(&diff));
+        assert_eq!(compact.compact(&bids), compact);
+        assert_eq!(
+            diff.compact(&OrderBookBids::default()),
+            OrderBookDiffBids::new_unchecked(vec![Order::new_unchecked(2.5, 0.5)])
+        );
+
+        let asks = OrderBookAsks::new_unchecked(vec![Order::new_unchecked(2.0, 1.0)]);
+        let diff = OrderBookDiffAsks::new_unchecked(vec![
+            Order::new_unchecked(1.0, 0.0),
+            Order::new_unchecked(2.0, 0.0),
+            Order::new_unchecked(3.0, 0.0),
+        ]);
+        assert_eq!(
+            diff.compact(&asks),
+            OrderBookDiffAsks::new_unchecked(vec![Order::new_unchecked(2.0, 0.0)])
+        );
+    }
+}
+
+#[test]
+fn current_spread_summary() {
+    let mut summary = SummaryOrderBook::default();
+    assert!(summary.current_spread().is_nan());
+    unsafe {
+        summary.reset_asks(
+            Exchange::Binance,
+            OrderBook::new_unchecked(vec![
+                Order::new_unchecked(2.3, 1.1),
+                Order::new_unchecked(2.4, 1.1),
+            ]),
+        );
+        assert_eq!(summary.current_spread(), f64::NEG_INFINITY);
+        summary.reset_bids(
+            Exchange::Bitstamp,
+            OrderBook::new_unchecked(vec![
+                Order::new_unchecked(2.1, 0.1),
+                Order::new_unchecked(2.0, 0.1),
+            ]),
+        );
+        summary.reset_bids(
+            Exchange::Kraken,
+            OrderBook::new_unchecked(vec![Order::new_unchecked(2.2, 0.1)]),
+        );
+    }
+    assert_feq!(summary.current_spread(), -0.1);
+    assert_eq!(
+        summary.current_spread(),
+        SummaryOrderBook::spread(
+            summary.bids().collect_vec().into_iter(),
+            summary.asks().collect_vec().into_iter()
+        )
+    );
+}
+
+#[test]
+fn quota_summary() {
+    let mut summary = SummaryOrderBook::default();
+    unsafe {
+        // Binance dominates every price level
+        summary.reset_asks(
+            Exchange::Binance,
+            OrderBook::new_unchecked(
+                (1..=10)
+                    .map(|price| Order::new_unchecked(price as f64, 1.0))
+                    .collect(),
+            ),
+        );
+        summary.reset_asks(
+            Exchange::Kraken,
+            OrderBook::new_unchecked(
+                (11..=13)
+                    .map(|price| Order::new_unchecked(price as f64, 1.0))
+                    .collect(),
+            ),
+        );
+    }
+    assert!(summary
+        .asks()
+        .all(|order| order.exchange() == Exchange::Binance));
+
+    let asks = summary.quotes_with_quota::<ASK>(2, 5).collect_vec();
+    assert_eq!(
+        asks.iter()
+            .map(|order| (order.exchange(), order.order().price().into_inner()))
+            .collect_vec(),
+        [
+            (Exchange::Binance, n(1.0)),
+            (Exchange::Binance, n(2.0)),
+            (Exchange::Binance, n(3.0)),
+            (Exchange::Kraken, n(11.0)),
+            (Exchange::Kraken, n(12.0)),
+        ]
+    );
+
+    // quotas exceeding total
+    let asks = summary.quotes_with_quota::<ASK>(3, 4).collect_vec();
+    assert_eq!(asks.len(), 6);
+    assert!(asks
+        .windows(2)
+        .all(|pair| pair[0].order().price() < pair[1].order().price()));
+
+    // no quota is the same as the best levels
+    assert!(summary
+        .quotes_with_quota::<ASK>(0, BEST_ORDER_BOOK_SIZE)
+        .eq(summary.asks()));
+    assert_eq!(summary.quotes_with_quota::<BID>(2, 5).count(), 0);
+}
+
+#[test]
+fn exchange_books_summary() {
+    let mut summary = SummaryOrderBook::default();
+    unsafe {
+        let binance = (
+            OrderBook::new_unchecked(vec![Order::new_unchecked(1.0, 0.1)]),
+            OrderBook::new_unchecked(vec![Order::new_unchecked(2.0, 0.2)]),
+        );
+        let kraken = (
+            OrderBook::new_unchecked(vec![Order::new_unchecked(1.5, 0.3)]),
+            OrderBook::new_unchecked(vec![
+                Order::new_unchecked(1.6, 0.4),
+                Order::new_unchecked(1.7, 0.5),
+            ]),
+        );
+        summary.reset(Exchange::Binance, binance.0.clone(), binance.1.clone());
+        summary.reset(Exchange::Kraken, kraken.0.clone(), kraken.1.clone());
+        assert_eq!(
+            summary.exchange_book(Exchange::Binance),
+            Some((&binance.0, &binance.1))
+        );
+        assert_eq!(
+            summary.exchange_book(Exchange::Kraken),
+            Some((&kraken.0, &kraken.1))
+        );
+
+        let books = summary.exchange_books().collect_vec();
+        assert_eq!(books.len(), Exchange::iter().count());
+        assert!(books.contains(&(Exchange::Binance, &binance.0, &binance.1)));
+        assert!(books
+            .iter()
+            .filter(|(exchange, ..)| ![Exchange::Binance, Exchange::Kraken].contains(exchange))
+            .all(|(_, bids, asks)| bids.orders().is_empty() && asks.orders().is_empty()));
+    }
+}
+
+#[test]
+fn as_diffs_summary() {
+    let mut summary = SummaryOrderBook::default();
+    unsafe {
+        summary.reset(
+            Exchange::Binance,
+            OrderBook::new_unchecked(vec![
+                Order::new_unchecked(1.0, 0.1),
+                Order::new_unchecked(0.9, 0.2),
+            ]),
+            OrderBook::new_unchecked(vec![Order::new_unchecked(2.0, 0.2)]),
+        );
+        summary.reset(
+            Exchange::Kraken,
+            OrderBook::new_unchecked(vec![Order::new_unchecked(1.5, 0.3)]),
+            OrderBook::default(),
+        );
+    }
+    let mut restored = SummaryOrderBook::default();
+    for (exchange, bids, asks) in summary.as_diffs() {
+        restored.reset(
+            exchange,
+            OrderBook::default().update(&bids),
+            OrderBook::default().update(&asks),
+        );
+    }
+    assert!(restored.bids().eq(summary.bids()));
+    assert!(restored.asks().eq(summary.asks()));
+    assert!(restored.exchange_books().eq(summary.exchange_books()));
+    assert_eq!(summary.as_diffs().count(), Exchange::iter().count());
+}
+
+#[test]
+fn contribution_summary() {
+    let mut summary = SummaryOrderBook::default();
+    assert!(summary.contribution().is_empty());
+    unsafe {
+        summary.reset(
+            Exchange::Binance,
+            OrderBook::new_unchecked(
+                (1..=8)
+                    .map(|i| Order::new_unchecked(100.0 - i as f64, 1.0))
+                    .collect(),
+            ),
+            OrderBook::new_unchecked(vec![Order::new_unchecked(101.0, 1.0)]),
+        );
+        summary.reset(
+            Exchange::Kraken,
+            OrderBook::new_unchecked(
+                (1..=5)
+                    .map(|i| Order::new_unchecked(100.5 - i as f64, 1.0))
+                    .collect(),
+            ),
+            OrderBook::default(),
+        );
+    }
+    // only best BEST_ORDER_BOOK_SIZE bids count, interleaved 99.5, 99, 98.5, ..
+    assert_eq!(
+        summary.contribution(),
+        HashMap::from([(Exchange::Binance, (5, 1)), (Exchange::Kraken, (5, 0))])
+    );
+}
+
+#[test]
+fn exchange_status_summary() {
+    let mut summary = SummaryOrderBook::default();
+    assert!(summary.exchange_status().values().all(|live| !live));
+    unsafe {
+        summary.reset(
+            Exchange::Kraken,
+            OrderBook::default(),
+            OrderBook::new_unchecked(vec![Order::new_unchecked(2.0, 1.0)]),
+        );
+    }
+    let status = summary.exchange_status();
+    assert_eq!(status.len(), Exchange::iter().count());
+    assert!(status[&Exchange::Kraken]);
+    assert!(status
+        .iter()
+        .all(|(exchange, live)| *live == (*exchange == Exchange::Kraken)));
+
+    summary.reset(Exchange::Kraken, OrderBook::default(), OrderBook::default());
+    assert!(!summary.exchange_status()[&Exchange::Kraken]);
+}
+
+#[test]
+fn arbitrage_summary() {
+    let mut summary = SummaryOrderBook::default();
+    assert_eq!(summary.arbitrage(), None);
+    unsafe {
+        summary.reset(
+            Exchange::Binance,
+            OrderBook::new_unchecked(vec![Order::new_unchecked(10.0, 1.0)]),
+            OrderBook::new_unchecked(vec![Order::new_unchecked(11.0, 1.0)]),
+        );
+        summary.reset(
+            Exchange::Kraken,
+            OrderBook::new_unchecked(vec![Order::new_unchecked(10.5, 2.0)]),
+            OrderBook::new_unchecked(vec![Order::new_unchecked(10.75, 2.0)]),
+        );
+        assert_eq!(summary.arbitrage(), None);
+
+        summary.reset(
+            Exchange::Kraken,
+            OrderBook::new_unchecked(vec![Order::new_unchecked(11.5, 2.0)]),
+            OrderBook::new_unchecked(vec![Order::new_unchecked(12.0, 2.0)]),
+        );
+        assert_eq!(
+            summary.arbitrage(),
+            Some(Arb {
+                bid: SummaryOrder(Exchange::Kraken, Order::new_unchecked(11.5, 2.0)),
+                ask: SummaryOrder(Exchange::Binance, Order::new_unchecked(11.0, 1.0)),
+                edge: 0.5,
+            })
+        );
+
+        // touching books are crossed with no edge
+        summary.reset(
+            Exchange::Kraken,
+            OrderBook::new_unchecked(vec![Order::new_unchecked(11.0, 2.0)]),
+            OrderBook::new_unchecked(vec![Order::new_unchecked(12.0, 2.0)]),
+        );
+        assert_eq!(summary.arbitrage().map(|arb| arb.edge), Some(0.0));
+    }
+}
+
+#[test]
+fn order_parts() {
+    let order = unsafe { Order::new_unchecked(2.0, 3.0) };
+    let (price, amount) = order.into_parts();
+    assert_eq!((price, amount), (order.price(), order.amount()));
+
+    let OrderParts {
+        price,
+        amount,
+        denomination,
+    } = order.with_denomination(AmountDenomination::Quote).into();
+    assert_eq!((price.0, amount.0), (n(2.0), n(3.0)));
+    assert_eq!(denomination, AmountDenomination::Quote);
+}
+
+#[test]
+fn changed_since_summary() {
+    let mut book = SummaryOrderBook::default();
+    let previous = Summary::new("ethbtc", &book);
+    assert!(!book.changed_since(&previous));
+    unsafe {
+        book.reset(
+            Exchange::Kraken,
+            OrderBook::new_unchecked(vec![Order::new_unchecked(1.0, 1.0)]),
+            OrderBook::default(),
+        );
+    }
+    assert!(book.changed_since(&previous));
+    assert!(!book.changed_since(&Summary::new("ethbtc", &book)));
+}
+
+#[test]
+fn from_sorted_slice() {
+    let orders = |levels: &[(f64, f64)]| {
+        levels
+            .iter()
+            .map(|&(price, amount)| unsafe { Order::new_unchecked(price, amount) })
+            .collect_vec()
+    };
+    let bids = orders(&[(3.0, 1.0), (2.0, 1.0), (1.0, 1.0)]);
+    let book = OrderBook::<BID, 2>::from_sorted_slice(&bids).unwrap();
+    assert_eq!(book.orders(), &bids[..2]);
+    assert!(book.0 .0.capacity() <= 2);
+    assert_eq!(Ok(book), OrderBook::new_sorted(bids.clone()));
+
+    assert_eq!(
+        OrderBook::<ASK, 2>::from_sorted_slice(&bids),
+        Err(OrderBookError::OrdersNotSortedAccordingToQuoteType {
+            price: Price(n(2.0)),
+            index: 1
+        })
+    );
+    assert_eq!(
+        OrderBookBids::from_sorted_slice(&orders(&[(3.0, 1.0), (3.0, 2.0)])),
+        Err(OrderBookError::HasOrderWithNotUniquePrice {
+            price: Price(n(3.0)),
+            index: 1
+        })
+    );
+    assert_eq!(
+        OrderBookBids::from_sorted_slice(&orders(&[(3.0, 1.0), (2.0, 0.0)])),
+        Err(OrderBookError::HasOrderWithEmptyAmount {
+            price: Price(n(2.0)),
+            index: 1
+        })
+    );
+    // only the order following the best ones is checked
+    assert!(
+        OrderBook::<BID, 1>::from_sorted_slice(&orders(&[(3.0, 1.0), (2.0, 1.0), (4.0, 0.0)]))
+            .is_ok()
+    );
+    assert_eq!(
+        OrderBookBids::from_sorted_slice(&[]),
+        Ok(OrderBookBids::default())
+    );
+}
+
+#[test]
+fn spread_bps_summary() {
+    let mut summary = SummaryOrderBook::default();
+    assert_eq!(summary.mid(), None);
+    assert_eq!(summary.spread_bps(), None);
+    unsafe {
+        summary.reset(
+            Exchange::Binance,
+            OrderBook::new_unchecked(vec![Order::new_unchecked(99.0, 1.0)]),
+            OrderBook::default(),
+        );
+        assert_eq!(summary.spread_bps(), None);
+
+        summary.reset(
+            Exchange::Kraken,
+            OrderBook::default(),
+            OrderBook::new_unchecked(vec![Order::new_unchecked(101.0, 1.0)]),
+        );
+        assert_eq!(summary.mid(), Some(100.0));
+        assert_eq!(summary.spread_bps(), Some(200.0));
+
+        // crossed
+        summary.reset(
+            Exchange::Binance,
+            OrderBook::new_unchecked(vec![Order::new_unchecked(102.0, 1.0)]),
+            OrderBook::default(),
+        );
+        assert_eq!(summary.mid(), Some(101.5));
+        assert!((summary.spread_bps().unwrap() + 1.0 / 101.5 * 10_000.0).abs() < 1e-9);
+    }
+}
+
+#[test]
+fn tie_break_summary() {
+    let mut summary = SummaryOrderBook::default();
+    unsafe {
+        for exchange in [Exchange::Binance, Exchange::Bitstamp] {
+            summary.reset(
+                exchange,
+                OrderBook::new_unchecked(vec![Order::new_unchecked(1.0, 1.0)]),
+                OrderBook::new_unchecked(vec![Order::new_unchecked(2.0, 1.0)]),
+            );
+        }
+        summary.reset(
+            Exchange::Kraken,
+            OrderBook::new_unchecked(vec![Order::new_unchecked(1.0, 2.0)]),
+            OrderBook::default(),
+        );
+    }
+    let exchanges = |orders: &mut dyn Iterator<Item = SummaryOrder>| {
+        orders.map(|order| order.exchange()).collect_vec()
+    };
+    assert_eq!(
+        exchanges(&mut summary.bids()),
+        [Exchange::Kraken, Exchange::Binance, Exchange::Bitstamp]
+    );
+
+    summary.set_tie_break(TieBreak::ByExchange(vec![
+        Exchange::Bitstamp,
+        Exchange::Binance,
+    ]));
+    assert_eq!(
+        exchanges(&mut summary.bids()),
+        [Exchange::Bitstamp, Exchange::Binance, Exchange::Kraken]
+    );
+    assert_eq!(
+        exchanges(&mut summary.asks()),
+        [Exchange::Bitstamp, Exchange::Binance]
+    );
+
+    summary.set_tie_break(TieBreak::Custom(Arc::new(|l, r| {
+        r.exchange().cmp(&l.exchange())
+    })));
+    assert_eq!(
+        exchanges(&mut summary.bids()),
+        [Exchange::Kraken, Exchange::Bitstamp, Exchange::Binance]
+    );
+}
+
+#[test]
+fn persist_summary() {
+    let mut summary = SummaryOrderBook::default();
+    unsafe {
+        summary.reset(
+            Exchange::Binance,
+            OrderBook::new_unchecked(vec![
+                Order::new_unchecked(2.0, 1.0),
+                Order::new_unchecked(1.5, 0.5),
+            ]),
+            OrderBook::new_unchecked(vec![Order::new_unchecked(3.0, 1.0)]),
+        );
+        summary.reset(
+            Exchange::Coinbase,
+            OrderBook::new_unchecked(vec![Order::new_unchecked(2.5, 0.1)]),
+            OrderBook::new_unchecked(vec![
+                Order::new_unchecked(2.75, 2.0),
+                Order::new_unchecked(4.0, 3.0),
+            ]),
+        );
+    }
+    let restored = SummaryOrderBook::from_bytes(&summary.to_bytes()).unwrap();
+    assert_eq!(
+        restored.exchange_books().collect_vec(),
+        summary.exchange_books().collect_vec()
+    );
+    assert_eq!(restored.bids().collect_vec(), summary.bids().collect_vec());
+
+    let unsorted = br#"[{"exchange":"kraken","bids":[[1.0,1.0],[2.0,1.0]],"asks":[]}]"#;
+    assert!(matches!(
+        SummaryOrderBook::from_bytes(unsorted),
+        Err(e) if e.contains("not properly sorted")
+    ));
+    let empty = br#"[{"exchange":"kraken","bids":[],"asks":[[1.0,0.0]]}]"#;
+    assert!(SummaryOrderBook::from_bytes(empty).is_err());
+    assert!(SummaryOrderBook::from_bytes(b"[{}]").is_err());
+}
+
+#[test]
+fn fillable_at_summary() {
+    let mut summary = SummaryOrderBook::default();
+    assert_eq!(
+        summary.fillable_at(Side::Buy, Price(n(10.0))),
+        Amount(n(0.0))
+    );
+    unsafe {
+        summary.reset(
+            Exchange::Binance,
+            OrderBook::new_unchecked(vec![
+                Order::new_unchecked(9.0, 1.0),
+                Order::new_unchecked(8.0, 2.0),
+            ]),
+            OrderBook::new_unchecked(vec![
+                Order::new_unchecked(10.0, 1.0),
+                Order::new_unchecked(12.0, 2.0),
+            ]),
+        );
+        summary.reset(
+            Exchange::Kraken,
+            OrderBook::new_unchecked(vec![
+                Order::new_unchecked(8.5, 0.5),
+                Order::new_unchecked(7.0, 4.0),
+            ]),
+            OrderBook::new_unchecked(vec![
+                Order::new_unchecked(11.0, 0.25),
+                Order::new_unchecked(12.0, 0.5),
+            ]),
+        );
+    }
+    assert_eq!(
+        summary.fillable_at(Side::Buy, Price(n(9.5))),
+        Amount(n(0.0))
+    );
+    assert_eq!(
+        summary.fillable_at(Side::Buy, Price(n(10.0))),
+        Amount(n(1.0))
+    );
+    assert_eq!(
+        summary.fillable_at(Side::Buy, Price(n(11.5))),
+        Amount(n(1.25))
+    );
+    assert_eq!(
+        summary.fillable_at(Side::Buy, Price(n(12.0))),
+        Amount(n(3.75))
+    );
+
+    assert_eq!(
+        summary.fillable_at(Side::Sell, Price(n(9.5))),
+        Amount(n(0.0))
+    );
+    assert_eq!(
+        summary.fillable_at(Side::Sell, Price(n(8.5))),
+        Amount(n(1.5))
+    );
+    assert_eq!(
+        summary.fillable_at(Side::Sell, Price(n(8.0))),
+        Amount(n(3.5))
+    );
+    assert_eq!(
+        summary.fillable_at(Side::Sell, Price(n(1.0))),
+        Amount(n(7.5))
+    );
+}
+
+#[test]
+fn summary_books() {
+    let (btc, eth) = (Symbol::new("BTC", "USD"), Symbol::new("ETH", "USD"));
+    let mut books = SummaryBooks::default();
+    assert!(books.spread(&btc).is_nan());
+    assert_eq!(books.bids(&btc).count(), 0);
+    unsafe {
+        books.reset(
+            &btc,
+            Exchange::Binance,
+            OrderBook::new_unchecked(vec![Order::new_unchecked(100.0, 1.0)]),
+            OrderBook::new_unchecked(vec![Order::new_unchecked(101.0, 1.0)]),
+        );
+        books.reset(
+            &btc,
+            Exchange::Kraken,
+            OrderBook::new_unchecked(vec![Order::new_unchecked(100.5, 1.0)]),
+            OrderBook::default(),
+        );
+        books.reset(
+            &eth,
+            Exchange::Kraken,
+            OrderBook::new_unchecked(vec![Order::new_unchecked(10.0, 2.0)]),
+            OrderBook::new_unchecked(vec![Order::new_unchecked(10.5, 2.0)]),
+        );
+    }
+    assert_eq!(books.symbols().sorted().collect_vec(), [&btc, &eth]);
+    assert_eq!(books.bids(&btc).collect_vec(), unsafe {
+        [
+            SummaryOrder(Exchange::Kraken, Order::new_unchecked(100.5, 1.0)),
+            SummaryOrder(Exchange::Binance, Order::new_unchecked(100.0, 1.0)),
+        ]
+    });
+    assert_eq!(books.spread(&btc), -0.5);
+    assert_eq!(
+        books.asks(&eth).collect_vec(),
+        [unsafe { SummaryOrder(Exchange::Kraken, Order::new_unchecked(10.5, 2.0)) }]
+    );
+    assert_eq!(books.spread(&eth), -0.5);
+
+    // resetting one symbol leaves the other one as is
+    unsafe {
+        books.reset(
+            &eth,
+            Exchange::Binance,
+            OrderBook::new_unchecked(vec![Order::new_unchecked(10.25, 1.0)]),
+            OrderBook::default(),
+        );
+    }
+    assert_eq!(books.bids(&eth).count(), 2);
+    assert_eq!(books.bids(&btc).count(), 2);
+    assert_eq!(books.get(&btc).unwrap().current_spread(), -0.5);
+}
+
+#[test]
+fn update_reporting() {
+    let book =
+        OrderBook::<ASK, 2>::new(diff::<ASK>(&[(1.0, 1.0), (2.0, 1.0), (3.0, 1.0)]).0).unwrap();
+    let no_op = [
+        // beyond kept levels
+        diff(&[(3.0, 5.0), (4.0, 1.0)]),
+        // repeats current levels
+        diff(&[(2.0, 1.0)]),
+        // removes missing level
+        diff(&[(1.5, 0.0)]),
+        OrderBookDiff::default(),
+    ];
+    for diff in &no_op {
+        let (updated, changed) = book.update_reporting(diff);
+        assert!(!changed);
+        assert_eq!(updated, book);
+    }
+
+    for diff in [
+        diff(&[(1.0, 2.0)]),
+        diff(&[(0.5, 1.0)]),
+        diff(&[(1.5, 1.0)]),
+        diff(&[(2.0, 0.0)]),
+    ] {
+        let (updated, changed) = book.update_reporting(&diff);
+        assert!(changed);
+        assert_eq!(updated, book.update(&diff));
+    }
+
+    let (emptied, changed) = OrderBook::<ASK, 2>::new(diff::<ASK>(&[(1.0, 1.0)]).0)
+        .unwrap()
+        .update_reporting(&diff(&[(1.0, 0.0)]));
+    assert!(changed);
+    assert!(emptied.orders().is_empty());
+}
+
+#[test]
+fn order_book_eq() {
+    let book = OrderBook::<BID, 2>::new(diff::<BID>(&[(3.0, 1.0), (2.0, 1.0)]).0).unwrap();
+    assert_eq!(book, book.clone());
+    assert!(book.deep_eq(&book.clone()));
+
+    // a retained tail isn't published
+    let deep = OrderBook::<BID, 2>(diff(&[(3.0, 1.0), (2.0, 1.0), (1.0, 1.0)]));
+    assert_eq!(book, deep);
+    assert!(!book.deep_eq(&deep));
+
+    let other = OrderBook::<BID, 2>::new(diff::<BID>(&[(3.0, 1.0), (2.0, 2.0)]).0).unwrap();
+    assert_ne!(book, other);
+    assert!(!book.deep_eq(&other));
+}
+
+#[test]
+fn clamped() {
+    let (min, max) = (Price(n(0.5)), Price(n(100.0)));
+    for (value, expected) in [
+        (1.5, 1.5),
+        (0.1, 0.5),
+        (1e9, 100.0),
+        (0.0, 0.5),
+        (-0.0, 0.5),
+        (-1.0, 0.5),
+    ] {
+        assert_eq!(
+            Price::new_clamped(n(value), min, max),
+            Price(n(expected)),
+            "{value}"
+        );
+    }
+
+    let max = Amount(n(10.0));
+    for (value, expected) in [
+        (1.5, 1.5),
+        (0.0, 0.0),
+        (-0.0, 0.0),
+        (-1.0, 0.0),
+        (1e9, 10.0),
+    ] {
+        assert_eq!(
+            Amount::new_clamped(n(value), max),
+            Amount(n(expected)),
+            "{value}"
+        );
+    }
+}
+
+#[test]
+#[cfg(not(feature = "decimal"))]
+fn clamped_non_finite() {
+    let (min, max) = (Price(0.5), Price(100.0));
+    for (value, expected) in [
+        (f64::MIN_POSITIVE / 2.0, 0.5),
+        (f64::NAN, 0.5),
+        (f64::INFINITY, 100.0),
+        (f64::NEG_INFINITY, 0.5),
+    ] {
+        assert_eq!(
+            Price::new_clamped(value, min, max),
+            Price(expected),
+            "{value}"
+        );
+    }
+
+    let max = Amount(10.0);
+    for (value, expected) in [
+        (f64::MIN_POSITIVE / 2.0, 0.0),
+        (f64::NAN, 0.0),
+        (f64::INFINITY, 10.0),
+        (f64::NEG_INFINITY, 0.0),
+    ] {
+        assert_eq!(Amount::new_clamped(value, max), Amount(expected), "{value}");
+    }
+}
+
+#[test]
+fn try_reset_crossed() {
+    let mut summary = SummaryOrderBook::default();
+    unsafe {
+        let bids = OrderBook::new_unchecked(vec![Order::new_unchecked(100.0, 1.0)]);
+        let asks = OrderBook::new_unchecked(vec![Order::new_unchecked(101.0, 1.0)]);
+        assert_eq!(summary.try_reset(Exchange::Binance, bids, asks), Ok(()));
+
+        let bids = OrderBook::new_unchecked(vec![Order::new_unchecked(102.0, 1.0)]);
+        let asks = OrderBook::new_unchecked(vec![Order::new_unchecked(101.5, 1.0)]);
+        assert_eq!(
+            summary.try_reset(Exchange::Kraken, bids, asks),
+            Err(OrderBookError::Crossed {
+                bid: Price(n(102.0)),
+                ask: Price(n(101.5))
+            })
+        );
+        // locked is crossed for a single exchange too
+        let bids = OrderBook::new_unchecked(vec![Order::new_unchecked(101.0, 1.0)]);
+        let asks = OrderBook::new_unchecked(vec![Order::new_unchecked(101.0, 1.0)]);
+        assert!(summary.try_reset(Exchange::Kraken, bids, asks).is_err());
+
+        // one sided books can't be crossed
+        let bids = OrderBook::new_unchecked(vec![Order::new_unchecked(102.0, 1.0)]);
+        assert_eq!(
+            summary.try_reset(Exchange::Kraken, bids, OrderBook::default()),
+            Ok(())
+        );
+    }
+    // crossed books are dropped while the good ones are kept
+    assert_eq!(summary.bids().count(), 2);
+    assert_eq!(summary.asks().count(), 1);
+}
+
+#[test]
+#[cfg(not(feature = "decimal"))]
+fn epsilon() {
+    let near = 0.1 + 0.2;
+    assert_ne!(near, 0.3);
+    unsafe {
+        let orders = vec![
+            Order::new_unchecked(0.3, 1.0),
+            Order::new_unchecked(0.4, 1.0),
+            Order::new_unchecked(near, 2.0),
+        ];
+        // 0 epsilon keeps levels as new does
+        let diff = OrderBookDiffAsks::new_with_epsilon(orders.clone(), 0.0).unwrap();
+        assert_eq!(diff, OrderBookDiffAsks::new(orders.clone()).unwrap());
+        assert_eq!(diff.0.len(), 3);
+        assert!(OrderBookDiffAsks::new_with_epsilon(
+            vec![
+                Order::new_unchecked(0.3, 1.0),
+                Order::new_unchecked(0.3, 2.0)
+            ],
+            0.0
+        )
+        .is_err());
+
+        // the latest of near equal orders is kept
+        let diff = OrderBookDiffAsks::new_with_epsilon(orders, 1e-9).unwrap();
+        assert_eq!(
+            diff.0,
+            [
+                Order::new_unchecked(near, 2.0),
+                Order::new_unchecked(0.4, 1.0)
+            ]
+        );
+        let diff = OrderBookDiffBids::new_with_epsilon(
+            vec![
+                Order::new_unchecked(near, 2.0),
+                Order::new_unchecked(0.3, 1.0),
+                Order::new_unchecked(0.3, 3.0),
+            ],
+            1e-9,
+        )
+        .unwrap();
+        assert_eq!(diff.0, [Order::new_unchecked(0.3, 3.0)]);
+
+        let book = OrderBookDiffAsks::new_unchecked(vec![
+            Order::new_unchecked(0.3, 1.0),
+            Order::new_unchecked(0.4, 1.0),
+        ]);
+        let update = OrderBookDiffAsks::new_unchecked(vec![Order::new_unchecked(near, 2.0)]);
+        assert_eq!(book.merge(&update).0.len(), 3);
+        assert_eq!(
+            book.merge_with_epsilon(&update, 1e-9).0,
+            [
+                Order::new_unchecked(near, 2.0),
+                Order::new_unchecked(0.4, 1.0)
+            ]
+        );
+        assert_eq!(book.merge_with_epsilon(&update, 0.0), book.merge(&update));
+    }
+}
+
+#[test]
+fn from_scaled() {
+    // satoshis
+    assert_eq!(
+        Price::from_scaled(2_000_012_345_678, 8),
+        Ok(Price(n(20000.12345678)))
+    );
+    assert_eq!(Amount::from_scaled(1, 8), Ok(Amount(n(1e-8))));
+    assert_eq!(Amount::from_scaled(150_000_000, 8), Ok(Amount(n(1.5))));
+    // cents
+    assert_eq!(Price::from_scaled(12345, 2), Ok(Price(n(123.45))));
+    assert_eq!(Amount::from_scaled(5, 2), Ok(Amount(n(0.05))));
+    assert_eq!(Price::from_scaled(7, 0), Ok(Price(n(7.0))));
+
+    assert_eq!(Price::from_scaled(0, 2), Err(n(0.0)));
+    assert_eq!(Price::from_scaled(-123, 2), Err(n(-1.23)));
+    assert_eq!(Amount::from_scaled(0, 8), Ok(Amount(n(0.0))));
+    assert_eq!(Amount::from_scaled(-1, 8), Err(n(-1e-8)));
+    // too fine to be a normal float
+    assert!(Price::from_scaled(1, 400).is_err());
+}
+
+#[test]
+fn try_from_iter() {
+    let levels = [(1.0, 1.0), (3.0, 0.0), (2.0, 2.0), (4.0, 1.0)];
+    let orders = levels
+        .iter()
+        .map(|&(price, amount)| unsafe { Order::new_unchecked(price, amount) });
+
+    let diff = OrderBookDiffAsks::try_from_iter(orders.clone()).unwrap();
+    assert_eq!(
+        diff,
+        OrderBookDiffAsks::new(orders.clone().collect()).unwrap()
+    );
+    assert_eq!(diff.0.len(), 4);
+
+    let resting = orders.clone().filter(|order| !order.is_empty());
+    let book = OrderBookBids::try_from_iter(resting.clone()).unwrap();
+    assert_eq!(book, OrderBookBids::new(resting.clone().collect()).unwrap());
+    assert_eq!(book.orders().len(), 3);
+
+    // invalid levels are rejected as new does
+    assert!(matches!(
+        OrderBookBids::try_from_iter(orders),
+        Err(OrderBookError::HasOrderWithEmptyAmount { .. })
+    ));
+    let repeated = unsafe { Order::new_unchecked(1.0, 5.0) };
+    assert!(matches!(
+        OrderBookAsks::try_from_iter(resting.chain([repeated])),
+        Err(OrderBookError::HasOrderWithNotUniquePrice { .. })
+    ));
+    assert_eq!(
+        OrderBookAsks::try_from_iter(std::iter::empty()),
+        Ok(OrderBookAsks::default())
+    );
+}
+
+#[test]
+fn duplicate_policy() {
+    unsafe {
+        let orders = vec![
+            Order::new_unchecked(2.0, 1.0),
+            Order::new_unchecked(1.0, 1.0),
+            Order::new_unchecked(2.0, 3.0),
+        ];
+        assert_eq!(
+            OrderBookDiffAsks::new_with_policy(orders.clone(), DuplicatePolicy::default()),
+            OrderBookDiffAsks::new(orders.clone())
+        );
+        assert!(
+            OrderBookDiffAsks::new_with_policy(orders.clone(), DuplicatePolicy::Reject)
+                .contains_err(&OrderBookError::HasOrderWithNotUniquePrice {
+                    price: Price(n(2.0)),
+                    index: 2
+                })
+        );
+
+        let diff =
+            OrderBookDiffAsks::new_with_policy(orders.clone(), DuplicatePolicy::KeepLast).unwrap();
+        assert_eq!(
+            diff.0,
+            [
+                Order::new_unchecked(1.0, 1.0),
+                Order::new_unchecked(2.0, 3.0)
+            ]
+        );
+        // the latest in the input, not the largest
+        let diff = OrderBookDiffBids::new_with_policy(
+            vec![
+                Order::new_unchecked(2.0, 3.0),
+                Order::new_unchecked(2.0, 0.0),
+            ],
+            DuplicatePolicy::KeepLast,
+        )
+        .unwrap();
+        assert_eq!(diff.0, [Order::new_unchecked(2.0, 0.0)]);
+
+        let diff = OrderBookDiffBids::new_with_policy(orders, DuplicatePolicy::Sum).unwrap();
+        assert_eq!(
+            diff.0,
+            [
+                Order::new_unchecked(2.0, 4.0),
+                Order::new_unchecked(1.0, 1.0)
+            ]
+        );
+        #[cfg(not(feature = "decimal"))]
+        assert_eq!(
+            OrderBookDiffBids::new_with_policy(
+                vec![
+                    Order::new_unchecked(2.0, f64::MAX),
+                    Order::new_unchecked(2.0, f64::MAX),
+                ],
+                DuplicatePolicy::Sum,
+            ),
+            Err(OrderBookError::InvalidSum { price: Price(2.0) })
+        );
+    }
+}
+
+#[test]
+fn quote_at_runtime() {
+    unsafe {
+        let orders = vec![
+            Order::new_unchecked(2.0, 1.0),
+            Order::new_unchecked(1.0, 1.0),
+        ];
+        let quote: Quote = "sell".parse().unwrap();
+        assert_eq!(quote, Quote::Ask);
+        let book = quote.build_book(orders.clone()).unwrap();
+        assert_eq!(book.quote(), Quote::Ask);
+        assert_eq!(book, Book::Asks(OrderBookAsks::new(orders).unwrap()));
+        let Book::Asks(asks) = book else {
+            panic!("asks expected")
+        };
+        assert_eq!(asks.orders()[0], Order::new_unchecked(1.0, 1.0));
+
+        assert_eq!("BUY".parse(), Ok(Quote::Bid));
+        assert_eq!("bids".parse::<Quote>().map(Quote::value), Ok(BID));
+        assert!("hold".parse::<Quote>().is_err());
+        assert_eq!(Quote::of::<ASK>(), Quote::from(ASK));
+        assert_eq!(Quote::Bid.to_string(), "bid");
+        assert!(Quote::Bid
+            .build_book(vec![Order::new_unchecked(1.0, 0.0)])
+            .is_err());
+    }
+}
@@ -1,53 +1,222 @@
 use serde::Deserialize;
 
-fn float_as_string<'de, D>(deserializer: D) -> Result<f64, D::Error>
-where
-    D: serde::Deserializer<'de>,
-{
-    String::deserialize(deserializer)?
-        .parse()
-        .map_err(serde::de::Error::custom)
-}
+#[cfg(test)]
+use crate::core::n;
+use crate::{
+    core,
+    core::Number,
+    feeds::{self, float_as_string},
+};
 
 #[derive(Debug, Deserialize, Clone, Copy)]
 pub struct Order {
     #[serde(deserialize_with = "float_as_string")]
-    pub price: f64,
+    pub price: Number,
     #[serde(deserialize_with = "float_as_string")]
-    pub quantity: f64,
+    pub quantity: Number,
 }
 
-#[cfg(test)]
-macro_rules! assert_feq {
-    ($left:expr, $right:expr $(,)?) => {
-        match (&$left, &$right) {
-            (left_val, right_val) => {
-                if (*left_val - *right_val).abs() > 1E-15 {
-                    panic!("left: {} not equal to right: {}", &*left_val, &*right_val);
-                }
-            }
-        }
-    };
-    ($left:expr, $right:expr, $delta:expr $(,)?) => {
-        match (&$left, &$right, &$delta) {
-            (left_val, right_val, delta_val) => {
-                if (*left_val - *right_val).abs() > *delta_val {
-                    panic!(
-                        "left: {} not equal to right: {} with precision: {}",
-                        &*left_val, &*right_val, &*delta_val
-                    );
-                }
-            }
-        }
-    };
+/// rejects with the offending value orders whose price or quantity isn't valid
+impl TryFrom<Order> for core::Order {
+    type Error = Number;
+
+    fn try_from(order: Order) -> Result<Self, Self::Error> {
+        Ok(core::Order::new(
+            core::Price::new(order.price)?,
+            core::Amount::new(order.quantity)?,
+        ))
+    }
+}
+
+/// Levels of a snapshot, every one rests on the book so none is empty
+/// converts into books only so diff levels can't initialize a book by mistake
+///
+/// ```compile_fail
+/// use aggregator::{core::OrderBookBids, feeds::binance::events::Diff};
+/// let _ = OrderBookBids::try_from(Diff::default());
+/// ```
+#[derive(Debug, Default, Deserialize, Clone)]
+#[serde(transparent)]
+pub struct Snapshot(pub Vec<Order>);
+
+/// Levels of a diff, empty ones remove their prices
+/// converts into diffs only so snapshot levels can't be applied as a diff by mistake
+///
+/// ```compile_fail
+/// use aggregator::{core::OrderBookDiffBids, feeds::binance::events::Snapshot};
+/// let _ = OrderBookDiffBids::try_from(Snapshot::default());
+/// ```
+#[derive(Debug, Default, Deserialize, Clone)]
+#[serde(transparent)]
+pub struct Diff(pub Vec<Order>);
+
+impl Snapshot {
+    /// book of the levels within bounds
+    pub fn into_book<const QUOTE: bool, const COUNT: usize>(
+        self,
+        bounds: &feeds::Bounds,
+    ) -> Result<core::OrderBook<QUOTE, COUNT>, feeds::Error> {
+        core::OrderBook::try_from_iter(orders_within(self.0, bounds)?)
+            .map_err(|e| feeds::Error::Binance(e.to_string()))
+    }
+}
+
+impl Diff {
+    /// diff of the levels within bounds
+    pub fn into_diff<const QUOTE: bool>(
+        self,
+        bounds: &feeds::Bounds,
+    ) -> Result<core::OrderBookDiff<QUOTE>, feeds::Error> {
+        core::OrderBookDiff::try_from_iter(orders_within(self.0, bounds)?)
+            .map_err(|e| feeds::Error::Binance(e.to_string()))
+    }
+}
+
+impl<const QUOTE: bool> TryFrom<Diff> for core::OrderBookDiff<QUOTE> {
+    type Error = feeds::Error;
+
+    fn try_from(diff: Diff) -> Result<Self, Self::Error> {
+        diff.into_diff(&feeds::Bounds::default())
+    }
+}
+
+impl<const QUOTE: bool, const COUNT: usize> TryFrom<Snapshot> for core::OrderBook<QUOTE, COUNT> {
+    type Error = feeds::Error;
+
+    fn try_from(snapshot: Snapshot) -> Result<Self, Self::Error> {
+        snapshot.into_book(&feeds::Bounds::default())
+    }
+}
+
+/// orders within bounds
+fn orders_within(
+    orders: Vec<Order>,
+    bounds: &feeds::Bounds,
+) -> Result<impl Iterator<Item = core::Order> + '_, feeds::Error> {
+    Ok(orders_try_into(orders)?
+        .into_iter()
+        .filter(|order| bounds.contains(order)))
+}
+
+fn orders_try_into(orders: Vec<Order>) -> Result<Vec<core::Order>, feeds::Error> {
+    orders
+        .into_iter()
+        .map(core::Order::try_from)
+        .collect::<Result<_, _>>()
+        .map_err(|e| feeds::Error::Binance(e.to_string()))
 }
 
 #[test]
 fn order() {
     let json = r#"["0.1", "0.2"]"#;
     let order: Order = serde_json::from_str(json).unwrap();
-    assert_feq!(order.price, 0.1);
-    assert_feq!(order.quantity, 0.2);
+    assert_eq!(order.price, n(0.1));
+    assert_eq!(order.quantity, n(0.2));
+
+    for json in [r#"[0.1, 0.2]"#, r#"["0.1", 0.2]"#] {
+        let order: Order = serde_json::from_str(json).unwrap();
+        assert_eq!(order.price, n(0.1));
+        assert_eq!(order.quantity, n(0.2));
+    }
+    let order: Order = serde_json::from_str("[1, 2]").unwrap();
+    assert_eq!(order.quantity, n(2.0));
+
+    assert!(serde_json::from_str::<Order>(r#"["x", "0.2"]"#).is_err());
+    assert!(serde_json::from_str::<Order>(r#"[null, "0.2"]"#).is_err());
+}
+
+#[test]
+fn order_try_into() {
+    let order = core::Order::try_from(Order {
+        price: n(0.1),
+        quantity: n(0.2),
+    })
+    .unwrap();
+    assert_eq!(order.price().into_inner(), n(0.1));
+    assert_eq!(order.amount().into_inner(), n(0.2));
+
+    // 0 quantity of diff removes price level
+    let order = core::Order::try_from(Order {
+        price: n(0.1),
+        quantity: n(0.0),
+    })
+    .unwrap();
+    assert!(order.is_empty());
+
+    let result = core::Order::try_from(Order {
+        price: n(0.0),
+        quantity: n(0.2),
+    });
+    assert!(result.is_err_and(|e| e == n(0.0)));
+
+    let result = core::Order::try_from(Order {
+        price: n(0.1),
+        quantity: n(-0.2),
+    });
+    assert!(result.is_err_and(|e| e == n(-0.2)));
+}
+
+#[cfg(not(feature = "decimal"))]
+#[test]
+fn nan_try_into() {
+    let result = core::Order::try_from(Order {
+        price: 0.1,
+        quantity: f64::NAN,
+    });
+    assert!(result.is_err_and(|e| e.is_nan()));
+
+    let orders = vec![Order {
+        price: f64::NAN,
+        quantity: 0.2,
+    }];
+    assert!(core::OrderBookDiffBids::try_from(Diff(orders)).is_err());
+}
+
+#[test]
+fn order_book_try_into() {
+    let orders = vec![
+        Order {
+            price: n(0.1),
+            quantity: n(0.2),
+        },
+        Order {
+            price: n(0.2),
+            quantity: n(0.0),
+        },
+    ];
+    let diff = core::OrderBookDiffAsks::try_from(Diff(orders.clone())).unwrap();
+    let expected = core::OrderBookDiffAsks::new(vec![
+        core::Order::new(
+            core::Price::new(n(0.1)).unwrap(),
+            core::Amount::new(n(0.2)).unwrap(),
+        ),
+        core::Order::new(core::Price::new(n(0.2)).unwrap(), core::Amount::default()),
+    ])
+    .unwrap();
+    assert!(diff == expected);
+    // snapshots have no empty orders
+    assert!(core::OrderBookAsks::try_from(Snapshot(orders.clone())).is_err());
+    let book = core::OrderBookAsks::try_from(Snapshot(orders[..1].to_vec())).unwrap();
+    assert_eq!(book.orders().len(), 1);
+
+    // bounds drop orders before the empty one is noticed
+    let bounds = feeds::Bounds {
+        max_price: core::Price::new(n(0.15)).ok(),
+        ..Default::default()
+    };
+    let book: core::OrderBookAsks = Snapshot(vec![
+        Order {
+            price: n(0.1),
+            quantity: n(0.2),
+        },
+        Order {
+            price: n(0.2),
+            quantity: n(0.0),
+        },
+    ])
+    .into_book(&bounds)
+    .unwrap();
+    assert_eq!(book.orders().len(), 1);
 }
 
 #[derive(Debug, Deserialize, Clone)]
@@ -65,10 +234,10 @@ pub struct OrderBookDiff {
     pub final_update_id: u64,
 
     #[serde(rename = "b")]
-    pub bids: Vec<Order>,
+    pub bids: Diff,
 
     #[serde(rename = "a")]
-    pub asks: Vec<Order>,
+    pub asks: Diff,
 }
 
 #[test]
@@ -111,8 +280,8 @@ pub use TypedEvent::*;
 #[serde(rename_all = "camelCase")]
 pub struct OrderBook {
     pub last_update_id: u64,
-    pub bids: Vec<Order>,
-    pub asks: Vec<Order>,
+    pub bids: Snapshot,
+    pub asks: Snapshot,
 }
 
 #[test]
@@ -121,17 +290,85 @@ fn order_book() {
         r#"{ "lastUpdateId" : 160, "bids": [["0.0024", "10"]], "asks": [["0.0026", "100.1"]] }"#;
     let book: OrderBook = serde_json::from_str(json).unwrap();
     assert_eq!(book.last_update_id, 160);
-    assert_feq!(book.bids[0].price, 0.0024);
-    assert_feq!(book.bids[0].quantity, 10.0);
-    assert_feq!(book.asks[0].price, 0.0026);
-    assert_feq!(book.asks[0].quantity, 100.1);
+    assert_eq!(book.bids.0[0].price, n(0.0024));
+    assert_eq!(book.bids.0[0].quantity, n(10.0));
+    assert_eq!(book.asks.0[0].price, n(0.0026));
+    assert_eq!(book.asks.0[0].quantity, n(100.1));
+}
+
+/// best levels of the symbol e.g. `{"u":400900217,"s":"BNBUSDT","b":"25.35","B":"31.21","a":"25.36","A":"40.66"}`
+#[derive(Debug, Deserialize, Clone)]
+pub struct BookTicker {
+    #[serde(rename = "u")]
+    pub update_id: u64,
+
+    #[serde(rename = "s")]
+    pub symbol: String,
+
+    #[serde(rename = "b", deserialize_with = "float_as_string")]
+    pub bid_price: Number,
+
+    #[serde(rename = "B", deserialize_with = "float_as_string")]
+    pub bid_quantity: Number,
+
+    #[serde(rename = "a", deserialize_with = "float_as_string")]
+    pub ask_price: Number,
+
+    #[serde(rename = "A", deserialize_with = "float_as_string")]
+    pub ask_quantity: Number,
 }
 
+impl BookTicker {
+    pub fn bid(&self) -> Order {
+        Order {
+            price: self.bid_price,
+            quantity: self.bid_quantity,
+        }
+    }
+    pub fn ask(&self) -> Order {
+        Order {
+            price: self.ask_price,
+            quantity: self.ask_quantity,
+        }
+    }
+}
+
+#[test]
+fn book_ticker() {
+    let json = r#"{"u":400900217,"s":"BNBUSDT","b":"25.35190000","B":"31.21000000","a":"25.36520000","A":"40.66000000"}"#;
+    let event: Event = serde_json::from_str(json).unwrap();
+    assert!(matches!(
+        event,
+        BookTicker(ref ticker) if ticker.update_id == 400900217 && ticker.symbol == "BNBUSDT"
+    ));
+
+    // depth updates have lower case fields only
+    let json = r#"{"E":1,"s":"BNBBTC","U":157,"u":160,"b":[],"a":[]}"#;
+    assert!(serde_json::from_str::<BookTicker>(json).is_err());
+}
+
+/// variants are told apart by their fields, unknown fields are ignored
 #[derive(Deserialize, Debug)]
 #[serde(untagged)]
 pub enum Event {
+    /// event of the combined stream e.g. `{"stream":"bnbbtc@depth10@100ms","data":{..}}`
+    Combined {
+        stream: String,
+        data: Box<Event>,
+    },
     Typed(TypedEvent),
     OrderBook(OrderBook),
+    BookTicker(BookTicker),
+    /// rejected request e.g. `{"code":2,"msg":"Invalid request","id":1}`
+    ApiError {
+        code: i64,
+        msg: String,
+    },
+    /// acknowledgement of a request e.g. `{"result":null,"id":1}`
+    Control {
+        result: Option<serde_json::Value>,
+        id: u64,
+    },
 }
 
 pub use Event::*;
@@ -171,3 +408,57 @@ fn event() {
     let result: serde_json::error::Result<Event> = serde_json::from_str(json);
     assert!(matches!(result, Err(_)));
 }
+
+#[test]
+fn control() {
+    let event: Event = serde_json::from_str(r#"{"result":null,"id":1}"#).unwrap();
+    assert!(matches!(
+        event,
+        Control {
+            result: None,
+            id: 1
+        }
+    ));
+
+    let event: Event = serde_json::from_str(r#"{"result":["bnbbtc@depth"],"id":3}"#).unwrap();
+    assert!(matches!(
+        event,
+        Control {
+            result: Some(_),
+            id: 3
+        }
+    ));
+
+    let json = r#"{"code": 0, "msg": "Unknown property","id": 2}"#;
+    let event: Event = serde_json::from_str(json).unwrap();
+    assert!(matches!(event, ApiError { code: 0, msg } if msg == "Unknown property"));
+}
+
+#[test]
+fn combined() {
+    let json = r#"{"stream":"bnbbtc@depth10@100ms","data":{"lastUpdateId":160,"bids":[["0.0024","10"]],"asks":[]}}"#;
+    let event: Event = serde_json::from_str(json).unwrap();
+    assert!(matches!(
+        event,
+        Combined { stream, data } if stream == "bnbbtc@depth10@100ms"
+            && matches!(*data, OrderBook(ref book) if book.last_update_id == 160)
+    ));
+
+    // fields in any order with unknown ones
+    let json = r#"{"data":{"asks":[],"E":1,"bids":[],"lastUpdateId":7},"extra":1,"stream":"bnbbtc@depth5"}"#;
+    let event: Event = serde_json::from_str(json).unwrap();
+    assert!(matches!(
+        event,
+        Combined { data, .. } if matches!(*data, OrderBook(ref book) if book.last_update_id == 7)
+    ));
+
+    let json = r#"{"stream":"bnbbtc@depth","data":{"u":160,"s":"BNBBTC","e":"depthUpdate","a":[],"b":[],"U":157,"E":1,"T":2}}"#;
+    let event: Event = serde_json::from_str(json).unwrap();
+    assert!(matches!(
+        event,
+        Combined { data, .. } if matches!(*data, Typed(DepthUpdate(ref diff)) if diff.final_update_id == 160)
+    ));
+
+    let json = r#"{"stream":"bnbbtc@depth","data":{"lastUpdateId":7}}"#;
+    assert!(serde_json::from_str::<Event>(json).is_err());
+}
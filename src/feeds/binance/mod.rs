@@ -1,32 +1,33 @@
 use crate::{
-    core::{
-        Amount, Order, OrderBookAsks, OrderBookBids, OrderBookDiffAsks, OrderBookDiffBids, Price,
+    core::{OrderBookDiffAsks, OrderBookDiffBids, ASK, BID},
+    feeds::{
+        clock::{Clock, TokioClock},
+        Bounds, Codec, FeedHealth, FeedStats, Stats, TopOfBook,
     },
+    symbol::Symbol,
     *,
 };
-use std::{
-    collections::HashMap, error::Error, fmt::Pointer, net::SocketAddr, sync::Arc, time::Duration,
-};
+use std::{collections::HashMap, sync::Arc, time::Duration};
 
 use futures_channel::mpsc::UnboundedSender;
-use futures_util::{
-    future,
-    lock::{Mutex, MutexGuard},
-    select, SinkExt, StreamExt, TryStreamExt,
-};
+use futures_util::{SinkExt, StreamExt};
 use strum::{EnumIter, IntoEnumIterator};
-use tokio::{
-    net::TcpStream,
-    task::JoinHandle,
-    time::{self, Timeout},
-};
+use tokio::{net::TcpStream, sync::watch, task::JoinHandle};
+#[cfg(not(feature = "native-tls"))]
+use tokio_tungstenite::connect_async_with_config;
+#[cfg(feature = "native-tls")]
+use tokio_tungstenite::{connect_async_tls_with_config, Connector};
 use tokio_tungstenite::{
-    connect_async,
-    tungstenite::{client::IntoClientRequest, http::request::Builder, Message},
+    tungstenite::{
+        client::IntoClientRequest,
+        http::{header::HeaderName, HeaderMap, HeaderValue},
+        protocol::WebSocketConfig,
+        Message,
+    },
     MaybeTlsStream, WebSocketStream,
 };
 
-use self::events::{OrderBook, OrderBookDiff};
+use self::events::{BookTicker, OrderBook, OrderBookDiff};
 
 #[derive(Default, Debug, Clone, Copy, Eq, PartialEq)]
 pub enum BookPeriod {
@@ -43,11 +44,59 @@ pub enum BookDepth {
     Large = 20,
 }
 
-type OrderBookTx = UnboundedSender<(core::OrderBookBids, core::OrderBookAsks)>;
+impl BookPeriod {
+    pub fn millis(&self) -> u32 {
+        match self {
+            Self::Normal => 1000,
+            Self::Fast => 100,
+        }
+    }
+}
+
+/// fails with the value if no period matches it
+impl TryFrom<u32> for BookPeriod {
+    type Error = u32;
+    fn try_from(millis: u32) -> Result<Self, Self::Error> {
+        [Self::Normal, Self::Fast]
+            .into_iter()
+            .find(|period| period.millis() == millis)
+            .ok_or(millis)
+    }
+}
+
+impl BookDepth {
+    pub fn levels(&self) -> u8 {
+        match self {
+            Self::Small => 5,
+            Self::Medium => 10,
+            Self::Large => 20,
+        }
+    }
+}
+
+/// fails with the value if no depth matches it
+impl TryFrom<u8> for BookDepth {
+    type Error = u8;
+    fn try_from(levels: u8) -> Result<Self, Self::Error> {
+        [Self::Small, Self::Medium, Self::Large]
+            .into_iter()
+            .find(|depth| depth.levels() == levels)
+            .ok_or(levels)
+    }
+}
+
+pub type OrderBookTx = feeds::BookTx;
+/// unbounded so the latest best levels are never held back by the subscriber
+pub type TopOfBookTx = UnboundedSender<TopOfBook>;
+
+/// diff streams carry changes at any depth so more levels than sent are kept
+/// to backfill the top when best levels are removed
+const DEPTH: usize = 1000;
 
 #[derive(EnumIter)]
 enum SubscriptionMember {
     OrderBook,
+    TopOfBook,
 }
 
 // #[derive(Clone)]
@@ -77,8 +126,10 @@ struct OrderBookSubscriptionState {
     tx: OrderBookTx,
     period: BookPeriod,
     depth: Option<BookDepth>,
-    bids: OrderBookBids,
-    asks: OrderBookAsks,
+    bids: core::OrderBook<BID, DEPTH>,
+    asks: core::OrderBook<ASK, DEPTH>,
+    /// final update id of the last applied diff
+    last_update_id: Option<u64>,
 }
 
 impl OrderBookSubscriptionState {
@@ -89,6 +140,7 @@ impl OrderBookSubscriptionState {
             depth,
             bids: Default::default(),
             asks: Default::default(),
+            last_update_id: None,
         }
     }
 
@@ -96,15 +148,27 @@ impl OrderBookSubscriptionState {
         format!(
             "depth{}@{}ms",
             self.depth
-                .map_or(String::default(), |d| (d as u8).to_string()),
-            self.period as u8
+                .map_or(String::default(), |depth| depth.levels().to_string()),
+            self.period.millis()
         )
     }
 }
 
 #[derive(Clone)]
+struct TopOfBookSubscriptionState {
+    tx: TopOfBookTx,
+}
+
+impl TopOfBookSubscriptionState {
+    fn to_subscription_string(&self) -> String {
+        "bookTicker".into()
+    }
+}
+
+#[derive(Clone, Default)]
 struct Subscriptions {
     order_book: Option<OrderBookSubscriptionState>,
+    top_of_book: Option<TopOfBookSubscriptionState>,
 }
 
 // impl<'a> IntoIterator for &'a Subscriptions {
@@ -116,11 +180,40 @@ struct Subscriptions {
 //     }
 // }
 
+/// Decodes Binance frames, text frames are JSON, binary frames are treated as UTF-8 JSON
+#[derive(Default, Debug, Clone, Copy)]
+pub struct JsonCodec;
+
+impl Codec for JsonCodec {
+    type Event = events::Event;
+
+    fn decode_text(&self, text: &str) -> Result<Self::Event, feeds::Error> {
+        serde_json::from_str(text).map_err(|e| feeds::Error::Binance(e.to_string()))
+    }
+    fn decode_binary(&self, binary: &[u8]) -> Result<Self::Event, feeds::Error> {
+        serde_json::from_slice(binary).map_err(|e| feeds::Error::Binance(e.to_string()))
+    }
+}
+
 #[derive(Clone)]
-struct Config {
+pub struct Config {
     url: url::Url,
     subscriptions: HashMap<String, Subscriptions>,
-    depth_order_book: String,
+    codec: Arc<dyn Codec<Event = events::Event>>,
+    stale_after: Option<Duration>,
+    bounds: Bounds,
+    stats: Arc<Stats>,
+    /// extra headers of the handshake request e.g. API key
+    headers: HeaderMap,
+    /// receives parse errors instead of failing the feed
+    error_sink: Option<UnboundedSender<feeds::Error>>,
+    clock: Arc<dyn Clock>,
+    /// limits of the socket e.g. message and frame sizes, tungstenite defaults if none
+    websocket: Option<WebSocketConfig>,
+    max_messages_per_second: Option<u32>,
+    /// e.g. with a specific root store, native-tls defaults if none
+    #[cfg(feature = "native-tls")]
+    tls: Option<Connector>,
 }
 
 impl Default for Config {
@@ -133,35 +226,104 @@ impl Config {
     pub fn new(url: url::Url) -> Self {
         Self {
             url,
-            ..Default::default()
+            subscriptions: HashMap::default(),
+            codec: Arc::new(JsonCodec),
+            stale_after: None,
+            bounds: Bounds::default(),
+            stats: Arc::default(),
+            headers: HeaderMap::default(),
+            error_sink: None,
+            clock: Arc::new(TokioClock),
+            websocket: None,
+            max_messages_per_second: None,
+            #[cfg(feature = "native-tls")]
+            tls: None,
         }
     }
+    /// replaces default JSON codec used to decode inbound frames
+    pub fn with_codec<C: Codec<Event = events::Event> + 'static>(mut self, codec: C) -> Self {
+        self.codec = Arc::new(codec);
+        self
+    }
+    /// fails the feed with Error::Stale if no order book message arrives within the window
+    pub fn stale_after(mut self, window: Duration) -> Self {
+        self.stale_after = Some(window);
+        self
+    }
+    /// replaces tokio time used for timeouts e.g. with MockClock in tests
+    pub fn with_clock(mut self, clock: impl Clock + 'static) -> Self {
+        self.clock = Arc::new(clock);
+        self
+    }
+    /// adds the header to the handshake request replacing the previous value if any
+    pub fn with_header(mut self, name: HeaderName, value: HeaderValue) -> Self {
+        self.headers.insert(name, value);
+        self
+    }
+    /// limits of the socket, frames over them fail the feed with Error::WS
+    pub fn with_websocket_config(mut self, websocket: WebSocketConfig) -> Self {
+        self.websocket = Some(websocket);
+        self
+    }
+    /// connector of wss connections, Connector::Plain disables TLS
+    #[cfg(feature = "native-tls")]
+    pub fn with_tls_connector(mut self, connector: Connector) -> Self {
+        self.tls = Some(connector);
+        self
+    }
+    /// fails the feed with Error::Flood if more messages of any kind arrive within a second
+    pub fn max_messages_per_second(mut self, limit: u32) -> Self {
+        self.max_messages_per_second = Some(limit);
+        self
+    }
+    /// reports frames that fail to parse to the sink and keeps the feed running
+    /// transport errors, API errors and gaps still fail the feed
+    pub fn error_sink(mut self, sink: UnboundedSender<feeds::Error>) -> Self {
+        self.error_sink = Some(sink);
+        self
+    }
+    /// drops orders out of bounds before books are built
+    pub fn bounds(mut self, bounds: Bounds) -> Self {
+        self.bounds = bounds;
+        self
+    }
     pub fn subscribe_order_book(
         mut self,
-        tx: OrderBookTx,
-        symbol: String,
+        tx: impl Into<OrderBookTx>,
+        symbol: Symbol,
         period: BookPeriod,
         depth: Option<BookDepth>,
     ) -> Self {
+        let symbol = symbol.to_binance();
         assert!(
-            self.subscriptions[&symbol].order_book.is_none(),
+            self.subscriptions
+                .get(&symbol)
+                .map_or(true, |subscriptions| subscriptions.order_book.is_none()),
             "order book stream has already subscribed for {symbol}"
         );
+        trace_event!(DEBUG, %symbol, ?period, ?depth, "subscribe order book");
+        self.subscriptions.entry(symbol).or_default().order_book =
+            Some(OrderBookSubscriptionState::new(tx.into(), period, depth));
+        self
+    }
+    /// sends only best levels of the book ticker stream, no books are built for them
+    pub fn subscribe_top_of_book(mut self, tx: TopOfBookTx, symbol: Symbol) -> Self {
+        let symbol = symbol.to_binance();
         assert!(
-            depth.is_none() || self.depth_order_book.is_empty(),
-            "Partial Book Depth Streams don't contain symbol to distinguish them"
-        );
-        if depth.is_some() {
-            self.depth_order_book = symbol.clone();
-        };
-        self.subscriptions.insert(
-            symbol,
-            feeds::binance::Subscriptions {
-                order_book: Some(OrderBookSubscriptionState::new(tx, period, depth)),
-            },
+            self.subscriptions
+                .get(&symbol)
+                .map_or(true, |subscriptions| subscriptions.top_of_book.is_none()),
+            "top of book stream has already subscribed for {symbol}"
         );
+        trace_event!(DEBUG, %symbol, "subscribe top of book");
+        self.subscriptions.entry(symbol).or_default().top_of_book =
+            Some(TopOfBookSubscriptionState { tx });
         self
     }
+    #[cfg_attr(
+        feature = "tracing",
+        tracing::instrument(name = "binance_connect", skip_all, fields(url = %self.url))
+    )]
     pub async fn connect(self) -> Result<Feed, feeds::Error> {
         let mut url = self.url.clone();
         url.set_path("stream");
@@ -170,146 +332,370 @@ impl Config {
                 "streams={}",
                 self.subscriptions
                     .iter()
-                    .map(|(symbol, subscriptions)| {
+                    .flat_map(|(symbol, subscriptions)| {
                         SubscriptionMember::iter()
                             .filter_map(|member| match member {
                                 SubscriptionMember::OrderBook => subscriptions
                                     .order_book
                                     .as_ref()
                                     .map(|state| state.to_subscription_string()),
+                                SubscriptionMember::TopOfBook => subscriptions
+                                    .top_of_book
+                                    .as_ref()
+                                    .map(|state| state.to_subscription_string()),
                             })
-                            .next()
-                            .into_iter()
                             .map(move |string| format!("{symbol}@{string}"))
                             .intersperse("/".into())
                     })
-                    .flatten()
                     .intersperse("/".into())
                     .collect::<String>()
             )
             .as_str(),
         ));
-        Ok(Feed::new(connect_async(url).await?.0, self))
+        let mut request = url.into_client_request()?;
+        request.headers_mut().extend(self.headers.clone());
+        #[cfg(feature = "native-tls")]
+        let (stream, _) =
+            connect_async_tls_with_config(request, self.websocket, self.tls.clone()).await?;
+        #[cfg(not(feature = "native-tls"))]
+        let (stream, _) = connect_async_with_config(request, self.websocket).await?;
+        Ok(Feed::new(stream, self))
     }
 }
 
-struct Feed {
-    config: Config,
+/// what a message carries for the subscriber of its symbol
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub(crate) enum Dispatched {
+    Book(feeds::BookUpdate),
+    Top(TopOfBook),
+}
+
+pub struct Feed {
+    task: JoinHandle<Result<(), feeds::Error>>,
+    stats: Arc<Stats>,
+    health: watch::Receiver<FeedHealth>,
 }
 
 impl Drop for Feed {
-    fn drop(&mut self) {}
+    fn drop(&mut self) {
+        self.task.abort();
+    }
 }
 
 impl Feed {
-    fn depth_update(config: &mut Config, diff: OrderBookDiff) -> Result<(), feeds::Error> {
+    fn depth_update(
+        config: &mut Config,
+        diff: OrderBookDiff,
+    ) -> Result<Option<(String, Dispatched)>, feeds::Error> {
+        // e.g. a recording of more streams than the replay subscribes to
+        let Some(state) = config
+            .subscriptions
+            // events carry upper case symbols while streams are named in lower case
+            .get_mut(&diff.symbol.to_lowercase())
+            .and_then(|subscriptions| subscriptions.order_book.as_mut())
+        else {
+            trace_event!(DEBUG, symbol = %diff.symbol, "depth update of unsubscribed symbol");
+            return Ok(None);
+        };
+
+        trace_event!(
+            TRACE,
+            symbol = %diff.symbol,
+            event_time = diff.event_time,
+            first_update_id = diff.first_update_id,
+            final_update_id = diff.final_update_id,
+            "depth update"
+        );
+        // continuity relies on update ids only so it doesn't depend on the update period
+        if let Some(last) = state.last_update_id {
+            if diff.final_update_id <= last {
+                // already applied
+                return Ok(None);
+            }
+            if diff.first_update_id > last + 1 {
+                trace_event!(
+                    WARN,
+                    symbol = %diff.symbol,
+                    expected = last + 1,
+                    got = diff.first_update_id,
+                    "missed updates"
+                );
+                return Err(feeds::Error::Gap {
+                    expected: last + 1,
+                    got: diff.first_update_id,
+                });
+            }
+        }
+        // a diff failing to convert isn't applied so the next one is reported as a gap
+        let bids: OrderBookDiffBids = diff.bids.into_diff(&config.bounds)?;
+        let asks: OrderBookDiffAsks = diff.asks.into_diff(&config.bounds)?;
+        state.last_update_id = Some(diff.final_update_id);
+        state.bids = state.bids.update(&bids);
+        state.asks = state.asks.update(&asks);
+
+        let update = feeds::BookUpdate {
+            time: Some(diff.event_time),
+            seq: diff.final_update_id,
+            bids: state.bids.best(),
+            asks: state.asks.best(),
+        };
+        Ok(Some((diff.symbol.to_lowercase(), Dispatched::Book(update))))
+    }
+    /// partial book depth events have no symbol so it's the one of the combined stream
+    /// or the only partial book depth subscription if the event came unwrapped
+    fn order_book(
+        config: &mut Config,
+        book: OrderBook,
+        symbol: Option<&str>,
+    ) -> Result<Option<(String, Dispatched)>, feeds::Error> {
+        let symbol = match symbol {
+            Some(symbol) => symbol.to_owned(),
+            None => {
+                let mut depths = config.subscriptions.iter().filter(|(_, subscriptions)| {
+                    subscriptions
+                        .order_book
+                        .as_ref()
+                        .is_some_and(|state| state.depth.is_some())
+                });
+                match (depths.next(), depths.next()) {
+                    (Some((symbol, _)), None) => symbol.clone(),
+                    _ => {
+                        return Err(feeds::Error::Binance(
+                            "partial book depth event has no stream to tell its symbol".into(),
+                        ))
+                    }
+                }
+            }
+        };
+        let bids = book.bids.into_book(&config.bounds)?;
+        let asks = book.asks.into_book(&config.bounds)?;
+        let Some(state) = config
+            .subscriptions
+            .get_mut(&symbol)
+            .and_then(|subscriptions| subscriptions.order_book.as_mut())
+        else {
+            trace_event!(DEBUG, %symbol, "partial book depth of unsubscribed symbol");
+            return Ok(None);
+        };
+
+        trace_event!(
+            TRACE,
+            %symbol,
+            last_update_id = book.last_update_id,
+            "partial book depth"
+        );
+        // the snapshot anchors the sync so diffs it already contains are dropped, see depth_update
+        state.last_update_id = Some(book.last_update_id);
+        state.bids = bids;
+        state.asks = asks;
+
+        // partial book depth streams have no event time
+        let update = feeds::BookUpdate {
+            time: None,
+            seq: book.last_update_id,
+            bids: state.bids.best(),
+            asks: state.asks.best(),
+        };
+        Ok(Some((symbol, Dispatched::Book(update))))
+    }
+    /// best levels go as they are, out of bounds ones are dropped as there is nothing to backfill
+    fn top_of_book(
+        config: &mut Config,
+        ticker: BookTicker,
+    ) -> Result<Option<(String, Dispatched)>, feeds::Error> {
+        let symbol = ticker.symbol.to_lowercase();
+        let subscribed = config
+            .subscriptions
+            .get(&symbol)
+            .is_some_and(|subscriptions| subscriptions.top_of_book.is_some());
+        if !subscribed {
+            trace_event!(DEBUG, %symbol, "book ticker of unsubscribed symbol");
+            return Ok(None);
+        }
+        trace_event!(TRACE, %symbol, update_id = ticker.update_id, "book ticker");
+        let [bid, ask] = [ticker.bid(), ticker.ask()].map(|order| {
+            core::Order::try_from(order).map_err(|e| feeds::Error::Binance(e.to_string()))
+        });
+        let top = TopOfBook {
+            bid: bid?,
+            ask: ask?,
+        };
+        if !(config.bounds.contains(&top.bid) && config.bounds.contains(&top.ask)) {
+            return Ok(None);
+        }
+        Ok(Some((symbol, Dispatched::Top(top))))
+    }
+
+    /// returns books to be sent to the subscriber of the symbol if any
+    pub(crate) fn dispatch(
+        config: &mut Config,
+        message: Message,
+    ) -> Result<Option<(String, Dispatched)>, feeds::Error> {
+        #[cfg(feature = "tracing")]
+        let _span = tracing::trace_span!("binance_dispatch").entered();
+        let event = match message {
+            Message::Text(text) => config.codec.decode_text(&text)?,
+            Message::Binary(binary) => config.codec.decode_binary(&binary)?,
+            _ => return Ok(None),
+        };
+        Self::event(config, event, None)
+    }
+    /// symbol is the one of the combined stream the event came from if any
+    fn event(
+        config: &mut Config,
+        event: events::Event,
+        symbol: Option<&str>,
+    ) -> Result<Option<(String, Dispatched)>, feeds::Error> {
+        match event {
+            events::Event::Combined { stream, data } => {
+                // streams are named symbol@stream
+                let symbol = stream.split('@').next();
+                Self::event(config, *data, symbol)
+            }
+            events::Event::Typed(events::TypedEvent::DepthUpdate(diff)) => {
+                Self::depth_update(config, diff)
+            }
+            events::Event::OrderBook(book) => Self::order_book(config, book, symbol),
+            events::Event::BookTicker(ticker) => Self::top_of_book(config, ticker),
+            // acks of requests carry no books
+            events::Event::Control { .. } => Ok(None),
+            events::Event::ApiError { code, msg } => Err(feeds::Error::BinanceApi { code, msg }),
+        }
+    }
+
+    /// dispatches the message and sends books waiting for room in bounded channel
+    /// returns false if the message carries no new books
+    async fn handle(config: &mut Config, message: Message) -> Result<bool, feeds::Error> {
+        config.stats.message();
+        let dispatched = match Self::dispatch(config, message) {
+            Err(e @ feeds::Error::Binance(_)) => {
+                trace_event!(WARN, error = %e, "parse error");
+                config.stats.parse_error();
+                let Some(sink) = &config.error_sink else {
+                    return Err(e);
+                };
+                // the feed goes on even if nobody listens for errors anymore
+                let _ = sink.unbounded_send(e);
+                return Ok(false);
+            }
+            dispatched => dispatched?,
+        };
+        match dispatched {
+            Some((symbol, Dispatched::Book(update))) => {
+                config.stats.book(update.time);
+                Self::send(config, &symbol, update).await;
+            }
+            Some((symbol, Dispatched::Top(top))) => {
+                // book ticker has no event time
+                config.stats.book(None);
+                Self::send_top(config, &symbol, top);
+            }
+            None => return Ok(false),
+        }
+        Ok(true)
+    }
+    /// counters of the feed so far
+    pub fn stats(&self) -> FeedStats {
+        self.stats.snapshot()
+    }
+
+    /// sends books to the subscriber of the symbol waiting for room in bounded channel
+    pub(crate) async fn send(config: &mut Config, symbol: &str, update: feeds::BookUpdate) {
         let state = config
             .subscriptions
-            .get_mut(&diff.symbol)
+            .get_mut(symbol)
             .expect("message for unsubscribed symbol")
             .order_book
             .as_mut()
             .expect("message for unsubscribed stream");
+        // nobody listens anymore, it isn't an error of the feed
+        state.tx.send(update).await.ok();
+    }
 
-        let bids: Result<Vec<_>, f64> = diff
-            .bids
-            .iter()
-            .map(|order| {
-                Ok(Order::new(
-                    Price::new(order.price)?,
-                    Amount::new(order.quantity)?,
-                ))
-            })
-            .collect();
-        let bids = OrderBookDiffBids::new(bids.map_err(|e| feeds::Error::Binance(e.to_string()))?)
-            .map_err(|e| feeds::Error::Binance(e.to_string()))?;
-        let bids = state.bids.update(&bids);
-        state.bids = bids.clone();
-
-        let asks: Result<Vec<_>, f64> = diff
-            .asks
-            .iter()
-            .map(|order| {
-                Ok(Order::new(
-                    Price::new(order.price)?,
-                    Amount::new(order.quantity)?,
-                ))
-            })
-            .collect();
-        let asks = OrderBookDiffAsks::new(asks.map_err(|e| feeds::Error::Binance(e.to_string()))?)
-            .map_err(|e| feeds::Error::Binance(e.to_string()))?;
-        let asks = state.asks.update(&asks);
-        state.asks = asks.clone();
-
-        state.tx.unbounded_send((bids, asks));
-        Ok(())
-    }
-    fn order_book(config: &mut Config, book: OrderBook) -> Result<(), feeds::Error> {
+    fn send_top(config: &mut Config, symbol: &str, top: TopOfBook) {
         let state = config
             .subscriptions
-            .get_mut(&config.depth_order_book)
+            .get(symbol)
             .expect("message for unsubscribed symbol")
-            .order_book
-            .as_mut()
+            .top_of_book
+            .as_ref()
             .expect("message for unsubscribed stream");
+        // nobody listens anymore, it isn't an error of the feed
+        state.tx.unbounded_send(top).ok();
+    }
 
-        let bids: Result<Vec<_>, f64> = book
-            .bids
-            .iter()
-            .map(|order| {
-                Ok(Order::new(
-                    Price::new(order.price)?,
-                    Amount::new(order.quantity)?,
-                ))
-            })
-            .collect();
-        let bids = OrderBookBids::new(bids.map_err(|e| feeds::Error::Binance(e.to_string()))?)
-            .map_err(|e| feeds::Error::Binance(e.to_string()))?;
-
-        let asks: Result<Vec<_>, f64> = book
-            .asks
-            .iter()
-            .map(|order| {
-                Ok(Order::new(
-                    Price::new(order.price)?,
-                    Amount::new(order.quantity)?,
-                ))
-            })
-            .collect();
-        let asks = OrderBookAsks::new(asks.map_err(|e| feeds::Error::Binance(e.to_string()))?)
-            .map_err(|e| feeds::Error::Binance(e.to_string()))?;
-
-        state.tx.unbounded_send((bids, asks));
-        Ok(())
-    }
-
-    pub fn new(mut stream: WebSocketStream<MaybeTlsStream<TcpStream>>, mut config: Config) -> Self {
-        let (sink, stream) = stream.split();
-        let shared_config = Arc::new(Mutex::new(config.clone()));
-        let stream = stream
-            .err_into::<feeds::Error>()
-            .try_for_each(move |message| {
-                let mut config_copy = shared_config.clone();
-                async move {
-                    let mut config = config_copy.lock_owned().await;
-                    if let Message::Text(json) = message {
-                        let event = serde_json::from_str::<events::Event>(&json)
-                            .map_err(|e| feeds::Error::Binance(e.to_string()))?;
-                        match event {
-                            events::Event::Typed(events::TypedEvent::DepthUpdate(diff)) => {
-                                Self::depth_update(&mut config, diff)
+    pub fn new(stream: WebSocketStream<MaybeTlsStream<TcpStream>>, mut config: Config) -> Self {
+        let (mut sink, mut stream) = stream.split();
+        let stats = config.stats.clone();
+        let (health_tx, health) = watch::channel(FeedHealth {
+            connected: true,
+            last_message_at: config.clock.now(),
+            reconnects: 0,
+        });
+        let task = async move {
+            let result = async {
+                // disabled branch still needs a timer
+                let mut stale = config.clock.sleep(config.stale_after.unwrap_or_default());
+                // start of the current second and messages within it
+                let mut second = (config.clock.now(), 0);
+                loop {
+                    tokio::select! {
+                        message = stream.next() => match message {
+                            Some(message) => {
+                                let now = config.clock.now();
+                                health_tx.send_modify(|health| health.last_message_at = now);
+                                if let Some(limit) = config.max_messages_per_second {
+                                    if now.duration_since(second.0) >= Duration::from_secs(1) {
+                                        second = (now, 0);
+                                    }
+                                    second.1 += 1;
+                                    if second.1 > limit {
+                                        return Err(feeds::Error::Flood(limit));
+                                    }
+                                }
+                                let message = message?;
+                                if let Message::Close(frame) = message {
+                                    // flushes the reply queued on receipt to complete the handshake
+                                    sink.close().await.ok();
+                                    return Err(feeds::Error::ClosedByServer(frame));
+                                }
+                                if Self::handle(&mut config, message).await? {
+                                    if let Some(window) = config.stale_after {
+                                        stale = config.clock.sleep(window);
+                                    }
+                                }
                             }
-                            events::Event::OrderBook(book) => Self::order_book(&mut config, book),
+                            None => return Ok(()),
+                        },
+                        _ = &mut stale, if config.stale_after.is_some() => {
+                            return Err(feeds::Error::Stale(config.stale_after.unwrap()));
                         }
-                    } else {
-                        Ok(())
                     }
                 }
-            });
-        Self { config }
+            }
+            .await;
+            health_tx.send_modify(|health| health.connected = false);
+            #[cfg(feature = "tracing")]
+            if let Err(e) = &result {
+                tracing::warn!(error = %e, "feed failed");
+            }
+            result
+        };
+        #[cfg(feature = "tracing")]
+        let task = tracing::Instrument::instrument(task, tracing::info_span!("binance_feed"));
+        Self {
+            task: tokio::spawn(task),
+            stats,
+            health,
+        }
+    }
+    /// liveness of the feed, the sender is gone once the feed is dropped
+    pub fn health(&self) -> watch::Receiver<FeedHealth> {
+        self.health.clone()
     }
 }
 
 pub mod events;
+pub mod pool;
 #[cfg(test)]
 mod tests;
@@ -0,0 +1,84 @@
+use std::sync::Arc;
+
+use futures_util::future::try_join_all;
+use itertools::Itertools;
+
+use crate::{
+    feeds::{
+        self,
+        binance::{BookDepth, BookPeriod, Config, Feed, OrderBookTx, TopOfBookTx},
+    },
+    symbol::Symbol,
+};
+
+/// adds a single stream to the config of a connection
+type Subscribe = Box<dyn FnOnce(Config) -> Config + Send>;
+
+/// Spreads streams of many symbols over as few connections as the cap allows
+/// every connection is a Feed of its own configured as the template
+pub struct FeedPool {
+    config: Config,
+    cap: usize,
+    subscriptions: Vec<Subscribe>,
+}
+
+impl FeedPool {
+    /// Binance limit of streams per connection
+    pub const MAX_STREAMS: usize = 1024;
+
+    /// subscriptions of the template are dropped, subscribe through the pool instead
+    pub fn new(mut config: Config) -> Self {
+        config.subscriptions.clear();
+        Self {
+            config,
+            cap: Self::MAX_STREAMS,
+            subscriptions: Vec::default(),
+        }
+    }
+    /// opens another connection once this many streams are subscribed
+    pub fn streams_per_connection(mut self, cap: usize) -> Self {
+        assert!(
+            (1..=Self::MAX_STREAMS).contains(&cap),
+            "connection carries 1 to {} streams",
+            Self::MAX_STREAMS
+        );
+        self.cap = cap;
+        self
+    }
+    /// see Config::subscribe_order_book
+    pub fn subscribe_order_book(
+        mut self,
+        tx: impl Into<OrderBookTx>,
+        symbol: Symbol,
+        period: BookPeriod,
+        depth: Option<BookDepth>,
+    ) -> Self {
+        let tx = tx.into();
+        self.subscriptions.push(Box::new(move |config: Config| {
+            config.subscribe_order_book(tx, symbol, period, depth)
+        }));
+        self
+    }
+    /// see Config::subscribe_top_of_book
+    pub fn subscribe_top_of_book(mut self, tx: TopOfBookTx, symbol: Symbol) -> Self {
+        self.subscriptions.push(Box::new(move |config: Config| {
+            config.subscribe_top_of_book(tx, symbol)
+        }));
+        self
+    }
+    /// number of connections connect opens
+    pub fn connections(&self) -> usize {
+        (self.subscriptions.len() + self.cap - 1) / self.cap
+    }
+    /// feeds in the order of subscriptions, fails if any of connections does
+    pub async fn connect(self) -> Result<Vec<Feed>, feeds::Error> {
+        let mut configs = Vec::with_capacity(self.connections());
+        for chunk in &self.subscriptions.into_iter().chunks(self.cap) {
+            let mut config = self.config.clone();
+            // counters are per connection
+            config.stats = Arc::default();
+            configs.push(chunk.fold(config, |config, subscribe| subscribe(config)));
+        }
+        try_join_all(configs.into_iter().map(Config::connect)).await
+    }
+}
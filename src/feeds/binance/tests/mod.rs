@@ -1,22 +1,30 @@
-use std::{borrow::BorrowMut, sync::Arc, time::Duration};
+use std::{sync::Arc, time::Duration};
 
-use crate::feeds::{
-    binance::{self, BookDepth, BookPeriod, Config, Feed},
-    Error,
+use crate::{
+    core::{Amount, Number, Order, OrderBookAsks, OrderBookBids, Price, BEST_ORDER_BOOK_SIZE},
+    feeds::{
+        self,
+        binance::{
+            events, pool::FeedPool, BookDepth, BookPeriod, Config, Dispatched, Feed,
+            OrderBookSubscriptionState, Subscriptions,
+        },
+        clock::MockClock,
+        BookUpdate, Codec, Error, FeedStats,
+    },
+    symbol::Symbol,
 };
-use futures_channel::mpsc::{unbounded, UnboundedReceiver, UnboundedSender};
-use futures_util::{future, pin_mut, StreamExt, TryStreamExt};
-use serde::{Deserialize, Serialize};
-use serde_json::{json, Value};
+use futures_channel::mpsc::{channel, unbounded, UnboundedReceiver, UnboundedSender};
+use futures_util::{future, StreamExt, TryStreamExt};
+use itertools::Itertools;
 use tokio::{
     io,
     net::{TcpListener, TcpStream},
-    task::JoinHandle,
-    time::{timeout, Timeout},
+    time::timeout,
 };
 use tokio_tungstenite::tungstenite::{
-    handshake::server::{Callback, ErrorResponse},
-    http::{Request, Response, Uri},
+    self,
+    http::{header::HeaderName, HeaderValue, Request},
+    protocol::{frame::coding::CloseCode, CloseFrame, WebSocketConfig},
     Message,
 };
 
@@ -62,38 +70,33 @@ impl Server {
 }
 
 impl Connection {
-    async fn handshake(&mut self) -> Uri {
+    /// returns the handshake request
+    async fn handshake(&mut self) -> Request<()> {
         let stream = if let Self::Default(socket) = self {
             socket.take().unwrap()
         } else {
             panic!("request can be got only once")
         };
 
-        let uri = Arc::new(std::sync::Mutex::new(Uri::default()));
-        let uri_move = uri.clone();
+        let handshake = Arc::new(std::sync::Mutex::new(Request::default()));
+        let handshake_move = handshake.clone();
         let (outgoing, incoming) =
             tokio_tungstenite::accept_hdr_async(stream, |request: &Request<()>, response| {
-                uri_move.lock().unwrap().clone_from(request.uri());
+                let mut handshake = handshake_move.lock().unwrap();
+                handshake.uri_mut().clone_from(request.uri());
+                handshake.headers_mut().clone_from(request.headers());
                 Ok(response)
             })
             .await
             .expect("Error during the websocket handshake occurred")
             .split();
 
-        let uri = uri.lock().unwrap().to_owned();
-        println!("WebSocket connection established: {uri}");
+        let request = std::mem::take(&mut *handshake.lock().unwrap());
+        println!("WebSocket connection established: {}", request.uri());
 
         let (tx1, rx1) = unbounded();
         let transfer = incoming.try_for_each(move |msg| {
-            match &msg {
-                Message::Text(_) => todo!(),
-                Message::Binary(_) => todo!(),
-                Message::Ping(_) => todo!(),
-                Message::Pong(p) => println!("Received pong: {p:?}"),
-                Message::Close(_) => todo!(),
-                Message::Frame(_) => todo!(),
-            }
-            //println!("Received a message from {addr}: {msg}");
+            println!("Received a message: {msg:?}");
             tx1.unbounded_send(msg)
                 .expect("error during transferring incoming message");
             future::ok(())
@@ -108,7 +111,7 @@ impl Connection {
         });
 
         *self = Self::Selected(tx2, rx1);
-        uri
+        request
     }
 
     fn send(&mut self, msg: Message) {
@@ -116,7 +119,7 @@ impl Connection {
             Connection::Default(_) => panic!("can't send before handshake finished"),
             Connection::Disconnected => panic!("can't send after connection closed"),
             Connection::Selected(tx, _) => {
-                if let Err(_) = tx.unbounded_send(msg) {
+                if tx.unbounded_send(msg).is_err() {
                     *self = Self::Disconnected;
                 }
             }
@@ -144,26 +147,99 @@ impl Connection {
             Connection::Disconnected => panic!("can't receive after connection closed"),
             Connection::Selected(_, rx) => match rx.try_next() {
                 Ok(Some(m)) => Some(m),
-                Ok(None) | Err(_) => {
+                Ok(None) => {
                     *self = Self::Disconnected;
                     None
                 }
+                Err(_) => None,
             },
         }
     }
 }
 
+async fn connect(server: &mut Server, config: Config) -> (Feed, Connection) {
+    let (feed, connection) = tokio::join!(config.connect(), async {
+        let mut connection = server.accept().await.expect("incoming connection");
+        connection.handshake().await;
+        connection
+    });
+    (feed.expect("connection"), connection)
+}
+
+/// books of the dispatched message, panics if it carries none
+fn book(dispatched: Option<(String, Dispatched)>) -> (String, BookUpdate) {
+    match dispatched {
+        Some((symbol, Dispatched::Book(update))) => (symbol, update),
+        other => panic!("expected books, got {other:?}"),
+    }
+}
+
 #[tokio::test]
-async fn connect() {
+async fn custom_header() {
     let mut server = Server::default();
-    let url = url::Url::parse("ws://127.0.0.1").unwrap();
-    let mut feed = Config::new(url).connect();
+    let url = server.bind().await;
+    let config = Config::new(url).with_header(
+        HeaderName::from_static("x-mbx-apikey"),
+        HeaderValue::from_static("key"),
+    );
+    let (feed, request) = tokio::join!(config.connect(), async {
+        let mut connection = server.accept().await.expect("incoming connection");
+        connection.handshake().await
+    });
+    assert!(feed.is_ok());
+    assert_eq!(request.headers()["x-mbx-apikey"], "key");
+    assert_eq!(request.uri().path(), "/stream");
+}
 
-    let feed = timeout(Duration::from_secs(1), feed);
-    assert!(feed.await.is_err_and(|e| matches!(e, Ellapsed)));
+#[cfg(feature = "native-tls")]
+#[tokio::test]
+async fn tls_connector() {
+    let mut server = Server::default();
+    let url = server.bind().await;
+    // the mock server speaks plain ws
+    let config = Config::new(url).with_tls_connector(tokio_tungstenite::Connector::Plain);
+    let (feed, mut connection) = connect(&mut server, config).await;
+    assert!(feed.health().borrow().connected);
+    connection.send(Message::Ping(Vec::default()));
+    assert!(connection.receive().await.is_some_and(|msg| msg.is_pong()));
+}
 
+#[tokio::test]
+async fn health() {
+    let mut server = Server::default();
     let url = server.bind().await;
-    assert!(Config::new(url).connect().await.is_ok());
+    let (feed, mut connection) = connect(&mut server, Config::new(url)).await;
+    let mut health = feed.health();
+    let connected = *health.borrow_and_update();
+    assert!(connected.connected);
+    assert_eq!(connected.reconnects, 0);
+
+    connection.send(Message::Ping(Vec::default()));
+    timeout(Duration::from_secs(1), health.changed())
+        .await
+        .expect("message in time")
+        .unwrap();
+    assert!(health.borrow().last_message_at >= connected.last_message_at);
+    assert!(health.borrow().connected);
+
+    connection.send(Message::Close(None));
+    timeout(Duration::from_secs(1), async {
+        while health.borrow_and_update().connected {
+            health.changed().await.unwrap();
+        }
+    })
+    .await
+    .expect("disconnected in time");
+}
+
+#[tokio::test]
+async fn connect_failed() {
+    let server = TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let url = url::Url::parse(&format!("ws://{}", server.local_addr().unwrap())).unwrap();
+    drop(server);
+
+    let feed = timeout(Duration::from_secs(1), Config::new(url).connect());
+    assert!(feed.await.is_ok_and(|feed| feed.is_err()));
 }
 
 #[tokio::test]
@@ -171,14 +247,997 @@ async fn ping_from_server() {
     let mut server = Server::default();
     let url = server.bind().await;
 
-    let mut feed = Config::new(url).connect();
-    let mut connection = server.accept().await.expect("incoming connection");
-    feed.await.expect("connection");
+    let (_feed, mut connection) = connect(&mut server, Config::new(url)).await;
     assert!(connection.try_receive().is_none());
 
     connection.send(Message::Ping(Vec::default()));
     assert!(connection.receive().await.is_some_and(|msg| msg.is_pong()));
+}
+
+/// Decodes binary frame of 4 bytes: bid price, bid quantity, ask price, ask quantity
+struct MockCodec;
+
+impl Codec for MockCodec {
+    type Event = events::Event;
+
+    fn decode_text(&self, _: &str) -> Result<Self::Event, Error> {
+        Err(Error::Binance("text isn't supported".into()))
+    }
+    fn decode_binary(&self, binary: &[u8]) -> Result<Self::Event, Error> {
+        let order = |price: u8, quantity: u8| events::Order {
+            price: Number::from(price),
+            quantity: Number::from(quantity),
+        };
+        match binary {
+            &[bid_price, bid_quantity, ask_price, ask_quantity] => {
+                Ok(events::Event::OrderBook(events::OrderBook {
+                    last_update_id: 160,
+                    bids: events::Snapshot(vec![order(bid_price, bid_quantity)]),
+                    asks: events::Snapshot(vec![order(ask_price, ask_quantity)]),
+                }))
+            }
+            _ => Err(Error::Binance("unexpected binary frame".into())),
+        }
+    }
+}
+
+#[tokio::test]
+async fn binary_frame() {
+    let mut server = Server::default();
+    let url = server.bind().await;
+
+    let (tx, mut rx) = unbounded();
+    let mut config = Config::new(url).with_codec(MockCodec);
+    config.subscriptions.insert(
+        "bnbbtc".into(),
+        Subscriptions {
+            order_book: Some(OrderBookSubscriptionState::new(
+                tx.into(),
+                BookPeriod::Normal,
+                Some(BookDepth::Small),
+            )),
+            top_of_book: None,
+        },
+    );
+
+    let (_feed, mut connection) = connect(&mut server, config).await;
+    connection.send(Message::Binary(vec![2, 1, 3, 4]));
+
+    let order = |price: u8, amount: u8| {
+        Order::new(
+            Price::new(Number::from(price)).unwrap(),
+            Amount::new(Number::from(amount)).unwrap(),
+        )
+    };
+    let update = timeout(Duration::from_secs(1), rx.next())
+        .await
+        .expect("books in time")
+        .expect("books");
+    assert_eq!(update.time, None);
+    assert_eq!(update.seq, 160);
+    assert_eq!(update.bids, OrderBookBids::new(vec![order(2, 1)]).unwrap());
+    assert_eq!(update.asks, OrderBookAsks::new(vec![order(3, 4)]).unwrap());
+}
+
+#[tokio::test]
+async fn collect_updates() {
+    let mut server = Server::default();
+    let url = server.bind().await;
+
+    let (tx, rx) = unbounded();
+    let mut config = Config::new(url).with_codec(MockCodec);
+    config.subscriptions.insert(
+        "bnbbtc".into(),
+        Subscriptions {
+            order_book: Some(OrderBookSubscriptionState::new(
+                tx.into(),
+                BookPeriod::Normal,
+                Some(BookDepth::Small),
+            )),
+            top_of_book: None,
+        },
+    );
+
+    let (_feed, mut connection) = connect(&mut server, config).await;
+    for price in 1..=3 {
+        connection.send(Message::Binary(vec![price, 1, 10, 1]));
+    }
+
+    // more are asked for than sent so it stops on timeout
+    let updates = feeds::collect_updates(rx, 5, Duration::from_millis(300)).await;
+    assert_eq!(updates.len(), 3);
+    assert_eq!(
+        updates
+            .iter()
+            .map(|update| update.bids.orders()[0].price())
+            .collect_vec(),
+        (1..=3u8)
+            .map(|price| Price::new(Number::from(price)).unwrap())
+            .collect_vec()
+    );
+}
+
+#[tokio::test]
+async fn stale() {
+    let window = Duration::from_millis(200);
+    let mut server = Server::default();
+    let url = server.bind().await;
+
+    let (tx, mut rx) = unbounded();
+    let mut config = Config::new(url).with_codec(MockCodec).stale_after(window);
+    config.subscriptions.insert(
+        "bnbbtc".into(),
+        Subscriptions {
+            order_book: Some(OrderBookSubscriptionState::new(
+                tx.into(),
+                BookPeriod::Normal,
+                Some(BookDepth::Small),
+            )),
+            top_of_book: None,
+        },
+    );
+
+    let (mut feed, mut connection) = connect(&mut server, config).await;
+    let sent = tokio::time::Instant::now();
+    connection.send(Message::Binary(vec![2, 1, 3, 4]));
+    timeout(Duration::from_secs(1), rx.next())
+        .await
+        .expect("books in time")
+        .expect("books");
+
+    // pings keep the socket alive but carry no books
+    connection.send(Message::Ping(Vec::default()));
+    let result = timeout(Duration::from_secs(1), &mut feed.task)
+        .await
+        .expect("stale in time")
+        .expect("feed not aborted");
+    assert!(matches!(result, Err(Error::Stale(w)) if w == window));
+    assert!(sent.elapsed() >= window);
+}
+
+#[tokio::test]
+async fn closed_by_server() {
+    let mut server = Server::default();
+    let url = server.bind().await;
+    let (mut feed, mut connection) = connect(&mut server, Config::new(url)).await;
+    connection.send(Message::Close(Some(CloseFrame {
+        code: CloseCode::Away,
+        reason: "restart".into(),
+    })));
+    let result = timeout(Duration::from_secs(1), &mut feed.task)
+        .await
+        .expect("closed in time")
+        .expect("feed not aborted");
+    assert!(matches!(
+        result,
+        Err(Error::ClosedByServer(Some(frame))) if frame.code == CloseCode::Away && frame.reason == "restart"
+    ));
+    // the feed replies to complete the closing handshake
+    let reply = timeout(Duration::from_secs(1), connection.receive())
+        .await
+        .expect("reply in time");
+    assert!(reply.is_some_and(|message| message.is_close()));
+}
+
+#[tokio::test]
+async fn oversized_message() {
+    let mut server = Server::default();
+    let url = server.bind().await;
+    let config = Config::new(url).with_websocket_config(WebSocketConfig {
+        max_message_size: Some(64),
+        ..WebSocketConfig::default()
+    });
+    let (mut feed, mut connection) = connect(&mut server, config).await;
+    connection.send(Message::Text("x".repeat(65)));
+    let result = timeout(Duration::from_secs(1), &mut feed.task)
+        .await
+        .expect("rejected in time")
+        .expect("feed not aborted");
+    assert!(matches!(
+        result,
+        Err(Error::WS(tungstenite::Error::Capacity(_)))
+    ));
+}
+
+#[tokio::test]
+async fn flood() {
+    let clock = MockClock::default();
+    let mut server = Server::default();
+    let url = server.bind().await;
+    let config = Config::new(url)
+        .max_messages_per_second(2)
+        .with_clock(clock.clone());
+    let (mut feed, mut connection) = connect(&mut server, config).await;
+    for _ in 0..2 {
+        connection.send(Message::Ping(Vec::default()));
+    }
+    while feed.stats().messages < 2 {
+        tokio::task::yield_now().await;
+    }
+    // the limit is per second
+    clock.advance(Duration::from_secs(1));
+    for _ in 0..3 {
+        connection.send(Message::Ping(Vec::default()));
+    }
+    let result = timeout(Duration::from_secs(1), &mut feed.task)
+        .await
+        .expect("flood in time")
+        .expect("feed not aborted");
+    assert!(matches!(result, Err(Error::Flood(2))));
+    assert_eq!(feed.stats().messages, 4);
+}
+
+#[tokio::test]
+async fn stale_mock_clock() {
+    let window = Duration::from_secs(60);
+    let clock = MockClock::default();
+    let mut server = Server::default();
+    let url = server.bind().await;
+
+    let (tx, mut rx) = unbounded();
+    let mut config = Config::new(url)
+        .with_codec(MockCodec)
+        .stale_after(window)
+        .with_clock(clock.clone());
+    config.subscriptions.insert(
+        "bnbbtc".into(),
+        Subscriptions {
+            order_book: Some(OrderBookSubscriptionState::new(
+                tx.into(),
+                BookPeriod::Normal,
+                Some(BookDepth::Small),
+            )),
+            top_of_book: None,
+        },
+    );
+
+    let (mut feed, mut connection) = connect(&mut server, config).await;
+    connection.send(Message::Binary(vec![2, 1, 3, 4]));
+    timeout(Duration::from_secs(1), rx.next())
+        .await
+        .expect("books in time")
+        .expect("books");
+
+    clock.advance(window - Duration::from_millis(1));
+    for _ in 0..10 {
+        tokio::task::yield_now().await;
+    }
+    assert!(!feed.task.is_finished());
+
+    clock.advance(Duration::from_millis(1));
+    let result = timeout(Duration::from_secs(1), &mut feed.task)
+        .await
+        .expect("stale in time")
+        .expect("feed not aborted");
+    assert!(matches!(result, Err(Error::Stale(w)) if w == window));
+}
+
+#[tokio::test]
+async fn backpressure() {
+    let mut server = Server::default();
+    let url = server.bind().await;
+
+    // room for a single update
+    let (tx, mut rx) = channel(0);
+    let mut config = Config::new(url).with_codec(MockCodec);
+    config.subscriptions.insert(
+        "bnbbtc".into(),
+        Subscriptions {
+            order_book: Some(OrderBookSubscriptionState::new(
+                tx.into(),
+                BookPeriod::Normal,
+                Some(BookDepth::Small),
+            )),
+            top_of_book: None,
+        },
+    );
+
+    let (_feed, mut connection) = connect(&mut server, config).await;
+    for price in 1..=3 {
+        connection.send(Message::Binary(vec![price, 1, price + 1, 1]));
+    }
+    connection.send(Message::Ping(Vec::default()));
+
+    // the feed waits for room and doesn't read the ping meanwhile
+    tokio::time::sleep(Duration::from_millis(200)).await;
     assert!(connection.try_receive().is_none());
+
+    for price in 1..=3 {
+        let update = timeout(Duration::from_secs(1), rx.next())
+            .await
+            .expect("books in time")
+            .expect("books");
+        assert_eq!(
+            update.bids,
+            OrderBookBids::new(vec![Order::new(
+                Price::new(Number::from(price)).unwrap(),
+                Amount::new(Number::from(1u8)).unwrap(),
+            )])
+            .unwrap()
+        );
+    }
+    let pong = timeout(Duration::from_secs(1), connection.receive())
+        .await
+        .expect("pong in time");
+    assert!(pong.is_some_and(|msg| msg.is_pong()));
+}
+
+#[tokio::test]
+async fn depth_update_frame() {
+    let mut server = Server::default();
+    let url = server.bind().await;
+
+    let (tx, mut rx) = unbounded();
+    let mut config = Config::new(url);
+    config.subscriptions.insert(
+        "bnbbtc".into(),
+        Subscriptions {
+            order_book: Some(OrderBookSubscriptionState::new(
+                tx.into(),
+                BookPeriod::Normal,
+                None,
+            )),
+            top_of_book: None,
+        },
+    );
+
+    let (_feed, mut connection) = connect(&mut server, config).await;
+    connection.send(Message::Text(
+        r#"{"e":"depthUpdate","E":123456789,"s":"BNBBTC","U":157,"u":160,"b":[["2","1"]],"a":[["3","4"]]}"#.into(),
+    ));
+
+    let update = timeout(Duration::from_secs(1), rx.next())
+        .await
+        .expect("books in time")
+        .expect("books");
+    assert_eq!(update.time, Some(123456789));
+    assert_eq!(update.seq, 160);
+    assert_eq!(
+        update.bids,
+        OrderBookBids::new(vec![Order::new(
+            Price::new(Number::from(2u8)).unwrap(),
+            Amount::new(Number::from(1u8)).unwrap(),
+        )])
+        .unwrap()
+    );
+}
+
+#[test]
+fn control_frames() {
+    let mut config = Config::default();
+    let ack = Message::Text(r#"{"result":null,"id":1}"#.into());
+    assert!(Feed::dispatch(&mut config, ack).unwrap().is_none());
+
+    let error = Message::Text(r#"{"code":2,"msg":"Invalid request","id":1}"#.into());
+    assert!(matches!(
+        Feed::dispatch(&mut config, error),
+        Err(Error::BinanceApi { code: 2, msg }) if msg == "Invalid request"
+    ));
+}
+
+#[test]
+fn subscribe_fresh_symbol() {
+    let config = Config::default().subscribe_order_book(
+        unbounded().0,
+        Symbol::new("ETH", "BTC"),
+        BookPeriod::Fast,
+        None,
+    );
+    let subscription = config.subscriptions["ethbtc"].order_book.as_ref();
+    assert_eq!(
+        subscription.map(|state| state.period),
+        Some(BookPeriod::Fast)
+    );
+}
+
+#[test]
+#[should_panic(expected = "already subscribed for ethbtc")]
+fn subscribe_twice() {
+    let symbol = Symbol::new("ETH", "BTC");
+    let _ = Config::default()
+        .subscribe_order_book(unbounded().0, symbol.clone(), BookPeriod::Fast, None)
+        .subscribe_order_book(unbounded().0, symbol, BookPeriod::Normal, None);
+}
+
+#[test]
+fn subscription_string() {
+    let subscription = |period, depth| {
+        OrderBookSubscriptionState::new(unbounded().0.into(), period, depth)
+            .to_subscription_string()
+    };
+    assert_eq!(
+        subscription(BookPeriod::Normal, Some(BookDepth::Medium)),
+        "depth10@1000ms"
+    );
+    assert_eq!(
+        subscription(BookPeriod::Fast, Some(BookDepth::Large)),
+        "depth20@100ms"
+    );
+    assert_eq!(subscription(BookPeriod::Normal, None), "depth@1000ms");
+}
+
+#[test]
+fn period_and_depth_values() {
+    for (period, millis) in [(BookPeriod::Normal, 1000), (BookPeriod::Fast, 100)] {
+        assert_eq!(period.millis(), millis);
+        assert_eq!(BookPeriod::try_from(millis), Ok(period));
+    }
+    assert_eq!(BookPeriod::try_from(250), Err(250));
+
+    for (depth, levels) in [
+        (BookDepth::Small, 5),
+        (BookDepth::Medium, 10),
+        (BookDepth::Large, 20),
+    ] {
+        assert_eq!(depth.levels(), levels);
+        assert_eq!(BookDepth::try_from(levels), Ok(depth));
+    }
+    assert_eq!(BookDepth::try_from(232), Err(232));
+}
+
+#[tokio::test]
+async fn stats() {
+    let mut server = Server::default();
+    let url = server.bind().await;
+
+    let (tx, mut rx) = unbounded();
+    let mut config = Config::new(url);
+    config.subscriptions.insert(
+        "bnbbtc".into(),
+        Subscriptions {
+            order_book: Some(OrderBookSubscriptionState::new(
+                tx.into(),
+                BookPeriod::Normal,
+                None,
+            )),
+            top_of_book: None,
+        },
+    );
+
+    let (feed, mut connection) = connect(&mut server, config).await;
+    assert_eq!(feed.stats(), FeedStats::default());
+    let time = std::time::SystemTime::now()
+        .duration_since(std::time::UNIX_EPOCH)
+        .unwrap()
+        .as_millis()
+        - 100;
+    connection.send(Message::Text(format!(
+        r#"{{"e":"depthUpdate","E":{time},"s":"BNBBTC","U":1,"u":1,"b":[["2","1"]],"a":[]}}"#
+    )));
+    timeout(Duration::from_secs(1), rx.next())
+        .await
+        .expect("books in time")
+        .expect("books");
+    let stats = feed.stats();
+    assert_eq!((stats.messages, stats.parse_errors, stats.books), (1, 0, 1));
+    assert!(stats.latency.unwrap() >= Duration::from_millis(100));
+
+    connection.send(Message::Text("{}".into()));
+    timeout(Duration::from_secs(1), async {
+        while feed.stats().parse_errors == 0 {
+            tokio::time::sleep(Duration::from_millis(10)).await;
+        }
+    })
+    .await
+    .expect("parse error in time");
+    let stats = feed.stats();
+    assert_eq!((stats.messages, stats.parse_errors, stats.books), (2, 1, 1));
+}
+
+#[tokio::test]
+async fn error_sink() {
+    let (tx, mut rx) = unbounded();
+    let (errors_tx, mut errors) = unbounded();
+    let mut config = Config::default()
+        .subscribe_order_book(tx, Symbol::new("BNB", "BTC"), BookPeriod::Normal, None)
+        .error_sink(errors_tx);
+    let frames = [
+        r#"{"e":"depthUpdate","E":1,"s":"BNBBTC","U":1,"u":1,"b":[["2","1"]],"a":[]}"#,
+        r#"{"e":"depthUpdate","#,
+        r#"{"e":"depthUpdate","E":2,"s":"BNBBTC","U":2,"u":2,"b":[["3","1"]],"a":[]}"#,
+    ];
+    for frame in frames {
+        Feed::handle(&mut config, Message::Text(frame.into()))
+            .await
+            .unwrap();
+    }
+    assert!(matches!(errors.try_next(), Ok(Some(Error::Binance(_)))));
+    assert!(errors.try_next().is_err());
+    let updates: Vec<_> = std::iter::from_fn(|| rx.try_next().ok().flatten()).collect();
+    assert_eq!(updates.len(), 2);
+    assert_eq!(updates[1].seq, 2);
+    assert_eq!(config.stats.snapshot().parse_errors, 1);
+
+    // gaps can't be recovered from
+    let gap = r#"{"e":"depthUpdate","E":3,"s":"BNBBTC","U":5,"u":5,"b":[],"a":[]}"#;
+    assert!(matches!(
+        Feed::handle(&mut config, Message::Text(gap.into())).await,
+        Err(Error::Gap { .. })
+    ));
+}
+
+#[tokio::test]
+async fn invalid_level_isnt_applied() {
+    let (tx, mut rx) = unbounded();
+    let (errors_tx, mut errors) = unbounded();
+    let mut config = Config::default()
+        .subscribe_order_book(tx, Symbol::new("BNB", "BTC"), BookPeriod::Normal, None)
+        .error_sink(errors_tx);
+    let frames = [
+        r#"{"e":"depthUpdate","E":1,"s":"BNBBTC","U":1,"u":1,"b":[["2","1"]],"a":[]}"#,
+        // parses but 0 isn't a valid price
+        r#"{"e":"depthUpdate","E":2,"s":"BNBBTC","U":2,"u":2,"b":[["0","1"]],"a":[]}"#,
+    ];
+    for frame in frames {
+        Feed::handle(&mut config, Message::Text(frame.into()))
+            .await
+            .unwrap();
+    }
+    assert!(matches!(errors.try_next(), Ok(Some(Error::Binance(_)))));
+
+    // contiguous with the dropped diff so books would silently diverge if it counted
+    let next = r#"{"e":"depthUpdate","E":3,"s":"BNBBTC","U":3,"u":3,"b":[["3","1"]],"a":[]}"#;
+    assert!(matches!(
+        Feed::handle(&mut config, Message::Text(next.into())).await,
+        Err(Error::Gap {
+            expected: 2,
+            got: 3
+        })
+    ));
+    let updates: Vec<_> = std::iter::from_fn(|| rx.try_next().ok().flatten()).collect();
+    assert_eq!(updates.len(), 1);
+    assert_eq!(updates[0].seq, 1);
+}
+
+/// feeds depth updates as if they were emitted every period
+/// and returns all the updates the subscriber has received
+async fn replay(
+    period: BookPeriod,
+    updates: &[(u64, u64, &str, &str)],
+) -> Result<Vec<BookUpdate>, Error> {
+    let (tx, mut rx) = unbounded();
+    let mut config = Config::default();
+    config.subscriptions.insert(
+        "bnbbtc".into(),
+        Subscriptions {
+            order_book: Some(OrderBookSubscriptionState::new(tx.into(), period, None)),
+            top_of_book: None,
+        },
+    );
+    for (i, (first, last, bids, asks)) in updates.iter().enumerate() {
+        let time = i as u64 * period as u64;
+        Feed::handle(
+            &mut config,
+            Message::Text(format!(
+                r#"{{"e":"depthUpdate","E":{time},"s":"BNBBTC","U":{first},"u":{last},"b":{bids},"a":{asks}}}"#
+            )),
+        )
+        .await?;
+    }
+    Ok(std::iter::from_fn(|| rx.try_next().ok().flatten()).collect())
+}
+
+#[tokio::test]
+async fn update_period() {
+    let normal = replay(
+        BookPeriod::Normal,
+        &[
+            (1, 3, r#"[["2","1"],["1","2"]]"#, r#"[["3","4"]]"#),
+            (4, 6, r#"[["2","0"]]"#, r#"[["4","1"]]"#),
+        ],
+    )
+    .await
+    .unwrap();
+    // the same updates split into more frequent events
+    let fast = replay(
+        BookPeriod::Fast,
+        &[
+            (1, 1, r#"[["2","1"]]"#, "[]"),
+            (2, 2, r#"[["1","2"]]"#, "[]"),
+            (3, 3, "[]", r#"[["3","4"]]"#),
+            // duplicate is ignored
+            (3, 3, r#"[["5","5"]]"#, "[]"),
+            (4, 5, r#"[["2","0"]]"#, "[]"),
+            (6, 6, "[]", r#"[["4","1"]]"#),
+        ],
+    )
+    .await
+    .unwrap();
+    assert_eq!(normal.len(), 2);
+    assert_eq!(fast.len(), 5);
+    let (normal, fast) = (normal.last().unwrap(), fast.last().unwrap());
+    assert_eq!(normal.seq, 6);
+    assert_eq!(fast.seq, 6);
+    assert_eq!(normal.time, Some(1000));
+    assert_eq!(fast.time, Some(500));
+    assert_eq!(normal.bids, fast.bids);
+    assert_eq!(normal.asks, fast.asks);
+
+    for period in [BookPeriod::Normal, BookPeriod::Fast] {
+        let result = replay(period, &[(1, 3, "[]", "[]"), (5, 6, "[]", "[]")]).await;
+        assert!(matches!(
+            result,
+            Err(Error::Gap {
+                expected: 4,
+                got: 5
+            })
+        ));
+    }
+}
+
+#[tokio::test]
+async fn refill() {
+    // more levels than sent, the best one is removed then
+    let bids = (1..=BEST_ORDER_BOOK_SIZE + 1)
+        .map(|price| format!(r#"["{price}","1"]"#))
+        .join(",");
+    let updates = replay(
+        BookPeriod::Normal,
+        &[
+            (1, 1, &format!("[{bids}]"), "[]"),
+            (
+                2,
+                2,
+                &format!(r#"[["{}","0"]]"#, BEST_ORDER_BOOK_SIZE + 1),
+                "[]",
+            ),
+        ],
+    )
+    .await
+    .unwrap();
+    let bids = updates.last().unwrap().bids.orders();
+    assert_eq!(bids.len(), BEST_ORDER_BOOK_SIZE);
+    assert_eq!(
+        bids.last().unwrap().price(),
+        Price::new(Number::from(1u8)).unwrap()
+    );
+}
+
+#[test]
+fn bounds() {
+    let number = |n: &str| n.parse::<Number>().unwrap();
+    let (tx, mut rx) = unbounded();
+    let mut config = Config::default().bounds(feeds::Bounds {
+        min_price: Some(Price::new(number("1")).unwrap()),
+        max_price: Some(Price::new(number("10")).unwrap()),
+        max_amount: Some(Amount::new(number("100")).unwrap()),
+    });
+    config.subscriptions.insert(
+        "bnbbtc".into(),
+        Subscriptions {
+            order_book: Some(OrderBookSubscriptionState::new(
+                tx.into(),
+                BookPeriod::Normal,
+                None,
+            )),
+            top_of_book: None,
+        },
+    );
+    let (symbol, update) = book(
+        Feed::dispatch(
+            &mut config,
+            Message::Text(
+                r#"{"e":"depthUpdate","E":1,"s":"BNBBTC","U":1,"u":1,"b":[["2","1"],["0.5","1"]],"a":[["3","1000"],["4","1"],["40","1"]]}"#
+                    .into(),
+            ),
+        )
+        .unwrap(),
+    );
+    assert_eq!(symbol, "bnbbtc");
+    let order = |price, amount| {
+        Order::new(
+            Price::new(number(price)).unwrap(),
+            Amount::new(number(amount)).unwrap(),
+        )
+    };
+    assert_eq!(update.bids.orders(), &[order("2", "1")]);
+    assert_eq!(update.asks.orders(), &[order("4", "1")]);
+
+    let (_, update) = book(
+        Feed::dispatch(
+            &mut config,
+            Message::Text(
+                r#"{"stream":"bnbbtc@depth@100ms","data":{"lastUpdateId":2,"bids":[["0.1","1"],["5","101"]],"asks":[["6","1"]]}}"#.into(),
+            ),
+        )
+        .unwrap(),
+    );
+    assert!(update.bids.orders().is_empty());
+    assert_eq!(update.asks.orders(), &[order("6", "1")]);
+    assert!(rx.try_next().is_err());
+}
+
+#[test]
+fn combined_partial_depth() {
+    let (tx, mut rx) = unbounded();
+    let mut config = Config::default()
+        .subscribe_order_book(tx, Symbol::new("BNB", "BTC"), BookPeriod::Fast, None)
+        .subscribe_order_book(
+            unbounded().0,
+            Symbol::new("ETH", "BTC"),
+            BookPeriod::Fast,
+            Some(BookDepth::Medium),
+        );
+    // the partial depth stream is told by the name of the combined stream
+    let frame = r#"{"stream":"bnbbtc@depth10@100ms","data":{"lastUpdateId":5,"bids":[["2","1"]],"asks":[["3","1"]]}}"#;
+    let (symbol, update) = book(Feed::dispatch(&mut config, Message::Text(frame.into())).unwrap());
+    assert_eq!(symbol, "bnbbtc");
+    assert_eq!(update.seq, 5);
+    assert_eq!(update.bids.orders().len(), 1);
+
+    let frame = r#"{"stream":"bnbbtc@depth@100ms","data":{"e":"depthUpdate","E":1,"s":"BNBBTC","U":6,"u":6,"b":[],"a":[["3","0"]]}}"#;
+    let (symbol, update) = book(Feed::dispatch(&mut config, Message::Text(frame.into())).unwrap());
+    assert_eq!(symbol, "bnbbtc");
+    assert!(update.asks.orders().is_empty());
+    assert!(rx.try_next().is_err());
+}
+
+#[tokio::test]
+async fn top_of_book() {
+    let mut server = Server::default();
+    let url = server.bind().await;
+    let (books_tx, mut books) = unbounded();
+    let (top_tx, mut tops) = unbounded();
+    let config = Config::new(url)
+        .subscribe_order_book(books_tx, Symbol::new("ETH", "BTC"), BookPeriod::Fast, None)
+        .subscribe_top_of_book(top_tx, Symbol::new("BNB", "BTC"));
+    let (feed, (request, mut connection)) = tokio::join!(config.connect(), async {
+        let mut connection = server.accept().await.expect("incoming connection");
+        (connection.handshake().await, connection)
+    });
+    let feed = feed.unwrap();
+    let streams = request.uri().query().unwrap().to_owned();
+    assert!(streams.contains("bnbbtc@bookTicker"));
+    assert!(streams.contains("ethbtc@depth@100ms"));
+
+    connection.send(Message::Text(
+        r#"{"stream":"bnbbtc@bookTicker","data":{"u":7,"s":"BNBBTC","b":"2","B":"1","a":"3","A":"4"}}"#
+            .into(),
+    ));
+    let top = timeout(Duration::from_secs(1), tops.next())
+        .await
+        .expect("top of book in time")
+        .expect("top of book");
+    let order = |price: u8, amount: u8| {
+        Order::new(
+            Price::new(Number::from(price)).unwrap(),
+            Amount::new(Number::from(amount)).unwrap(),
+        )
+    };
+    assert_eq!(top.bid, order(2, 1));
+    assert_eq!(top.ask, order(3, 4));
+    assert_eq!(feed.stats().books, 1);
+    assert!(books.try_next().is_err());
+}
+
+#[test]
+fn unsubscribed_top_of_book() {
+    let (tx, _rx) = unbounded();
+    let mut config = Config::default().subscribe_order_book(
+        tx,
+        Symbol::new("ETH", "BTC"),
+        BookPeriod::Fast,
+        None,
+    );
+    // neither the symbol nor the stream of a subscribed symbol
+    for ticker in [
+        r#"{"u":7,"s":"BNBBTC","b":"2","B":"1","a":"3","A":"4"}"#,
+        r#"{"u":7,"s":"ETHBTC","b":"2","B":"1","a":"3","A":"4"}"#,
+    ] {
+        assert!(Feed::dispatch(&mut config, Message::Text(ticker.into()))
+            .unwrap()
+            .is_none());
+    }
+}
+
+#[tokio::test]
+async fn partial_depth_of_two_symbols() {
+    let mut server = Server::default();
+    let url = server.bind().await;
+    let (bnb_tx, mut bnb_rx) = unbounded();
+    let (eth_tx, mut eth_rx) = unbounded();
+    let config = Config::new(url)
+        .subscribe_order_book(
+            bnb_tx,
+            Symbol::new("BNB", "BTC"),
+            BookPeriod::Fast,
+            Some(BookDepth::Small),
+        )
+        .subscribe_order_book(
+            eth_tx,
+            Symbol::new("ETH", "BTC"),
+            BookPeriod::Fast,
+            Some(BookDepth::Small),
+        );
+
+    // unwrapped events can't tell which of the streams they come from
+    let mut unwrapped = config.clone();
+    assert!(matches!(
+        Feed::dispatch(
+            &mut unwrapped,
+            Message::Text(r#"{"lastUpdateId":1,"bids":[],"asks":[]}"#.into()),
+        ),
+        Err(Error::Binance(_))
+    ));
+
+    let (_feed, mut connection) = connect(&mut server, config).await;
+    connection.send(Message::Text(
+        r#"{"stream":"ethbtc@depth5@100ms","data":{"lastUpdateId":7,"bids":[["0.06","1"]],"asks":[["0.07","1"]]}}"#.into(),
+    ));
+    connection.send(Message::Text(
+        r#"{"stream":"bnbbtc@depth5@100ms","data":{"lastUpdateId":3,"bids":[["0.002","5"]],"asks":[["0.003","5"]]}}"#.into(),
+    ));
+
+    let price = |price: &str| Price::new(price.parse().unwrap()).unwrap();
+    let bnb = timeout(Duration::from_secs(1), bnb_rx.next())
+        .await
+        .expect("books in time")
+        .expect("books");
+    assert_eq!(bnb.seq, 3);
+    assert_eq!(bnb.bids.orders()[0].price(), price("0.002"));
+    let eth = timeout(Duration::from_secs(1), eth_rx.next())
+        .await
+        .expect("books in time")
+        .expect("books");
+    assert_eq!(eth.seq, 7);
+    assert_eq!(eth.asks.orders()[0].price(), price("0.07"));
+    assert!(bnb_rx.try_next().is_err() && eth_rx.try_next().is_err());
+}
+
+#[tokio::test]
+async fn pool() {
+    let mut server = Server::default();
+    let url = server.bind().await;
+    let (tx, _rx) = unbounded();
+    let pool = ["BTC", "ETH", "BNB", "XRP", "ADA"].into_iter().fold(
+        FeedPool::new(Config::new(url)).streams_per_connection(2),
+        |pool, base| {
+            pool.subscribe_order_book(
+                tx.clone(),
+                Symbol::new(base, "USDT"),
+                BookPeriod::Fast,
+                None,
+            )
+        },
+    );
+    assert_eq!(pool.connections(), 3);
+    let (feeds, requests) = tokio::join!(pool.connect(), async {
+        let mut requests = Vec::new();
+        for _ in 0..3 {
+            let mut connection = server.accept().await.expect("incoming connection");
+            requests.push(connection.handshake().await);
+        }
+        requests
+    });
+    assert_eq!(feeds.unwrap().len(), 3);
+    let mut streams = requests
+        .iter()
+        .map(|request| {
+            let query = request.uri().query().unwrap();
+            query.trim_start_matches("streams=").split('/').count()
+        })
+        .collect_vec();
+    streams.sort_unstable();
+    assert_eq!(streams, [1, 2, 2]);
+}
+
+/// events formatted as `message field=value ...`
+#[cfg(feature = "tracing")]
+#[derive(Default, Clone)]
+struct Capture(Arc<std::sync::Mutex<Vec<String>>>);
+
+#[cfg(feature = "tracing")]
+impl tracing::Subscriber for Capture {
+    fn enabled(&self, _: &tracing::Metadata<'_>) -> bool {
+        true
+    }
+    fn new_span(&self, _: &tracing::span::Attributes<'_>) -> tracing::span::Id {
+        tracing::span::Id::from_u64(1)
+    }
+    fn record(&self, _: &tracing::span::Id, _: &tracing::span::Record<'_>) {}
+    fn record_follows_from(&self, _: &tracing::span::Id, _: &tracing::span::Id) {}
+    fn event(&self, event: &tracing::Event<'_>) {
+        struct Fields(String);
+        impl tracing::field::Visit for Fields {
+            fn record_debug(&mut self, field: &tracing::field::Field, value: &dyn std::fmt::Debug) {
+                match field.name() {
+                    "message" => self.0.insert_str(0, &format!("{value:?}")),
+                    name => self.0 += &format!(" {name}={value:?}"),
+                }
+            }
+        }
+        let mut fields = Fields(String::default());
+        event.record(&mut fields);
+        self.0.lock().unwrap().push(fields.0);
+    }
+    fn enter(&self, _: &tracing::span::Id) {}
+    fn exit(&self, _: &tracing::span::Id) {}
+}
+
+#[cfg(feature = "tracing")]
+#[tokio::test]
+async fn tracing_events() {
+    let capture = Capture::default();
+    let _default = tracing::subscriber::set_default(capture.clone());
+    let (errors, _rx) = unbounded();
+    let mut config = Config::default()
+        .subscribe_order_book(
+            unbounded().0,
+            Symbol::new("BNB", "BTC"),
+            BookPeriod::Normal,
+            None,
+        )
+        .error_sink(errors);
+    for frame in [
+        r#"{"e":"depthUpdate","E":7,"s":"BNBBTC","U":1,"u":2,"b":[],"a":[]}"#,
+        r#"{"e":"depthUpdate","#,
+        r#"{"e":"depthUpdate","E":8,"s":"BNBBTC","U":5,"u":6,"b":[],"a":[]}"#,
+    ] {
+        let _ = Feed::handle(&mut config, Message::Text(frame.into())).await;
+    }
+    let events = capture.0.lock().unwrap().clone();
+    assert_eq!(
+        events[..2],
+        [
+            "subscribe order book symbol=bnbbtc period=Normal depth=None",
+            "depth update symbol=BNBBTC event_time=7 first_update_id=1 final_update_id=2",
+        ]
+    );
+    assert!(events[2].starts_with("parse error error=Binance error: EOF"));
+    assert_eq!(
+        events[3..],
+        [
+            "depth update symbol=BNBBTC event_time=8 first_update_id=5 final_update_id=6",
+            "missed updates symbol=BNBBTC expected=3 got=5",
+        ]
+    );
+}
+
+#[test]
+fn snapshot_sync() {
+    let (tx, mut rx) = unbounded();
+    let mut config = Config::default().subscribe_order_book(
+        tx,
+        Symbol::new("BNB", "BTC"),
+        BookPeriod::Normal,
+        Some(BookDepth::Small),
+    );
+    let mut dispatch = |text: &str| {
+        Feed::dispatch(&mut config, Message::Text(text.into()))
+            .map(|dispatched| dispatched.map(|dispatched| book(Some(dispatched)).1))
+    };
+    let snapshot = dispatch(r#"{"lastUpdateId":10,"bids":[["2","1"]],"asks":[["3","1"]]}"#)
+        .unwrap()
+        .unwrap();
+    assert_eq!(snapshot.seq, 10);
+
+    // already in the snapshot
+    let stale = r#"{"e":"depthUpdate","E":1,"s":"BNBBTC","U":8,"u":10,"b":[["2","0"]],"a":[]}"#;
+    assert!(dispatch(stale).unwrap().is_none());
+
+    // straddles the snapshot
+    let fresh = r#"{"e":"depthUpdate","E":2,"s":"BNBBTC","U":9,"u":12,"b":[["1","1"]],"a":[]}"#;
+    let update = dispatch(fresh).unwrap().unwrap();
+    assert_eq!(update.seq, 12);
+    let prices = |book: &[Order]| book.iter().map(|order| order.price()).collect_vec();
+    assert_eq!(
+        prices(snapshot.bids.orders()),
+        [Price::new(Number::from(2u8)).unwrap()]
+    );
+    assert_eq!(
+        prices(update.bids.orders()),
+        [2u8, 1].map(|price| Price::new(Number::from(price)).unwrap())
+    );
+
+    let gap = r#"{"e":"depthUpdate","E":3,"s":"BNBBTC","U":14,"u":15,"b":[],"a":[]}"#;
+    assert!(matches!(
+        dispatch(gap),
+        Err(Error::Gap {
+            expected: 13,
+            got: 14
+        })
+    ));
+    assert!(rx.try_next().is_err());
 }
 
 // #[tokio::test]
@@ -0,0 +1,89 @@
+use std::{
+    sync::{Arc, Mutex},
+    time::Duration,
+};
+
+use futures_channel::oneshot;
+use futures_util::{
+    future::{self, BoxFuture},
+    FutureExt,
+};
+use tokio::time::Instant;
+
+/// Source of time for timeouts of feeds so tests can drive them, see MockClock
+pub trait Clock: Send + Sync {
+    fn now(&self) -> Instant;
+    fn sleep(&self, duration: Duration) -> BoxFuture<'static, ()>;
+}
+
+/// Time of the tokio runtime
+#[derive(Default, Debug, Clone, Copy)]
+pub struct TokioClock;
+
+impl Clock for TokioClock {
+    fn now(&self) -> Instant {
+        Instant::now()
+    }
+    fn sleep(&self, duration: Duration) -> BoxFuture<'static, ()> {
+        tokio::time::sleep(duration).boxed()
+    }
+}
+
+#[derive(Debug)]
+struct MockState {
+    now: Instant,
+    sleepers: Vec<(Instant, oneshot::Sender<()>)>,
+}
+
+/// Time standing still until advanced, clones share the time
+#[derive(Debug, Clone)]
+pub struct MockClock(Arc<Mutex<MockState>>);
+
+impl Default for MockClock {
+    fn default() -> Self {
+        Self(Arc::new(Mutex::new(MockState {
+            now: Instant::now(),
+            sleepers: Vec::default(),
+        })))
+    }
+}
+
+impl MockClock {
+    /// moves time forward waking sleeps which are due
+    pub fn advance(&self, duration: Duration) {
+        let mut state = self.0.lock().unwrap();
+        state.now += duration;
+        let now = state.now;
+        let (due, pending) = state
+            .sleepers
+            .drain(..)
+            .partition::<Vec<_>, _>(|(deadline, _)| *deadline <= now);
+        state.sleepers = pending;
+        for (_, sleeper) in due {
+            // dropped sleeps don't wait anymore
+            let _ = sleeper.send(());
+        }
+    }
+}
+
+impl Clock for MockClock {
+    fn now(&self) -> Instant {
+        self.0.lock().unwrap().now
+    }
+    fn sleep(&self, duration: Duration) -> BoxFuture<'static, ()> {
+        if duration.is_zero() {
+            return future::ready(()).boxed();
+        }
+        let (tx, rx) = oneshot::channel();
+        let mut state = self.0.lock().unwrap();
+        let deadline = state.now + duration;
+        state.sleepers.push((deadline, tx));
+        async move {
+            // time never comes once the clock is gone
+            if rx.await.is_err() {
+                future::pending().await
+            }
+        }
+        .boxed()
+    }
+}
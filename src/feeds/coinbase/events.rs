@@ -0,0 +1,126 @@
+use serde::{Deserialize, Serialize};
+
+use crate::{core::Number, feeds::float_as_string};
+
+#[derive(Debug, Deserialize, Clone, Copy)]
+pub struct Level {
+    #[serde(deserialize_with = "float_as_string")]
+    pub price: Number,
+    #[serde(deserialize_with = "float_as_string")]
+    pub size: Number,
+}
+
+#[derive(Debug, Deserialize, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum Side {
+    Buy,
+    Sell,
+}
+
+/// `["buy"|"sell", price, size]`, 0 size removes the level
+#[derive(Debug, Deserialize, Clone, Copy)]
+pub struct Change {
+    pub side: Side,
+    #[serde(deserialize_with = "float_as_string")]
+    pub price: Number,
+    #[serde(deserialize_with = "float_as_string")]
+    pub size: Number,
+}
+
+#[derive(Debug, Deserialize, Clone)]
+pub struct Snapshot {
+    pub product_id: String,
+    pub bids: Vec<Level>,
+    pub asks: Vec<Level>,
+}
+
+#[derive(Debug, Deserialize, Clone)]
+pub struct L2Update {
+    pub product_id: String,
+    pub changes: Vec<Change>,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum Event {
+    Snapshot(Snapshot),
+    L2update(L2Update),
+    Error {
+        message: String,
+        #[serde(default)]
+        reason: Option<String>,
+    },
+    /// subscriptions confirmation, heartbeats and the like
+    #[serde(other)]
+    Other,
+}
+
+#[derive(Debug, Serialize)]
+pub struct Channel<'a> {
+    pub name: &'static str,
+    pub product_ids: &'a [String],
+}
+
+#[derive(Debug, Serialize)]
+pub struct Subscribe<'a> {
+    #[serde(rename = "type")]
+    pub kind: &'static str,
+    pub channels: [Channel<'a>; 1],
+}
+
+#[cfg(not(feature = "decimal"))]
+#[test]
+fn snapshot() {
+    let json = r#"{"type":"snapshot","product_id":"BTC-USD","bids":[["10101.10","0.45054140"]],"asks":[["10102.55","0.57753524"]]}"#;
+    let event: Event = serde_json::from_str(json).unwrap();
+    let Event::Snapshot(snapshot) = event else {
+        panic!("snapshot expected")
+    };
+    assert_eq!(snapshot.product_id, "BTC-USD");
+    assert_eq!(snapshot.bids[0].price, 10101.10);
+    assert_eq!(snapshot.bids[0].size, 0.45054140);
+    assert_eq!(snapshot.asks[0].price, 10102.55);
+    assert_eq!(snapshot.asks[0].size, 0.57753524);
+}
+
+#[cfg(not(feature = "decimal"))]
+#[test]
+fn l2update() {
+    let json = r#"{"type":"l2update","product_id":"BTC-USD","time":"2019-08-14T20:42:27.265Z","changes":[["buy","10101.80000000","0.162567"],["sell","10102.00","0"]]}"#;
+    let event: Event = serde_json::from_str(json).unwrap();
+    let Event::L2update(update) = event else {
+        panic!("l2update expected")
+    };
+    assert_eq!(update.product_id, "BTC-USD");
+    assert_eq!(update.changes[0].side, Side::Buy);
+    assert_eq!(update.changes[0].price, 10101.8);
+    assert_eq!(update.changes[0].size, 0.162567);
+    assert_eq!(update.changes[1].side, Side::Sell);
+    assert_eq!(update.changes[1].size, 0.0);
+
+    let json = r#"{"type":"heartbeat","sequence":90,"last_trade_id":20,"product_id":"BTC-USD"}"#;
+    assert!(matches!(serde_json::from_str(json).unwrap(), Event::Other));
+
+    let json = r#"{"type":"error","message":"Failed to subscribe","reason":"BTC-XXX is not a valid product"}"#;
+    assert!(matches!(
+        serde_json::from_str(json).unwrap(),
+        Event::Error { .. }
+    ));
+}
+
+#[test]
+fn subscribe() {
+    let products = ["BTC-USD".to_string()];
+    let json = serde_json::to_string(&Subscribe {
+        kind: "subscribe",
+        channels: [Channel {
+            name: "level2",
+            product_ids: &products,
+        }],
+    })
+    .unwrap();
+    assert_eq!(
+        json,
+        r#"{"type":"subscribe","channels":[{"name":"level2","product_ids":["BTC-USD"]}]}"#
+    );
+}
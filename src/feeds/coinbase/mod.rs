@@ -0,0 +1,184 @@
+use crate::{
+    core::{Number, Order, OrderBook, OrderBookDiff, ASK, BID},
+    feeds::BookUpdate,
+    symbol::Symbol,
+    *,
+};
+use std::{collections::HashMap, sync::Arc};
+
+use futures_channel::mpsc::UnboundedSender;
+use futures_util::{lock::Mutex, SinkExt, StreamExt, TryStreamExt};
+use tokio::{net::TcpStream, task::JoinHandle};
+use tokio_tungstenite::{connect_async, tungstenite::Message, MaybeTlsStream, WebSocketStream};
+
+use self::events::{Change, Level, Side};
+
+pub type OrderBookTx = UnboundedSender<BookUpdate>;
+
+/// level2 channel streams changes at any depth so more levels than sent are kept
+/// to backfill the top when best levels are removed
+const DEPTH: usize = 1000;
+
+struct OrderBookSubscriptionState {
+    tx: OrderBookTx,
+    bids: OrderBook<BID, DEPTH>,
+    asks: OrderBook<ASK, DEPTH>,
+    /// number of books sent
+    seq: u64,
+}
+
+impl OrderBookSubscriptionState {
+    fn new(tx: OrderBookTx) -> Self {
+        Self {
+            tx,
+            bids: Default::default(),
+            asks: Default::default(),
+            seq: 0,
+        }
+    }
+}
+
+pub struct Config {
+    url: url::Url,
+    subscriptions: HashMap<String, OrderBookSubscriptionState>,
+}
+
+impl Default for Config {
+    fn default() -> Self {
+        Self::new(url::Url::parse("wss://ws-feed.exchange.coinbase.com").unwrap())
+    }
+}
+
+impl Config {
+    pub fn new(url: url::Url) -> Self {
+        Self {
+            url,
+            subscriptions: HashMap::default(),
+        }
+    }
+    pub fn subscribe_order_book(mut self, tx: OrderBookTx, symbol: Symbol) -> Self {
+        let product = symbol.to_coinbase();
+        assert!(
+            !self.subscriptions.contains_key(&product),
+            "order book stream has already subscribed for {product}"
+        );
+        self.subscriptions
+            .insert(product, OrderBookSubscriptionState::new(tx));
+        self
+    }
+    pub async fn connect(self) -> Result<Feed, feeds::Error> {
+        let (mut stream, _) = connect_async(self.url.clone()).await?;
+        let products: Vec<_> = self.subscriptions.keys().cloned().collect();
+        let subscribe = serde_json::to_string(&events::Subscribe {
+            kind: "subscribe",
+            channels: [events::Channel {
+                name: "level2",
+                product_ids: &products,
+            }],
+        })
+        .unwrap();
+        stream.send(Message::Text(subscribe)).await?;
+        Ok(Feed::new(stream, self))
+    }
+}
+
+fn order(price: Number, size: Number) -> Result<Order, feeds::Error> {
+    Order::try_new(price, size).map_err(|e| feeds::Error::Coinbase(e.to_string()))
+}
+
+fn book<const QUOTE: bool>(levels: &[Level]) -> Result<OrderBook<QUOTE, DEPTH>, feeds::Error> {
+    let orders = levels
+        .iter()
+        .map(|level| order(level.price, level.size))
+        .collect::<Result<_, _>>()?;
+    OrderBook::new(orders).map_err(|e| feeds::Error::Coinbase(e.to_string()))
+}
+
+fn diff<const QUOTE: bool>(
+    changes: &[Change],
+    side: Side,
+) -> Result<OrderBookDiff<QUOTE>, feeds::Error> {
+    let orders = changes
+        .iter()
+        .filter(|change| change.side == side)
+        .map(|change| order(change.price, change.size))
+        .collect::<Result<_, _>>()?;
+    OrderBookDiff::new(orders).map_err(|e| feeds::Error::Coinbase(e.to_string()))
+}
+
+pub struct Feed {
+    task: JoinHandle<Result<(), feeds::Error>>,
+}
+
+impl Drop for Feed {
+    fn drop(&mut self) {
+        self.task.abort();
+    }
+}
+
+impl Feed {
+    fn send(state: &mut OrderBookSubscriptionState) {
+        state.seq += 1;
+        // l2update time is RFC 3339 which isn't worth a dependency yet
+        // nobody listens anymore, it isn't an error of the feed
+        state
+            .tx
+            .unbounded_send(BookUpdate {
+                time: None,
+                seq: state.seq,
+                bids: state.bids.best(),
+                asks: state.asks.best(),
+            })
+            .ok();
+    }
+
+    fn dispatch(config: &mut Config, message: Message) -> Result<(), feeds::Error> {
+        let Message::Text(text) = message else {
+            return Ok(());
+        };
+        match serde_json::from_str(&text).map_err(|e| feeds::Error::Coinbase(e.to_string()))? {
+            events::Event::Snapshot(snapshot) => {
+                let state = config
+                    .subscriptions
+                    .get_mut(&snapshot.product_id)
+                    .expect("message for unsubscribed product");
+                state.bids = book(&snapshot.bids)?;
+                state.asks = book(&snapshot.asks)?;
+                Self::send(state);
+                Ok(())
+            }
+            events::Event::L2update(update) => {
+                let state = config
+                    .subscriptions
+                    .get_mut(&update.product_id)
+                    .expect("message for unsubscribed product");
+                state.bids = state.bids.update(&diff(&update.changes, Side::Buy)?);
+                state.asks = state.asks.update(&diff(&update.changes, Side::Sell)?);
+                Self::send(state);
+                Ok(())
+            }
+            events::Event::Error { message, reason } => Err(feeds::Error::Coinbase(
+                reason.map_or(message.clone(), |reason| format!("{message}: {reason}")),
+            )),
+            events::Event::Other => Ok(()),
+        }
+    }
+
+    pub fn new(stream: WebSocketStream<MaybeTlsStream<TcpStream>>, config: Config) -> Self {
+        let (_sink, stream) = stream.split();
+        let shared_config = Arc::new(Mutex::new(config));
+        let stream = stream
+            .err_into::<feeds::Error>()
+            .try_for_each(move |message| {
+                let config = shared_config.clone();
+                async move { Self::dispatch(&mut *config.lock().await, message) }
+            });
+        Self {
+            task: tokio::spawn(stream),
+        }
+    }
+}
+
+pub mod events;
+#[cfg(test)]
+mod tests;
@@ -0,0 +1,96 @@
+use futures_channel::mpsc::{unbounded, UnboundedReceiver};
+use tokio_tungstenite::tungstenite::Message;
+
+use crate::{
+    core::{Amount, Order, OrderBookAsks, OrderBookBids, Price},
+    feeds::{
+        coinbase::{Config, Feed},
+        BookUpdate, Error,
+    },
+    symbol::Symbol,
+};
+
+fn order(price: &str, amount: &str) -> Order {
+    Order::new(
+        Price::new(price.parse().unwrap()).unwrap(),
+        Amount::new(amount.parse().unwrap()).unwrap(),
+    )
+}
+
+fn config() -> (Config, UnboundedReceiver<BookUpdate>) {
+    let (tx, rx) = unbounded();
+    (
+        Config::default().subscribe_order_book(tx, Symbol::new("BTC", "USD")),
+        rx,
+    )
+}
+
+fn dispatch(config: &mut Config, text: &str) -> Result<(), Error> {
+    Feed::dispatch(config, Message::Text(text.into()))
+}
+
+#[test]
+fn snapshot() {
+    let (mut config, mut rx) = config();
+    let bids = (1..=12)
+        .map(|price| format!(r#"["{price}","1"]"#))
+        .collect::<Vec<_>>()
+        .join(",");
+    dispatch(
+        &mut config,
+        &format!(
+            r#"{{"type":"snapshot","product_id":"BTC-USD","bids":[{bids}],"asks":[["13","1.5"]]}}"#
+        ),
+    )
+    .unwrap();
+    let update = rx.try_next().unwrap().unwrap();
+    assert_eq!(update.seq, 1);
+    assert_eq!(update.time, None);
+    assert_eq!(
+        update.bids,
+        OrderBookBids::new(
+            (3..=12)
+                .map(|price| order(&price.to_string(), "1"))
+                .collect()
+        )
+        .unwrap()
+    );
+    assert_eq!(
+        update.asks,
+        OrderBookAsks::new(vec![order("13", "1.5")]).unwrap()
+    );
+
+    // removed best bid is backfilled from levels beyond the top
+    dispatch(
+        &mut config,
+        r#"{"type":"l2update","product_id":"BTC-USD","time":"2019-08-14T20:42:27.265Z","changes":[["buy","12","0"],["sell","12.5","0.5"],["sell","13","0.000"]]}"#,
+    )
+    .unwrap();
+    let update = rx.try_next().unwrap().unwrap();
+    assert_eq!(update.seq, 2);
+    assert_eq!(
+        update.bids,
+        OrderBookBids::new(
+            (2..=11)
+                .map(|price| order(&price.to_string(), "1"))
+                .collect()
+        )
+        .unwrap()
+    );
+    assert_eq!(
+        update.asks,
+        OrderBookAsks::new(vec![order("12.5", "0.5")]).unwrap()
+    );
+}
+
+#[test]
+fn error() {
+    let (mut config, mut rx) = config();
+    assert!(dispatch(&mut config, r#"{"type":"subscriptions","channels":[]}"#).is_ok());
+    let result = dispatch(
+        &mut config,
+        r#"{"type":"error","message":"Failed to subscribe","reason":"BTC-XXX is not a valid product"}"#,
+    );
+    assert!(matches!(result, Err(Error::Coinbase(message)) if message.contains("BTC-XXX")));
+    assert!(rx.try_next().is_err());
+}
@@ -0,0 +1,175 @@
+use serde::{de, Deserialize, Serialize};
+use serde_json::Value;
+
+use crate::core::Number;
+
+/// Price level as sent by Kraken: price, volume, timestamp and optional update type
+/// raw strings are kept since the checksum is calculated over them
+#[derive(Debug, Deserialize, Clone, PartialEq)]
+#[serde(try_from = "Vec<String>")]
+pub struct Level {
+    pub price: String,
+    pub volume: String,
+    pub timestamp: String,
+}
+
+impl TryFrom<Vec<String>> for Level {
+    type Error = String;
+
+    fn try_from(fields: Vec<String>) -> Result<Self, Self::Error> {
+        match <[String; 3]>::try_from(fields.into_iter().take(3).collect::<Vec<_>>()) {
+            Ok([price, volume, timestamp]) => Ok(Self {
+                price,
+                volume,
+                timestamp,
+            }),
+            Err(fields) => Err(format!("level has {} fields", fields.len())),
+        }
+    }
+}
+
+impl Level {
+    pub fn price(&self) -> Result<Number, String> {
+        self.price.parse().map_err(|_| self.price.clone())
+    }
+    pub fn volume(&self) -> Result<Number, String> {
+        self.volume.parse().map_err(|_| self.volume.clone())
+    }
+    /// timestamp in ms since epoch
+    pub fn time(&self) -> Option<u64> {
+        self.timestamp
+            .parse::<f64>()
+            .ok()
+            .map(|seconds| (seconds * 1000.0) as u64)
+    }
+}
+
+/// Payload of a book frame, snapshots have `as`/`bs`, updates have `a`/`b` and `c`
+#[derive(Debug, Deserialize, Default, Clone, PartialEq)]
+pub struct Book {
+    #[serde(default, rename = "as")]
+    pub snapshot_asks: Vec<Level>,
+    #[serde(default, rename = "bs")]
+    pub snapshot_bids: Vec<Level>,
+    #[serde(default, rename = "a")]
+    pub asks: Vec<Level>,
+    #[serde(default, rename = "b")]
+    pub bids: Vec<Level>,
+    #[serde(default, rename = "c")]
+    pub checksum: Option<String>,
+}
+
+impl Book {
+    pub fn is_snapshot(&self) -> bool {
+        !self.snapshot_asks.is_empty() || !self.snapshot_bids.is_empty()
+    }
+}
+
+/// `[channelID, {...}, channelName, pair]` frame
+/// updates of both sides come as `[channelID, {a}, {b}, channelName, pair]`
+#[derive(Debug, Clone, PartialEq)]
+pub struct BookFrame {
+    pub channel_id: u64,
+    pub book: Book,
+    pub channel_name: String,
+    pub pair: String,
+}
+
+impl<'de> Deserialize<'de> for BookFrame {
+    fn deserialize<D: de::Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        let mut fields = Vec::<Value>::deserialize(deserializer)?;
+        if fields.len() < 4 {
+            return Err(de::Error::invalid_length(
+                fields.len(),
+                &"at least 4 fields",
+            ));
+        }
+        let pair = String::deserialize(fields.pop().unwrap()).map_err(de::Error::custom)?;
+        let channel_name = String::deserialize(fields.pop().unwrap()).map_err(de::Error::custom)?;
+        let mut fields = fields.into_iter();
+        let channel_id = u64::deserialize(fields.next().unwrap()).map_err(de::Error::custom)?;
+        let book = fields.try_fold(Book::default(), |mut book, value| {
+            let part = Book::deserialize(value).map_err(de::Error::custom)?;
+            book.snapshot_asks.extend(part.snapshot_asks);
+            book.snapshot_bids.extend(part.snapshot_bids);
+            book.asks.extend(part.asks);
+            book.bids.extend(part.bids);
+            book.checksum = part.checksum.or(book.checksum);
+            Ok(book)
+        })?;
+        Ok(Self {
+            channel_id,
+            book,
+            channel_name,
+            pair,
+        })
+    }
+}
+
+/// Inbound frames, books are arrays and everything else is an object tagged by event
+#[derive(Debug, Deserialize)]
+#[serde(untagged)]
+pub enum Event {
+    Book(BookFrame),
+    Status(Status),
+}
+
+#[derive(Debug, Deserialize)]
+pub struct Status {
+    pub event: String,
+    #[serde(default, rename = "errorMessage")]
+    pub error_message: Option<String>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct Subscription {
+    pub name: &'static str,
+    pub depth: u16,
+}
+
+/// Outbound subscribe/unsubscribe request
+#[derive(Debug, Serialize)]
+pub struct Request<'a> {
+    pub event: &'static str,
+    pub pair: &'a [String],
+    pub subscription: Subscription,
+}
+
+#[test]
+fn snapshot() {
+    let json = r#"[0,{"as":[["5541.30000","2.50700000","1534614248.123678"],["5541.80000","0.33000000","1534614098.345543"]],"bs":[["5541.20000","1.52900000","1534614248.765567"]]},"book-10","XBT/USD"]"#;
+    let event: Event = serde_json::from_str(json).unwrap();
+    let Event::Book(frame) = event else {
+        panic!("book frame expected")
+    };
+    assert_eq!(frame.channel_id, 0);
+    assert_eq!(frame.channel_name, "book-10");
+    assert_eq!(frame.pair, "XBT/USD");
+    assert!(frame.book.is_snapshot());
+    assert_eq!(frame.book.snapshot_asks.len(), 2);
+    assert_eq!(frame.book.snapshot_bids[0].price, "5541.20000");
+    assert_eq!(frame.book.snapshot_bids[0].volume, "1.52900000");
+    assert_eq!(frame.book.snapshot_bids[0].time(), Some(1534614248765));
+    assert!(frame.book.checksum.is_none());
+}
+
+#[test]
+fn update() {
+    let json = r#"[1234,{"a":[["5541.30000","2.50700000","1534614248.456738","r"]]},{"b":[["5541.30000","0.00000000","1534614335.345903"]],"c":"974942666"},"book-10","XBT/USD"]"#;
+    let event: Event = serde_json::from_str(json).unwrap();
+    let Event::Book(frame) = event else {
+        panic!("book frame expected")
+    };
+    assert_eq!(frame.channel_id, 1234);
+    assert!(!frame.book.is_snapshot());
+    assert_eq!(frame.book.asks[0].price, "5541.30000");
+    assert_eq!(frame.book.bids[0].volume, "0.00000000");
+    assert_eq!(frame.book.checksum.as_deref(), Some("974942666"));
+
+    let json = r#"{"event":"heartbeat"}"#;
+    let event: Event = serde_json::from_str(json).unwrap();
+    assert!(matches!(event, Event::Status(Status { event, .. }) if event == "heartbeat"));
+
+    let json = r#"[1234,"book-10","XBT/USD"]"#;
+    assert!(serde_json::from_str::<Event>(json).is_err());
+}
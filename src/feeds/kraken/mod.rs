@@ -0,0 +1,294 @@
+use crate::{
+    core::{
+        self, Order, OrderBook, OrderBookAsks, OrderBookBids, OrderBookDiff, Price,
+        BEST_ORDER_BOOK_SIZE,
+    },
+    feeds::BookUpdate,
+    symbol::Symbol,
+    *,
+};
+use std::{collections::HashMap, sync::Arc};
+
+use futures_channel::mpsc::UnboundedSender;
+use futures_util::{lock::Mutex, SinkExt, StreamExt, TryStreamExt};
+use tokio::{net::TcpStream, task::JoinHandle};
+use tokio_tungstenite::{connect_async, tungstenite::Message, MaybeTlsStream, WebSocketStream};
+
+use self::events::{Book, BookFrame, Level};
+
+pub type OrderBookTx = UnboundedSender<BookUpdate>;
+
+/// Kraken truncates books to the subscribed depth and calculates checksum over top 10 levels
+/// so the depth has to match the size of the books
+const DEPTH: u16 = BEST_ORDER_BOOK_SIZE as u16;
+
+struct OrderBookSubscriptionState {
+    tx: OrderBookTx,
+    bids: OrderBookBids,
+    asks: OrderBookAsks,
+    /// levels of the books as sent, the checksum is calculated over their text
+    bid_levels: HashMap<Price, Level>,
+    ask_levels: HashMap<Price, Level>,
+    /// false until snapshot arrives, updates are ignored meanwhile
+    synced: bool,
+    /// number of books sent, Kraken has no update ids
+    seq: u64,
+}
+
+impl OrderBookSubscriptionState {
+    fn new(tx: OrderBookTx) -> Self {
+        Self {
+            tx,
+            bids: Default::default(),
+            asks: Default::default(),
+            bid_levels: HashMap::default(),
+            ask_levels: HashMap::default(),
+            synced: false,
+            seq: 0,
+        }
+    }
+}
+
+pub struct Config {
+    url: url::Url,
+    subscriptions: HashMap<String, OrderBookSubscriptionState>,
+}
+
+impl Default for Config {
+    fn default() -> Self {
+        Self::new(url::Url::parse("wss://ws.kraken.com").unwrap())
+    }
+}
+
+impl Config {
+    pub fn new(url: url::Url) -> Self {
+        Self {
+            url,
+            subscriptions: HashMap::default(),
+        }
+    }
+    /// pair is in Kraken notation e.g. XBT/USD
+    pub fn subscribe_order_book(mut self, tx: OrderBookTx, symbol: Symbol) -> Self {
+        let pair = symbol.to_kraken();
+        assert!(
+            !self.subscriptions.contains_key(&pair),
+            "order book stream has already subscribed for {pair}"
+        );
+        self.subscriptions
+            .insert(pair, OrderBookSubscriptionState::new(tx));
+        self
+    }
+    pub async fn connect(self) -> Result<Feed, feeds::Error> {
+        let (mut stream, _) = connect_async(self.url.clone()).await?;
+        let pairs: Vec<_> = self.subscriptions.keys().cloned().collect();
+        stream.send(request("subscribe", &pairs)).await?;
+        Ok(Feed::new(stream, self))
+    }
+}
+
+fn request(event: &'static str, pairs: &[String]) -> Message {
+    Message::Text(
+        serde_json::to_string(&events::Request {
+            event,
+            pair: pairs,
+            subscription: events::Subscription {
+                name: "book",
+                depth: DEPTH,
+            },
+        })
+        .unwrap(),
+    )
+}
+
+fn order(level: &Level) -> Result<Order, feeds::Error> {
+    let price = level.price().map_err(feeds::Error::Kraken)?;
+    let volume = level.volume().map_err(feeds::Error::Kraken)?;
+    Order::try_new(price, volume).map_err(|e| feeds::Error::Kraken(e.to_string()))
+}
+
+fn orders(levels: &[Level]) -> Result<Vec<Order>, feeds::Error> {
+    levels.iter().map(order).collect()
+}
+
+fn diff<const QUOTE: bool>(levels: &[Level]) -> Result<OrderBookDiff<QUOTE>, feeds::Error> {
+    let mut orders = orders(levels)?;
+    // a price may be repeated within a frame, the latest one wins
+    orders.reverse();
+    orders.sort_by_key(Order::price);
+    orders.dedup_by_key(|order| order.price());
+    OrderBookDiff::new(orders).map_err(|e| feeds::Error::Kraken(e.to_string()))
+}
+
+/// applies levels to their text the same way as to the book, the latest one wins
+/// and only text of levels the book has kept remains
+fn update_levels<const QUOTE: bool>(
+    levels: &mut HashMap<Price, Level>,
+    updates: &[Level],
+    book: &OrderBook<QUOTE, BEST_ORDER_BOOK_SIZE>,
+) -> Result<(), feeds::Error> {
+    for level in updates {
+        let order = order(level)?;
+        if order.is_empty() {
+            levels.remove(&order.price());
+        } else {
+            levels.insert(order.price(), level.clone());
+        }
+    }
+    levels.retain(|price, _| book.amount_at(*price).is_some());
+    Ok(())
+}
+
+/// text of the level of the order, none if the level is unknown so the checksum fails
+fn text<'a>(levels: &'a HashMap<Price, Level>) -> impl FnMut(&Order) -> (&'a str, &'a str) {
+    move |order| {
+        levels
+            .get(&order.price())
+            .map_or(("", ""), |level| (&level.price, &level.volume))
+    }
+}
+
+/// checksum of top asks followed by top bids, see core::checksum
+fn checksum(state: &OrderBookSubscriptionState, bids: &OrderBookBids, asks: &OrderBookAsks) -> u32 {
+    let depth = DEPTH.into();
+    core::checksum(
+        asks.orders()
+            .iter()
+            .take(depth)
+            .map(text(&state.ask_levels))
+            .chain(
+                bids.orders()
+                    .iter()
+                    .take(depth)
+                    .map(text(&state.bid_levels)),
+            ),
+    )
+}
+
+pub struct Feed {
+    task: JoinHandle<Result<(), feeds::Error>>,
+}
+
+impl Drop for Feed {
+    fn drop(&mut self) {
+        self.task.abort();
+    }
+}
+
+impl Feed {
+    fn snapshot(state: &mut OrderBookSubscriptionState, book: Book) -> Result<(), feeds::Error> {
+        state.bids = OrderBookBids::new(orders(&book.snapshot_bids)?)
+            .map_err(|e| feeds::Error::Kraken(e.to_string()))?;
+        state.asks = OrderBookAsks::new(orders(&book.snapshot_asks)?)
+            .map_err(|e| feeds::Error::Kraken(e.to_string()))?;
+        state.bid_levels.clear();
+        update_levels(&mut state.bid_levels, &book.snapshot_bids, &state.bids)?;
+        state.ask_levels.clear();
+        update_levels(&mut state.ask_levels, &book.snapshot_asks, &state.asks)?;
+        state.synced = true;
+        Ok(())
+    }
+    /// false if the updated books don't match the checksum
+    fn update(state: &mut OrderBookSubscriptionState, book: Book) -> Result<bool, feeds::Error> {
+        let bids = state.bids.update(&diff(&book.bids)?);
+        let asks = state.asks.update(&diff(&book.asks)?);
+        // text of levels is replaced by the snapshot if the checksum fails
+        update_levels(&mut state.bid_levels, &book.bids, &bids)?;
+        update_levels(&mut state.ask_levels, &book.asks, &asks)?;
+        if let Some(expected) = book.checksum {
+            let expected: u32 = expected
+                .parse()
+                .map_err(|_| feeds::Error::Kraken(format!("invalid checksum {expected}")))?;
+            if checksum(state, &bids, &asks) != expected {
+                return Ok(false);
+            }
+        }
+        state.bids = bids;
+        state.asks = asks;
+        Ok(true)
+    }
+    fn order_book(config: &mut Config, frame: BookFrame) -> Result<Vec<Message>, feeds::Error> {
+        let state = config
+            .subscriptions
+            .get_mut(&frame.pair)
+            .expect("message for unsubscribed pair");
+
+        let time = frame
+            .book
+            .snapshot_asks
+            .iter()
+            .chain(&frame.book.snapshot_bids)
+            .chain(&frame.book.asks)
+            .chain(&frame.book.bids)
+            .filter_map(Level::time)
+            .max();
+        if frame.book.is_snapshot() {
+            Self::snapshot(state, frame.book)?;
+        } else if !state.synced {
+            // waiting for snapshot after resync
+            return Ok(vec![]);
+        } else if !Self::update(state, frame.book)? {
+            // resubscribing is the only way to get a fresh snapshot
+            state.synced = false;
+            let pair = [frame.pair];
+            return Ok(vec![
+                request("unsubscribe", &pair),
+                request("subscribe", &pair),
+            ]);
+        }
+
+        state.seq += 1;
+        // nobody listens anymore, it isn't an error of the feed
+        state
+            .tx
+            .unbounded_send(BookUpdate {
+                time,
+                seq: state.seq,
+                bids: state.bids.clone(),
+                asks: state.asks.clone(),
+            })
+            .ok();
+        Ok(vec![])
+    }
+
+    /// returns messages to be sent back to the exchange
+    fn dispatch(config: &mut Config, message: Message) -> Result<Vec<Message>, feeds::Error> {
+        let Message::Text(text) = message else {
+            return Ok(vec![]);
+        };
+        match serde_json::from_str(&text).map_err(|e| feeds::Error::Kraken(e.to_string()))? {
+            events::Event::Book(frame) => Self::order_book(config, frame),
+            events::Event::Status(events::Status {
+                error_message: Some(error),
+                ..
+            }) => Err(feeds::Error::Kraken(error)),
+            events::Event::Status(_) => Ok(vec![]),
+        }
+    }
+
+    pub fn new(stream: WebSocketStream<MaybeTlsStream<TcpStream>>, config: Config) -> Self {
+        let (sink, stream) = stream.split();
+        let shared_config = Arc::new(Mutex::new(config));
+        let shared_sink = Arc::new(Mutex::new(sink));
+        let stream = stream
+            .err_into::<feeds::Error>()
+            .try_for_each(move |message| {
+                let config = shared_config.clone();
+                let sink = shared_sink.clone();
+                async move {
+                    let replies = Self::dispatch(&mut *config.lock().await, message)?;
+                    let mut sink = sink.lock().await;
+                    for reply in replies {
+                        sink.send(reply).await?;
+                    }
+                    Ok(())
+                }
+            });
+        Self {
+            task: tokio::spawn(stream),
+        }
+    }
+}
+
+pub mod events;
+#[cfg(test)]
+mod tests;
@@ -0,0 +1,146 @@
+use futures_channel::mpsc::{unbounded, UnboundedReceiver};
+use tokio_tungstenite::tungstenite::Message;
+
+use crate::{
+    core::{Amount, Order, OrderBookAsks, OrderBookBids, Price},
+    feeds::{
+        kraken::{Config, Feed},
+        BookUpdate, Error,
+    },
+    symbol::Symbol,
+};
+
+fn order(price: &str, amount: &str) -> Order {
+    Order::new(
+        Price::new(price.parse().unwrap()).unwrap(),
+        Amount::new(amount.parse().unwrap()).unwrap(),
+    )
+}
+
+fn config() -> (Config, UnboundedReceiver<BookUpdate>) {
+    let (tx, rx) = unbounded();
+    (
+        Config::default().subscribe_order_book(tx, Symbol::new("BTC", "USD")),
+        rx,
+    )
+}
+
+fn dispatch(config: &mut Config, text: &str) -> Result<Vec<Message>, Error> {
+    Feed::dispatch(config, Message::Text(text.into()))
+}
+
+const SNAPSHOT: &str = r#"[0,{"as":[["0.05005","0.00000500","1582905487.684110"],["0.05010","0.00000500","1582905486.187983"]],"bs":[["0.05000","0.00000500","1582905487.439814"]]},"book-10","XBT/USD"]"#;
+
+#[test]
+fn snapshot() {
+    let (mut config, mut rx) = config();
+    assert!(dispatch(&mut config, SNAPSHOT).unwrap().is_empty());
+    let update = rx.try_next().unwrap().unwrap();
+    assert_eq!(update.time, Some(1582905487684));
+    assert_eq!(update.seq, 1);
+    assert_eq!(
+        update.bids,
+        OrderBookBids::new(vec![order("0.05", "0.000005")]).unwrap()
+    );
+    assert_eq!(
+        update.asks,
+        OrderBookAsks::new(vec![
+            order("0.05005", "0.000005"),
+            order("0.0501", "0.000005")
+        ])
+        .unwrap()
+    );
+}
+
+#[test]
+fn checksum() {
+    let (mut config, mut rx) = config();
+    dispatch(&mut config, SNAPSHOT).unwrap();
+    rx.try_next().unwrap();
+
+    // asks then bids, without decimal points and leading zeros
+    let checksum = crc32fast::hash(
+        concat!("5005", "500", "5010", "500", "5000", "500", "4990", "1000").as_bytes(),
+    );
+    let update = format!(
+        r#"[0,{{"b":[["0.04990","0.00001000","1582905488.000000"]],"c":"{checksum}"}},"book-10","XBT/USD"]"#
+    );
+    assert!(dispatch(&mut config, &update).unwrap().is_empty());
+    let update = rx.try_next().unwrap().unwrap();
+    assert_eq!(update.seq, 2);
+    assert_eq!(
+        update.bids,
+        OrderBookBids::new(vec![order("0.05", "0.000005"), order("0.0499", "0.00001")]).unwrap()
+    );
+
+    // mismatch resubscribes and ignores updates until the next snapshot
+    let update = r#"[0,{"a":[["0.05005","0.00000000","1582905489.000000"]]},{"b":[["0.05001","0.00000100","1582905489.000000"]],"c":"1"},"book-10","XBT/USD"]"#;
+    let replies = dispatch(&mut config, update).unwrap();
+    assert_eq!(replies.len(), 2);
+    assert!(
+        matches!(&replies[0], Message::Text(text) if text.contains(r#""event":"unsubscribe""#))
+    );
+    assert!(matches!(&replies[1], Message::Text(text) if text.contains(r#""event":"subscribe""#)));
+    assert!(rx.try_next().is_err());
+
+    let update =
+        r#"[0,{"a":[["0.05005","0.00000000","1582905490.000000"]],"c":"2"},"book-10","XBT/USD"]"#;
+    assert!(dispatch(&mut config, update).unwrap().is_empty());
+    assert!(rx.try_next().is_err());
+
+    dispatch(&mut config, SNAPSHOT).unwrap();
+    let update = rx.try_next().unwrap().unwrap();
+    assert_eq!(update.seq, 3);
+    assert_eq!(
+        update.bids,
+        OrderBookBids::new(vec![order("0.05", "0.000005")]).unwrap()
+    );
+}
+
+#[test]
+fn checksum_of_raw_text() {
+    let (mut config, mut rx) = config();
+    // more digits than f64 holds and decimals differing between levels
+    let snapshot = r#"[0,{"as":[["0.05005","123456789.123456789012","1582905487.684110"]],"bs":[["0.05000","1.5","1582905487.439814"]]},"book-10","XBT/USD"]"#;
+    dispatch(&mut config, snapshot).unwrap();
+    rx.try_next().unwrap();
+
+    let checksum = crc32fast::hash(
+        concat!(
+            "5005",
+            "123456789123456789012",
+            "5000",
+            "15",
+            "4990",
+            "1000"
+        )
+        .as_bytes(),
+    );
+    let update = format!(
+        r#"[0,{{"b":[["0.04990","0.00001000","1582905488.000000"]],"c":"{checksum}"}},"book-10","XBT/USD"]"#
+    );
+    assert!(dispatch(&mut config, &update).unwrap().is_empty());
+    assert_eq!(rx.try_next().unwrap().unwrap().seq, 2);
+
+    // removed levels no longer count
+    let checksum =
+        crc32fast::hash(concat!("5005", "123456789123456789012", "4990", "1000").as_bytes());
+    let update = format!(
+        r#"[0,{{"b":[["0.05000","0.0","1582905489.000000"]],"c":"{checksum}"}},"book-10","XBT/USD"]"#
+    );
+    assert!(dispatch(&mut config, &update).unwrap().is_empty());
+    assert_eq!(rx.try_next().unwrap().unwrap().seq, 3);
+}
+
+#[test]
+fn subscription_error() {
+    let (mut config, _rx) = config();
+    let status = r#"{"event":"subscriptionStatus","status":"error","errorMessage":"Currency pair not supported XBT/USD"}"#;
+    assert!(matches!(
+        dispatch(&mut config, status),
+        Err(Error::Kraken(_))
+    ));
+    assert!(dispatch(&mut config, r#"{"event":"heartbeat"}"#)
+        .unwrap()
+        .is_empty());
+}
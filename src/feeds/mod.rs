@@ -1,11 +1,336 @@
+use std::{
+    pin::Pin,
+    sync::atomic::{AtomicU64, Ordering},
+    task::{Context, Poll},
+    time::{Duration, SystemTime, UNIX_EPOCH},
+};
+
+use futures_channel::mpsc::{channel, unbounded, SendError, Sender, UnboundedSender};
+use futures_util::{
+    future::poll_fn,
+    stream::{unfold, BoxStream},
+    FutureExt, Stream, StreamExt,
+};
+use serde::Deserialize;
 use thiserror::Error;
 
+use tokio::time::Instant;
+use tokio_tungstenite::tungstenite::protocol::CloseFrame;
+
+use crate::core::{Amount, Number, Order, OrderBookAsks, OrderBookBids, Price};
+
 #[derive(Error, Debug)]
 pub enum Error {
     #[error("Websocket error: {0}")]
     WS(#[from] tokio_tungstenite::tungstenite::Error),
+    #[error("IO error: {0}")]
+    IO(#[from] std::io::Error),
     #[error("Binance error: {0}")]
     Binance(String),
+    #[error("Binance API error {code}: {msg}")]
+    BinanceApi { code: i64, msg: String },
+    #[error("Kraken error: {0}")]
+    Kraken(String),
+    #[error("Coinbase error: {0}")]
+    Coinbase(String),
+    #[error("No order book updates for {0:?}")]
+    Stale(Duration),
+    #[error("Missed updates: expected update {expected}, got {got}")]
+    Gap { expected: u64, got: u64 },
+    #[error("More than {0} messages per second")]
+    Flood(u32),
+    #[error("Connection closed by server: {0:?}")]
+    ClosedByServer(Option<CloseFrame<'static>>),
+}
+
+/// Emits the event of the level as tracing::event! does if the tracing feature is on
+/// e.g. `trace_event!(DEBUG, symbol, "subscribed")`
+macro_rules! trace_event {
+    ($level:ident, $($arg:tt)+) => {
+        #[cfg(feature = "tracing")]
+        tracing::event!(tracing::Level::$level, $($arg)+);
+    };
+}
+
+/// Deserializes numbers sent as strings or as bare JSON numbers
+pub(crate) fn float_as_string<'de, D>(deserializer: D) -> Result<Number, D::Error>
+where
+    D: serde::Deserializer<'de>,
+{
+    #[derive(Deserialize)]
+    #[serde(untagged)]
+    enum StringOrNumber {
+        String(String),
+        Number(Number),
+    }
+
+    match StringOrNumber::deserialize(deserializer)? {
+        StringOrNumber::String(string) => string.parse().map_err(serde::de::Error::custom),
+        StringOrNumber::Number(number) => Ok(number),
+    }
+}
+
+/// Books of a symbol along with sequencing of the update
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct BookUpdate {
+    /// exchange time of the event in ms since epoch if the exchange provides it
+    pub time: Option<u64>,
+    /// grows monotonically per subscription, it is the exchange id of the last update
+    /// included into books where the exchange provides one e.g. Binance
+    /// otherwise a count of books sent which says nothing about continuity
+    pub seq: u64,
+    pub bids: OrderBookBids,
+    pub asks: OrderBookAsks,
+}
+
+/// Best levels of a symbol for consumers which need no depth, no books are built for it
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct TopOfBook {
+    pub bid: Order,
+    pub ask: Order,
+}
+
+/// Plausible levels of an exchange, orders out of bounds are dropped by the feed
+/// unlike parse validation it guards against fat fingers and the like
+/// default one accepts every valid order
+#[derive(Debug, Default, Clone, Copy, PartialEq)]
+pub struct Bounds {
+    pub min_price: Option<Price>,
+    pub max_price: Option<Price>,
+    pub max_amount: Option<Amount>,
+}
+
+impl Bounds {
+    pub fn contains(&self, order: &Order) -> bool {
+        self.min_price.map_or(true, |min| order.price() >= min)
+            && self.max_price.map_or(true, |max| order.price() <= max)
+            && self.max_amount.map_or(true, |max| order.amount() <= max)
+    }
+}
+
+/// Channel feeds send books into
+/// bounded one makes the feed wait for the subscriber and stop reading the socket meanwhile
+/// so a slow subscriber slows down the feed instead of exhausting memory
+/// but the exchange may drop the connection if the feed lags behind for too long
+/// unbounded one never holds the feed back and buffers books without limit
+#[derive(Debug, Clone)]
+pub enum BookTx {
+    Unbounded(UnboundedSender<BookUpdate>),
+    Bounded(Sender<BookUpdate>),
+}
+
+impl From<UnboundedSender<BookUpdate>> for BookTx {
+    fn from(tx: UnboundedSender<BookUpdate>) -> Self {
+        Self::Unbounded(tx)
+    }
+}
+
+impl From<Sender<BookUpdate>> for BookTx {
+    fn from(tx: Sender<BookUpdate>) -> Self {
+        Self::Bounded(tx)
+    }
+}
+
+impl BookTx {
+    /// waits until bounded channel has room for the update
+    pub async fn send(&mut self, update: BookUpdate) -> Result<(), SendError> {
+        match self {
+            Self::Unbounded(tx) => tx.unbounded_send(update).map_err(|e| e.into_send_error()),
+            Self::Bounded(tx) => {
+                poll_fn(|cx| tx.poll_ready(cx)).await?;
+                tx.start_send(update)
+            }
+        }
+    }
+}
+
+/// Receiving end of BookTx, books of a subscription in the order sent
+pub struct BookStream(BoxStream<'static, BookUpdate>);
+
+impl Stream for BookStream {
+    type Item = BookUpdate;
+
+    fn poll_next(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Option<Self::Item>> {
+        self.0.poll_next_unpin(cx)
+    }
+}
+
+impl BookStream {
+    /// see BookTx::Unbounded
+    pub fn unbounded() -> (BookTx, Self) {
+        let (tx, rx) = unbounded();
+        (tx.into(), Self(rx.boxed()))
+    }
+    /// see BookTx::Bounded
+    pub fn bounded(buffer: usize) -> (BookTx, Self) {
+        let (tx, rx) = channel(buffer);
+        (tx.into(), Self(rx.boxed()))
+    }
+    /// yields only the most recent of books available at once
+    /// so a slow consumer skips books it has no time for
+    pub fn latest(self) -> Self {
+        Self(
+            unfold(self.0.fuse(), |mut books| async move {
+                let mut latest = books.next().await?;
+                while let Some(Some(book)) = books.next().now_or_never() {
+                    latest = book;
+                }
+                Some((latest, books))
+            })
+            .boxed(),
+        )
+    }
+    /// yields at most one book per period, the latest one, see aggregator::coalesce
+    pub fn throttle(self, period: Duration) -> Self {
+        Self(crate::aggregator::coalesce(self.0, period).boxed())
+    }
+}
+
+/// up to n books of the stream, fewer if it ends or the timeout elapses before
+pub async fn collect_updates<S: Stream<Item = BookUpdate> + Unpin>(
+    stream: S,
+    n: usize,
+    timeout: Duration,
+) -> Vec<BookUpdate> {
+    let mut updates = Vec::new();
+    let mut stream = stream.take(n);
+    // books received in time are kept when it elapses
+    let _ = tokio::time::timeout(timeout, async {
+        while let Some(update) = stream.next().await {
+            updates.push(update);
+        }
+    })
+    .await;
+    updates
+}
+
+/// Snapshot of feed counters since it has connected
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub struct FeedStats {
+    pub messages: u64,
+    pub parse_errors: u64,
+    pub books: u64,
+    /// local receive time minus exchange event time of the last book which has one
+    /// clocks may be skewed so a local clock behind the exchange one gives 0
+    pub latency: Option<Duration>,
+}
+
+/// Liveness of a feed to supervise it without consuming its books
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct FeedHealth {
+    pub connected: bool,
+    /// or the time it has connected if no message has arrived yet
+    pub last_message_at: Instant,
+    /// always 0 for feeds which don't reconnect by themselves
+    pub reconnects: u64,
+}
+
+/// Counters updated by the feed task, relaxed atomics so scraping doesn't slow it down
+#[derive(Debug)]
+pub(crate) struct Stats {
+    messages: AtomicU64,
+    parse_errors: AtomicU64,
+    books: AtomicU64,
+    /// latency in ms, u64::MAX if unknown
+    latency: AtomicU64,
+}
+
+impl Default for Stats {
+    fn default() -> Self {
+        Self {
+            messages: AtomicU64::default(),
+            parse_errors: AtomicU64::default(),
+            books: AtomicU64::default(),
+            latency: AtomicU64::new(u64::MAX),
+        }
+    }
+}
+
+impl Stats {
+    pub(crate) fn message(&self) {
+        self.messages.fetch_add(1, Ordering::Relaxed);
+    }
+    pub(crate) fn parse_error(&self) {
+        self.parse_errors.fetch_add(1, Ordering::Relaxed);
+    }
+    /// time is exchange time of the book in ms since epoch if known
+    pub(crate) fn book(&self, time: Option<u64>) {
+        self.books.fetch_add(1, Ordering::Relaxed);
+        if let Some(time) = time {
+            let now = SystemTime::now()
+                .duration_since(UNIX_EPOCH)
+                .unwrap_or_default()
+                .as_millis() as u64;
+            self.latency
+                .store(now.saturating_sub(time), Ordering::Relaxed);
+        }
+    }
+    pub(crate) fn snapshot(&self) -> FeedStats {
+        let latency = self.latency.load(Ordering::Relaxed);
+        FeedStats {
+            messages: self.messages.load(Ordering::Relaxed),
+            parse_errors: self.parse_errors.load(Ordering::Relaxed),
+            books: self.books.load(Ordering::Relaxed),
+            latency: (latency != u64::MAX).then(|| Duration::from_millis(latency)),
+        }
+    }
+}
+
+/// Decodes inbound websocket frames into exchange specific events
+pub trait Codec: Send + Sync {
+    type Event;
+
+    fn decode_text(&self, text: &str) -> Result<Self::Event, Error>;
+    fn decode_binary(&self, binary: &[u8]) -> Result<Self::Event, Error>;
 }
 
 pub mod binance;
+pub mod clock;
+pub mod coinbase;
+pub mod kraken;
+pub mod replay;
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn book_stream() {
+        let book = |seq| BookUpdate {
+            time: None,
+            seq,
+            bids: Default::default(),
+            asks: Default::default(),
+        };
+        let (mut tx, books) = BookStream::unbounded();
+        for seq in 1..=3 {
+            tx.send(book(seq)).await.unwrap();
+        }
+        drop(tx);
+        assert_eq!(
+            books.map(|book| book.seq).collect::<Vec<_>>().await,
+            [1, 2, 3]
+        );
+
+        let (mut tx, mut books) = BookStream::bounded(4);
+        books = books.latest();
+        for seq in 1..=3 {
+            tx.send(book(seq)).await.unwrap();
+        }
+        assert_eq!(books.next().await.unwrap().seq, 3);
+        tx.send(book(4)).await.unwrap();
+        drop(tx);
+        assert_eq!(books.map(|book| book.seq).collect::<Vec<_>>().await, [4]);
+
+        let (mut tx, books) = BookStream::unbounded();
+        let mut books = books.throttle(Duration::from_millis(100));
+        for seq in 1..=3 {
+            tx.send(book(seq)).await.unwrap();
+        }
+        let start = tokio::time::Instant::now();
+        assert_eq!(books.next().await.unwrap().seq, 3);
+        tx.send(book(4)).await.unwrap();
+        assert_eq!(books.next().await.unwrap().seq, 4);
+        assert!(start.elapsed() >= Duration::from_millis(90));
+    }
+}
@@ -0,0 +1,226 @@
+use std::{path::PathBuf, time::Duration};
+
+use tokio::{
+    task::JoinHandle,
+    time::{sleep_until, Instant},
+};
+use tokio_tungstenite::tungstenite::Message;
+
+use crate::{
+    feeds::{
+        self,
+        binance::{self, BookDepth, BookPeriod, OrderBookTx},
+    },
+    symbol::Symbol,
+};
+
+/// Replays Binance frames recorded one raw JSON message per line
+/// through the same parsing and continuity checks as the live feed
+pub struct ReplayConfig {
+    path: PathBuf,
+    config: binance::Config,
+    paced: bool,
+}
+
+impl ReplayConfig {
+    pub fn from_path(path: impl Into<PathBuf>) -> Self {
+        Self {
+            path: path.into(),
+            config: binance::Config::default(),
+            paced: false,
+        }
+    }
+    pub fn subscribe_order_book(
+        mut self,
+        tx: impl Into<OrderBookTx>,
+        symbol: Symbol,
+        period: BookPeriod,
+        depth: Option<BookDepth>,
+    ) -> Self {
+        self.config = self.config.subscribe_order_book(tx, symbol, period, depth);
+        self
+    }
+    /// sends books as far apart as their event times instead of at max speed
+    pub fn paced(mut self) -> Self {
+        self.paced = true;
+        self
+    }
+    /// the feed finishes once every line is replayed
+    /// frames of symbols or streams not subscribed to are skipped
+    pub async fn connect(self) -> Result<Feed, feeds::Error> {
+        let path = self.path.clone();
+        let recording = tokio::task::spawn_blocking(move || std::fs::read_to_string(path))
+            .await
+            .map_err(|e| std::io::Error::new(std::io::ErrorKind::Other, e))??;
+        Ok(Feed::new(recording, self))
+    }
+}
+
+pub struct Feed {
+    task: JoinHandle<Result<(), feeds::Error>>,
+}
+
+impl Drop for Feed {
+    fn drop(&mut self) {
+        self.task.abort();
+    }
+}
+
+impl Feed {
+    fn new(recording: String, replay: ReplayConfig) -> Self {
+        let ReplayConfig {
+            mut config, paced, ..
+        } = replay;
+        let task = async move {
+            // event time of the first book and when it was sent
+            let mut origin = None;
+            for line in recording.lines().filter(|line| !line.trim().is_empty()) {
+                // recordings are replayed into order book subscriptions only
+                let Some((symbol, binance::Dispatched::Book(update))) =
+                    binance::Feed::dispatch(&mut config, Message::Text(line.into()))?
+                else {
+                    continue;
+                };
+                if let (true, Some(time)) = (paced, update.time) {
+                    let (first, start) = *origin.get_or_insert((time, Instant::now()));
+                    sleep_until(start + Duration::from_millis(time.saturating_sub(first))).await;
+                }
+                binance::Feed::send(&mut config, &symbol, update).await;
+            }
+            Ok(())
+        };
+        Self {
+            task: tokio::spawn(task),
+        }
+    }
+    /// waits until the whole recording is replayed or the feed fails
+    pub async fn finished(mut self) -> Result<(), feeds::Error> {
+        (&mut self.task)
+            .await
+            .map_err(|e| std::io::Error::new(std::io::ErrorKind::Other, e))?
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::Duration;
+
+    use futures_channel::mpsc::unbounded;
+    use tokio::time::Instant;
+
+    use crate::{
+        feeds::{binance::BookPeriod, replay::ReplayConfig, Error},
+        symbol::Symbol,
+    };
+
+    fn record(name: &str, frames: &[&str]) -> std::path::PathBuf {
+        let path =
+            std::env::temp_dir().join(format!("keyrock-{}-{name}.jsonl", std::process::id()));
+        std::fs::write(&path, frames.join("\n")).unwrap();
+        path
+    }
+
+    const FRAMES: [&str; 3] = [
+        r#"{"e":"depthUpdate","E":1000,"s":"BNBBTC","U":1,"u":3,"b":[["2","1"]],"a":[["3","4"]]}"#,
+        r#"{"e":"depthUpdate","E":1200,"s":"BNBBTC","U":4,"u":4,"b":[["2","0"]],"a":[]}"#,
+        r#"{"e":"depthUpdate","E":1300,"s":"BNBBTC","U":3,"u":4,"b":[],"a":[]}"#,
+    ];
+
+    #[tokio::test]
+    async fn replay() {
+        let path = record("replay", &FRAMES);
+        let (tx, mut rx) = unbounded();
+        let feed = ReplayConfig::from_path(&path)
+            .subscribe_order_book(tx, Symbol::new("BNB", "BTC"), BookPeriod::Normal, None)
+            .connect()
+            .await
+            .unwrap();
+        feed.finished().await.unwrap();
+        std::fs::remove_file(path).unwrap();
+
+        // the last frame is already applied
+        let updates: Vec<_> = std::iter::from_fn(|| rx.try_next().ok().flatten()).collect();
+        assert_eq!(updates.len(), 2);
+        assert_eq!(updates[0].time, Some(1000));
+        assert_eq!(updates[1].seq, 4);
+        assert!(updates[1].bids.orders().is_empty());
+    }
+
+    #[tokio::test]
+    async fn paced() {
+        let path = record("paced", &FRAMES[..2]);
+        let (tx, mut rx) = unbounded();
+        let feed = ReplayConfig::from_path(&path)
+            .subscribe_order_book(tx, Symbol::new("BNB", "BTC"), BookPeriod::Normal, None)
+            .paced()
+            .connect()
+            .await
+            .unwrap();
+        let start = Instant::now();
+        feed.finished().await.unwrap();
+        std::fs::remove_file(path).unwrap();
+        assert!(start.elapsed() >= Duration::from_millis(200));
+        assert_eq!(
+            std::iter::from_fn(|| rx.try_next().ok().flatten()).count(),
+            2
+        );
+    }
+
+    #[tokio::test]
+    async fn unsubscribed() {
+        let path = record(
+            "unsubscribed",
+            &[
+                r#"{"e":"depthUpdate","E":900,"s":"ETHBTC","U":1,"u":2,"b":[["5","1"]],"a":[]}"#,
+                FRAMES[0],
+                r#"{"stream":"ethbtc@depth5","data":{"lastUpdateId":5,"bids":[],"asks":[]}}"#,
+                FRAMES[1],
+            ],
+        );
+        let (tx, mut rx) = unbounded();
+        let feed = ReplayConfig::from_path(&path)
+            .subscribe_order_book(tx, Symbol::new("BNB", "BTC"), BookPeriod::Normal, None)
+            .connect()
+            .await
+            .unwrap();
+        feed.finished().await.unwrap();
+        std::fs::remove_file(path).unwrap();
+
+        let updates: Vec<_> = std::iter::from_fn(|| rx.try_next().ok().flatten()).collect();
+        assert_eq!(updates.len(), 2);
+        assert_eq!(updates[0].time, Some(1000));
+    }
+
+    #[tokio::test]
+    async fn gap() {
+        let path = record(
+            "gap",
+            &[
+                FRAMES[0],
+                r#"{"e":"depthUpdate","E":1200,"s":"BNBBTC","U":6,"u":7,"b":[],"a":[]}"#,
+            ],
+        );
+        let (tx, _rx) = unbounded();
+        let feed = ReplayConfig::from_path(&path)
+            .subscribe_order_book(tx, Symbol::new("BNB", "BTC"), BookPeriod::Normal, None)
+            .connect()
+            .await
+            .unwrap();
+        let result = feed.finished().await;
+        std::fs::remove_file(path).unwrap();
+        assert!(matches!(
+            result,
+            Err(Error::Gap {
+                expected: 4,
+                got: 6
+            })
+        ));
+
+        assert!(matches!(
+            ReplayConfig::from_path("/nonexistent/recording.jsonl")
+                .connect()
+                .await,
+            Err(Error::IO(_))
+        ));
+    }
+}
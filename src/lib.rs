@@ -4,11 +4,13 @@
 #![feature(iter_collect_into)]
 #![feature(is_sorted)]
 #![feature(associated_type_bounds)]
-#![feature(inherent_associated_types)]
 #![feature(const_type_id)]
 #![feature(async_closure)]
-#![feature(async_fn_in_trait)]
 #![feature(iter_intersperse)]
 
+pub mod aggregator;
 pub mod core;
 pub mod feeds;
+pub mod symbol;
+#[cfg(test)]
+pub(crate) mod testing;
@@ -0,0 +1,157 @@
+use std::fmt::Display;
+
+/// Quote assets recognized when splitting concatenated symbols, longer ones first
+const QUOTES: &[&str] = &[
+    "USDT", "BUSD", "USDC", "TUSD", "USD", "EUR", "GBP", "BTC", "ETH", "BNB",
+];
+
+/// Kraken specific asset codes and their common names
+const KRAKEN_ASSETS: &[(&str, &str)] = &[("XBT", "BTC"), ("XDG", "DOGE")];
+
+/// Instrument normalized across exchanges, assets are upper case common codes e.g. BTC/USD
+#[derive(Debug, Clone, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub struct Symbol {
+    pub base: String,
+    pub quote: String,
+}
+
+impl Display for Symbol {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}/{}", self.base, self.quote)
+    }
+}
+
+impl Symbol {
+    pub fn new(base: &str, quote: &str) -> Self {
+        Self {
+            base: base.to_uppercase(),
+            quote: quote.to_uppercase(),
+        }
+    }
+    /// splits symbols without separator by known quote assets
+    /// fails with the symbol if none of them matches
+    fn from_concatenated(symbol: &str) -> Result<Self, String> {
+        let upper = symbol.to_uppercase();
+        QUOTES
+            .iter()
+            .find_map(|quote| {
+                upper
+                    .strip_suffix(quote)
+                    .filter(|base| !base.is_empty())
+                    .map(|base| Self::new(base, quote))
+            })
+            .ok_or_else(|| symbol.to_owned())
+    }
+    fn split(symbol: &str, separator: char) -> Result<Self, String> {
+        match symbol.split_once(separator) {
+            Some((base, quote)) if !base.is_empty() && !quote.is_empty() => {
+                Ok(Self::new(base, quote))
+            }
+            _ => Err(symbol.to_owned()),
+        }
+    }
+    /// e.g. btcusdt
+    pub fn to_binance(&self) -> String {
+        format!("{}{}", self.base, self.quote).to_lowercase()
+    }
+    pub fn from_binance(symbol: &str) -> Result<Self, String> {
+        Self::from_concatenated(symbol)
+    }
+    /// e.g. btcusd
+    pub fn to_bitstamp(&self) -> String {
+        format!("{}{}", self.base, self.quote).to_lowercase()
+    }
+    pub fn from_bitstamp(symbol: &str) -> Result<Self, String> {
+        Self::from_concatenated(symbol)
+    }
+    /// e.g. XBT/USD
+    pub fn to_kraken(&self) -> String {
+        let asset = |asset: &str| {
+            KRAKEN_ASSETS
+                .iter()
+                .find(|(_, common)| *common == asset)
+                .map_or(asset.to_owned(), |(kraken, _)| kraken.to_string())
+        };
+        format!("{}/{}", asset(&self.base), asset(&self.quote))
+    }
+    pub fn from_kraken(symbol: &str) -> Result<Self, String> {
+        let symbol = Self::split(symbol, '/')?;
+        let asset = |asset: String| {
+            KRAKEN_ASSETS
+                .iter()
+                .find(|(kraken, _)| *kraken == asset)
+                .map_or(asset.clone(), |(_, common)| common.to_string())
+        };
+        Ok(Self {
+            base: asset(symbol.base),
+            quote: asset(symbol.quote),
+        })
+    }
+    /// e.g. BTC-USD
+    pub fn to_coinbase(&self) -> String {
+        format!("{}-{}", self.base, self.quote)
+    }
+    pub fn from_coinbase(symbol: &str) -> Result<Self, String> {
+        Self::split(symbol, '-')
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn binance() {
+        let symbol = Symbol::new("btc", "usdt");
+        assert_eq!(symbol.to_binance(), "btcusdt");
+        assert_eq!(Symbol::from_binance("btcusdt"), Ok(symbol.clone()));
+        assert_eq!(Symbol::from_binance("BTCUSDT"), Ok(symbol));
+        assert_eq!(
+            Symbol::from_binance("bnbbtc"),
+            Ok(Symbol::new("BNB", "BTC"))
+        );
+        assert!(Symbol::from_binance("usdt").is_err());
+        assert!(Symbol::from_binance("btcxyz").is_err());
+    }
+
+    #[test]
+    fn bitstamp() {
+        let symbol = Symbol::new("ETH", "USD");
+        assert_eq!(symbol.to_bitstamp(), "ethusd");
+        assert_eq!(Symbol::from_bitstamp("ethusd"), Ok(symbol));
+        assert_eq!(
+            Symbol::from_bitstamp("ethbtc"),
+            Ok(Symbol::new("ETH", "BTC"))
+        );
+    }
+
+    #[test]
+    fn kraken() {
+        let symbol = Symbol::new("BTC", "USD");
+        assert_eq!(symbol.to_kraken(), "XBT/USD");
+        assert_eq!(Symbol::from_kraken("XBT/USD"), Ok(symbol));
+        assert_eq!(Symbol::new("ETH", "EUR").to_kraken(), "ETH/EUR");
+        assert_eq!(
+            Symbol::from_kraken("XDG/XBT"),
+            Ok(Symbol::new("DOGE", "BTC"))
+        );
+        assert!(Symbol::from_kraken("XBTUSD").is_err());
+        assert!(Symbol::from_kraken("/USD").is_err());
+    }
+
+    #[test]
+    fn coinbase() {
+        let symbol = Symbol::new("BTC", "USD");
+        assert_eq!(symbol.to_coinbase(), "BTC-USD");
+        assert_eq!(Symbol::from_coinbase("BTC-USD"), Ok(symbol.clone()));
+        assert!(Symbol::from_coinbase("BTCUSD").is_err());
+
+        // the same instrument across exchanges
+        assert_eq!(Symbol::from_kraken(&symbol.to_kraken()), Ok(symbol.clone()));
+        assert_eq!(
+            Symbol::from_bitstamp(&symbol.to_bitstamp()),
+            Ok(symbol.clone())
+        );
+        assert_eq!(symbol.to_string(), "BTC/USD");
+    }
+}
@@ -0,0 +1,192 @@
+use std::{pin::Pin, time::Duration};
+
+use futures_channel::mpsc::{unbounded, UnboundedSender};
+use futures_util::{Stream, StreamExt};
+use strum::IntoEnumIterator;
+
+use crate::{
+    aggregator::aggregate,
+    core::{
+        n, summary::Summary, Amount, Exchange, Order, OrderBook, OrderBookAsks, OrderBookBids,
+        Price,
+    },
+};
+
+/// Aggregator fed by mock feeds of every exchange
+pub struct Harness {
+    feeds: Vec<(Exchange, UnboundedSender<(OrderBookBids, OrderBookAsks)>)>,
+    summaries: Pin<Box<dyn Stream<Item = Summary>>>,
+}
+
+impl Harness {
+    pub const SYMBOL: &str = "ethbtc";
+
+    pub fn new() -> Self {
+        let (feeds, rxs): (Vec<_>, Vec<_>) = Exchange::iter()
+            .map(|exchange| {
+                let (tx, rx) = unbounded();
+                ((exchange, tx), (exchange, rx))
+            })
+            .unzip();
+        Self {
+            feeds,
+            summaries: Box::pin(aggregate(Self::SYMBOL, rxs)),
+        }
+    }
+    pub fn feed(&self, exchange: Exchange, bids: OrderBookBids, asks: OrderBookAsks) {
+        let (_, tx) = self
+            .feeds
+            .iter()
+            .find(|(e, _)| *e == exchange)
+            .expect("mock feed for every exchange");
+        tx.unbounded_send((bids, asks)).expect("aggregator alive");
+    }
+    /// panics if the aggregator emits a summary within 100ms
+    pub async fn assert_no_summary(&mut self) {
+        let summary = tokio::time::timeout(Duration::from_millis(100), self.summaries.next()).await;
+        assert!(summary.is_err(), "unexpected summary {summary:?}");
+    }
+    /// panics unless the aggregator emits a summary within a second
+    pub async fn await_summary(&mut self) -> Summary {
+        tokio::time::timeout(Duration::from_secs(1), self.summaries.next())
+            .await
+            .expect("summary in time")
+            .expect("aggregator alive")
+    }
+}
+
+/// book out of (price, amount) levels, panics if they are not valid
+pub fn book<const QUOTE: bool, const COUNT: usize>(
+    levels: &[(f64, f64)],
+) -> OrderBook<QUOTE, COUNT> {
+    let orders = levels
+        .iter()
+        .map(|&(price, amount)| {
+            Order::new(
+                Price::new(n(price)).unwrap(),
+                Amount::new(n(amount)).unwrap(),
+            )
+        })
+        .collect();
+    OrderBook::new(orders).unwrap()
+}
+
+#[tokio::test]
+async fn reset_summary() {
+    use crate::core::summary::Level;
+
+    let level = |exchange, price, amount| Level {
+        exchange,
+        price,
+        amount,
+    };
+    let mut harness = Harness::new();
+
+    harness.feed(
+        Exchange::Binance,
+        book(&[(1.1, 0.1), (1.0, 0.1)]),
+        book(&[(0.8, 0.1), (0.9, 0.1)]),
+    );
+    let summary = harness.await_summary().await;
+    assert_eq!(summary.symbol, Harness::SYMBOL);
+    assert_eq!(
+        summary.bids,
+        vec![
+            level(Exchange::Binance, 1.1, 0.1),
+            level(Exchange::Binance, 1.0, 0.1)
+        ]
+    );
+    assert_eq!(
+        summary.asks,
+        vec![
+            level(Exchange::Binance, 0.8, 0.1),
+            level(Exchange::Binance, 0.9, 0.1)
+        ]
+    );
+
+    harness.feed(
+        Exchange::Bitstamp,
+        book(&[(2.1, 1.1), (1.0, 1.1)]),
+        book(&[(0.9, 2.1), (1.9, 2.1)]),
+    );
+    let summary = harness.await_summary().await;
+    assert_eq!(
+        summary.bids,
+        vec![
+            level(Exchange::Bitstamp, 2.1, 1.1),
+            level(Exchange::Binance, 1.1, 0.1),
+            level(Exchange::Bitstamp, 1.0, 1.1),
+            level(Exchange::Binance, 1.0, 0.1),
+        ]
+    );
+    assert_eq!(
+        summary.asks,
+        vec![
+            level(Exchange::Binance, 0.8, 0.1),
+            level(Exchange::Bitstamp, 0.9, 2.1),
+            level(Exchange::Binance, 0.9, 0.1),
+            level(Exchange::Bitstamp, 1.9, 2.1),
+        ]
+    );
+
+    harness.feed(Exchange::Binance, book(&[]), book(&[]));
+    let summary = harness.await_summary().await;
+    assert_eq!(summary.bids.len(), 2);
+    assert!(summary
+        .bids
+        .iter()
+        .chain(summary.asks.iter())
+        .all(|level| level.exchange == Exchange::Bitstamp));
+}
+
+#[tokio::test]
+async fn unchanged_summary() {
+    use crate::core::BEST_ORDER_BOOK_SIZE;
+
+    let mut harness = Harness::new();
+    let levels = |deepest| {
+        (1..=BEST_ORDER_BOOK_SIZE)
+            .map(|level| (level as f64, 1.0))
+            .chain([(0.5, deepest)])
+            .collect::<Vec<_>>()
+    };
+    harness.feed(Exchange::Binance, book(&levels(1.0)), book(&[(20.0, 1.0)]));
+    let summary = harness.await_summary().await;
+    assert_eq!(summary.bids.len(), BEST_ORDER_BOOK_SIZE);
+
+    // the deepest bid isn't published
+    harness.feed(Exchange::Binance, book(&levels(2.0)), book(&[(20.0, 1.0)]));
+    harness.assert_no_summary().await;
+
+    harness.feed(Exchange::Binance, book(&levels(2.0)), book(&[(20.0, 2.0)]));
+    assert_ne!(harness.await_summary().await, summary);
+}
+
+#[tokio::test]
+async fn interleaved_spread() {
+    let mut harness = Harness::new();
+
+    harness.feed(Exchange::Binance, book(&[(1.0, 1.0)]), book(&[]));
+    let summary = harness.await_summary().await;
+    assert_eq!(summary.spread, None);
+
+    harness.feed(Exchange::Kraken, book(&[]), book(&[(1.5, 1.0)]));
+    assert_eq!(harness.await_summary().await.spread, Some(-0.5));
+
+    // the best bid moves to another exchange, the best ask stays
+    harness.feed(
+        Exchange::Coinbase,
+        book(&[(1.25, 1.0)]),
+        book(&[(2.0, 1.0)]),
+    );
+    assert_eq!(harness.await_summary().await.spread, Some(-0.25));
+
+    // the best ask moves while bids stay
+    harness.feed(Exchange::Kraken, book(&[]), book(&[(1.75, 1.0)]));
+    assert_eq!(harness.await_summary().await.spread, Some(-0.5));
+
+    harness.feed(Exchange::Binance, book(&[(1.75, 1.0)]), book(&[]));
+    let summary = harness.await_summary().await;
+    assert_eq!(summary.spread, Some(0.0));
+    assert!(summary.locked);
+}